@@ -0,0 +1,83 @@
+//! Avatar appearance rules (ADR-020). The sim checks only that an appearance is well-formed: model
+//! ids name entries of each client's own avatar catalog, and a client shows the default model for
+//! ids it does not have.
+
+use crate::protocol::AvatarAppearance;
+
+/// Model every client has; also what unknown models fall back to.
+pub const DEFAULT_AVATAR_MODEL: &str = "fox";
+/// Longest accepted model id.
+pub const MAX_MODEL_ID_LEN: usize = 32;
+/// Accepted range of [`AvatarAppearance::scale`]; keeps avatars within doors and out of the sky.
+pub const MIN_AVATAR_SCALE: f32 = 0.5;
+pub const MAX_AVATAR_SCALE: f32 = 2.0;
+
+impl Default for AvatarAppearance {
+    fn default() -> Self {
+        Self {
+            model: DEFAULT_AVATAR_MODEL.into(),
+            tint: [1.0; 3],
+            scale: 1.0,
+        }
+    }
+}
+
+/// Model ids are 1..=[`MAX_MODEL_ID_LEN`] of `a-z`, `0-9`, `-` and `_`; tint channels are within
+/// 0..=1 and the scale within [`MIN_AVATAR_SCALE`]..=[`MAX_AVATAR_SCALE`]. `Err` is user-facing.
+pub fn validate_appearance(appearance: &AvatarAppearance) -> Result<(), String> {
+    let model = &appearance.model;
+    let id_char = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_';
+    if model.is_empty() || model.len() > MAX_MODEL_ID_LEN || !model.chars().all(id_char) {
+        return Err(format!(
+            "avatar model id must be 1..={MAX_MODEL_ID_LEN} of a-z, 0-9, '-' and '_'"
+        ));
+    }
+    if !appearance.tint.iter().all(|c| (0.0..=1.0).contains(c)) {
+        return Err("avatar tint channels must be within 0..=1".into());
+    }
+    if !(MIN_AVATAR_SCALE..=MAX_AVATAR_SCALE).contains(&appearance.scale) {
+        return Err(format!(
+            "avatar scale must be within {MIN_AVATAR_SCALE}..={MAX_AVATAR_SCALE}"
+        ));
+    }
+    Ok(())
+}
+
+/// Tint from a `RRGGBB` hex color (with or without `#`), as 0..=1 channels.
+pub fn parse_tint(hex: &str) -> Result<[f32; 3], String> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    let bad = || format!("tint {hex:?} is not an RRGGBB hex color");
+    if digits.len() != 6 || !digits.is_ascii() {
+        return Err(bad());
+    }
+    let mut tint = [0.0; 3];
+    for (i, c) in tint.iter_mut().enumerate() {
+        let v = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16).map_err(|_| bad())?;
+        *c = f32::from(v) / 255.0;
+    }
+    Ok(tint)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn appearances_are_bounded() {
+        assert_eq!(validate_appearance(&AvatarAppearance::default()), Ok(()));
+        let with = |f: fn(&mut AvatarAppearance)| {
+            let mut a = AvatarAppearance::default();
+            f(&mut a);
+            validate_appearance(&a)
+        };
+        assert!(with(|a| a.model = "Fox!".into()).is_err());
+        assert!(with(|a| a.model.clear()).is_err());
+        assert!(with(|a| a.tint[1] = 1.5).is_err());
+        assert!(with(|a| a.tint[0] = f32::NAN).is_err());
+        assert!(with(|a| a.scale = 3.0).is_err());
+        assert!(with(|a| a.scale = f32::NAN).is_err());
+        assert_eq!(parse_tint("#ff8000"), Ok([1.0, 128.0 / 255.0, 0.0]));
+        assert!(parse_tint("ff80").is_err());
+        assert!(parse_tint("gg0000").is_err());
+    }
+}
@@ -0,0 +1,38 @@
+//! Account login (ADR-009 hello, protocol v10): an account is an Ed25519 keypair. The client names
+//! its public key in [`crate::NetMessage::ClientHello`], the sim answers with a random nonce in
+//! [`crate::NetMessage::AuthChallenge`], and the client proves ownership by signing
+//! [`challenge_payload`] in [`crate::NetMessage::AuthResponse`]. No secret ever crosses the wire.
+
+/// First protocol version with login; older sessions join as guests ([`GUEST_USER_ID`]).
+pub const LOGIN_PROTOCOL_VERSION: u16 = 10;
+/// `user_id` of a session that could not log in: no account, a visitor in every region.
+pub const GUEST_USER_ID: i64 = 0;
+/// Ed25519 public key length.
+pub const PUBLIC_KEY_LEN: usize = 32;
+/// Length of the sim's challenge nonce.
+pub const NONCE_LEN: usize = 32;
+/// Domain separation, so a login signature can never be replayed as any other signed message.
+const CONTEXT: &[u8] = b"vibers-login-v1\0";
+
+/// Bytes the client signs to answer `nonce` for `public_key`.
+#[must_use]
+pub fn challenge_payload(nonce: &[u8], public_key: &[u8]) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(CONTEXT.len() + nonce.len() + public_key.len());
+    payload.extend_from_slice(CONTEXT);
+    payload.extend_from_slice(nonce);
+    payload.extend_from_slice(public_key);
+    payload
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payload_binds_nonce_and_key() {
+        let p = challenge_payload(&[1; NONCE_LEN], &[2; PUBLIC_KEY_LEN]);
+        assert!(p.starts_with(CONTEXT));
+        assert_ne!(p, challenge_payload(&[1; NONCE_LEN], &[3; PUBLIC_KEY_LEN]));
+        assert_ne!(p, challenge_payload(&[4; NONCE_LEN], &[2; PUBLIC_KEY_LEN]));
+    }
+}
@@ -0,0 +1,37 @@
+//! Chat rules (ADR-018): what a chat line may contain and how far local chat carries. The sim
+//! enforces them; clients can check before sending.
+
+/// Longest accepted line, in characters, after trimming.
+pub const MAX_CHAT_LEN: usize = 512;
+/// Distance (metres) from the speaker within which [`crate::ChatScope::Local`] is heard.
+pub const LOCAL_CHAT_RADIUS: f32 = 20.0;
+
+/// The line as it will be sent: trimmed, non-empty, at most [`MAX_CHAT_LEN`] characters and free
+/// of control characters (so one line cannot fake several in a log). `Err` is user-facing.
+pub fn validate_chat(text: &str) -> Result<&str, String> {
+    let text = text.trim();
+    if text.is_empty() {
+        return Err("message is empty".into());
+    }
+    if text.chars().count() > MAX_CHAT_LEN {
+        return Err(format!("message is longer than {MAX_CHAT_LEN} characters"));
+    }
+    if text.chars().any(char::is_control) {
+        return Err("message contains control characters".into());
+    }
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chat_lines_are_trimmed_and_bounded() {
+        assert_eq!(validate_chat("  hi there \n"), Ok("hi there"));
+        assert!(validate_chat(" \t ").is_err());
+        assert!(validate_chat("one\ntwo").is_err());
+        assert!(validate_chat(&"é".repeat(MAX_CHAT_LEN)).is_ok());
+        assert!(validate_chat(&"a".repeat(MAX_CHAT_LEN + 1)).is_err());
+    }
+}
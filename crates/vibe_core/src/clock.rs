@@ -0,0 +1,77 @@
+//! Heartbeat timing (ADR-008): wall-clock timestamps for `Ping` / `Pong` and a smoothed
+//! round-trip / clock-offset estimate built from them.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Weight of each new sample in the smoothed estimates (TCP's SRTT uses the same 1/8).
+const SMOOTHING: f64 = 0.125;
+
+/// Microseconds since the Unix epoch on this machine's wall clock.
+#[must_use]
+pub fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_micros() as u64)
+}
+
+/// Smoothed RTT and peer-clock offset from `Ping` → `Pong` exchanges.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ClockSync {
+    /// Smoothed round-trip time in seconds; `None` until the first sample.
+    pub rtt: Option<f64>,
+    /// Smoothed `peer clock − local clock` in seconds (assumes symmetric paths).
+    pub offset: f64,
+    pub samples: u64,
+}
+
+impl ClockSync {
+    /// Fold in one exchange: `sent` and `received` on the local clock, `peer` when the peer replied.
+    pub fn observe(&mut self, sent: u64, peer: u64, received: u64) {
+        let rtt = received.saturating_sub(sent) as f64 / 1e6;
+        let midpoint = sent as f64 / 1e6 + rtt / 2.0;
+        let offset = peer as f64 / 1e6 - midpoint;
+        match self.rtt {
+            None => {
+                self.rtt = Some(rtt);
+                self.offset = offset;
+            }
+            Some(srtt) => {
+                self.rtt = Some(srtt + SMOOTHING * (rtt - srtt));
+                self.offset += SMOOTHING * (offset - self.offset);
+            }
+        }
+        self.samples += 1;
+    }
+
+    /// Local wall clock expressed on the peer's clock, in microseconds.
+    #[must_use]
+    pub fn peer_now_micros(&self) -> u64 {
+        (now_micros() as f64 + self.offset * 1e6).max(0.0) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn first_sample_sets_rtt_and_offset() {
+        let mut c = ClockSync::default();
+        // Sent at 10 s, peer stamped 15.05 s, back at 10.1 s: RTT 100 ms, peer 5 s ahead.
+        c.observe(10_000_000, 15_050_000, 10_100_000);
+        assert_relative_eq!(c.rtt.unwrap(), 0.1, epsilon = 1e-9);
+        assert_relative_eq!(c.offset, 5.0, epsilon = 1e-9);
+    }
+
+    #[test]
+    fn later_samples_are_smoothed() {
+        let mut c = ClockSync::default();
+        c.observe(0, 50_000, 100_000);
+        c.observe(1_000_000, 1_150_000, 1_300_000);
+        // 0.1 + (0.3 − 0.1) / 8
+        assert_relative_eq!(c.rtt.unwrap(), 0.125, epsilon = 1e-9);
+        assert_relative_eq!(c.offset, 0.0, epsilon = 1e-9);
+        assert_eq!(c.samples, 2);
+    }
+}
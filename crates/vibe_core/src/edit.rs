@@ -0,0 +1,219 @@
+//! Prim edit rules (ADR-011 write path): what a [`PrimEdit`] does to a prim and which prims are
+//! acceptable. The sim enforces them; clients can check before sending.
+
+use glam::Vec3;
+use std::ops::RangeInclusive;
+
+use crate::protocol::{PrimDto, PrimEdit};
+use crate::world::REGION_SIZE_METERS;
+
+/// Shapes the client can render; anything else is refused.
+pub const PRIM_SHAPES: [&str; 5] = ["box", "sphere", "cylinder", "cone", "torus"];
+pub const MAX_PRIM_NAME_LEN: usize = 64;
+/// Per-axis scale bounds.
+pub const PRIM_SCALE_RANGE: RangeInclusive<f32> = 0.05..=64.0;
+/// Sim-space height a prim's centre may be placed at.
+pub const PRIM_HEIGHT_RANGE: RangeInclusive<f32> = -32.0..=512.0;
+
+impl PrimEdit {
+    /// Prim the edit targets; `None` for [`PrimEdit::Create`].
+    #[must_use]
+    pub fn prim_id(&self) -> Option<i64> {
+        match self {
+            Self::Create(_) => None,
+            Self::Transform { id, .. }
+            | Self::Recolor { id, .. }
+            | Self::Rename { id, .. }
+            | Self::Delete { id } => Some(*id),
+        }
+    }
+
+    /// `prim` with the edit applied; `None` once deleted. `Create` yields its own prim.
+    #[must_use]
+    pub fn applied_to(&self, prim: &PrimDto) -> Option<PrimDto> {
+        let mut out = prim.clone();
+        match self {
+            Self::Create(new) => return Some(new.clone()),
+            Self::Transform {
+                position,
+                rotation,
+                scale,
+                ..
+            } => {
+                out.position = *position;
+                out.rotation = *rotation;
+                out.scale = *scale;
+            }
+            Self::Recolor { color, .. } => out.color = *color,
+            Self::Rename { name, .. } => out.name.clone_from(name),
+            Self::Delete { .. } => return None,
+        }
+        Some(out)
+    }
+}
+
+/// Edits that turn `from` into `to` (`None`: no such prim), e.g. to undo one. Shape and region
+/// cannot be edited, so they are left as `from` has them.
+#[must_use]
+pub fn edits_between(from: Option<&PrimDto>, to: Option<&PrimDto>) -> Vec<PrimEdit> {
+    let (from, to) = match (from, to) {
+        (Some(from), Some(to)) => (from, to),
+        (None, Some(to)) => return vec![PrimEdit::Create(to.clone())],
+        (Some(from), None) => return vec![PrimEdit::Delete { id: from.id }],
+        (None, None) => return Vec::new(),
+    };
+    let id = from.id;
+    let mut edits = Vec::new();
+    if (from.position, from.rotation, from.scale) != (to.position, to.rotation, to.scale) {
+        edits.push(PrimEdit::Transform {
+            id,
+            position: to.position,
+            rotation: to.rotation,
+            scale: to.scale,
+        });
+    }
+    if from.color != to.color {
+        edits.push(PrimEdit::Recolor {
+            id,
+            color: to.color,
+        });
+    }
+    if from.name != to.name {
+        edits.push(PrimEdit::Rename {
+            id,
+            name: to.name.clone(),
+        });
+    }
+    edits
+}
+
+/// Whether `prim` may exist in the region whose sim origin is `region_origin`: known shape, sane
+/// name, finite transform inside the region's square, bounded scale and an sRGB color in `0..=1`.
+pub fn validate_prim(prim: &PrimDto, region_origin: Vec3) -> Result<(), String> {
+    let name = prim.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_PRIM_NAME_LEN {
+        return Err(format!("name must be 1..={MAX_PRIM_NAME_LEN} characters"));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("name contains control characters".into());
+    }
+    if !PRIM_SHAPES.contains(&prim.shape.as_str()) {
+        return Err(format!("unknown shape {:?}", prim.shape));
+    }
+    if !(prim.position.is_finite() && prim.rotation.is_finite() && prim.scale.is_finite()) {
+        return Err("transform is not finite".into());
+    }
+    let half = REGION_SIZE_METERS as f32 / 2.0;
+    let offset = prim.position - region_origin;
+    if offset.x.abs() > half || offset.z.abs() > half {
+        return Err(format!("position is outside region {}", prim.region_id));
+    }
+    if !PRIM_HEIGHT_RANGE.contains(&prim.position.y) {
+        return Err(format!("height must be within {PRIM_HEIGHT_RANGE:?}"));
+    }
+    let scale = prim.scale.to_array();
+    if !scale.iter().all(|s| PRIM_SCALE_RANGE.contains(s)) {
+        return Err(format!("scale must be within {PRIM_SCALE_RANGE:?}"));
+    }
+    if !prim.color.iter().all(|c| (0.0..=1.0).contains(c)) {
+        return Err("color components must be within 0..=1".into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prim() -> PrimDto {
+        PrimDto {
+            id: 4,
+            region_id: 1,
+            name: "Bench".into(),
+            shape: "box".into(),
+            position: Vec3::new(10.0, 1.0, -20.0),
+            rotation: Vec3::ZERO,
+            scale: Vec3::ONE,
+            color: [0.5, 0.4, 0.3],
+        }
+    }
+
+    #[test]
+    fn edits_change_only_their_fields() {
+        let p = prim();
+        let moved = PrimEdit::Transform {
+            id: 4,
+            position: Vec3::Y,
+            rotation: Vec3::X,
+            scale: Vec3::splat(2.0),
+        }
+        .applied_to(&p)
+        .unwrap();
+        assert_eq!((moved.position, moved.scale), (Vec3::Y, Vec3::splat(2.0)));
+        assert_eq!((moved.name.as_str(), moved.color), ("Bench", p.color));
+
+        let renamed = PrimEdit::Rename {
+            id: 4,
+            name: "Table".into(),
+        }
+        .applied_to(&p)
+        .unwrap();
+        assert_eq!(renamed.name, "Table");
+        assert_eq!(renamed.position, p.position);
+        assert_eq!(PrimEdit::Delete { id: 4 }.applied_to(&p), None);
+    }
+
+    #[test]
+    fn edits_between_reproduce_the_target() {
+        let from = prim();
+        let to = PrimDto {
+            name: "Table".into(),
+            position: Vec3::new(12.0, 1.0, -20.0),
+            color: [1.0, 0.0, 0.0],
+            ..from.clone()
+        };
+        let edits = edits_between(Some(&from), Some(&to));
+        assert_eq!(edits.len(), 3);
+        let applied = edits.iter().try_fold(from.clone(), |p, e| e.applied_to(&p));
+        assert_eq!(applied, Some(to));
+
+        assert!(edits_between(Some(&from), Some(&from)).is_empty());
+        assert_eq!(
+            edits_between(None, Some(&from)),
+            vec![PrimEdit::Create(from.clone())]
+        );
+        assert_eq!(
+            edits_between(Some(&from), None),
+            vec![PrimEdit::Delete { id: 4 }]
+        );
+    }
+
+    #[test]
+    fn validation_bounds_prims_to_their_region() {
+        let origin = Vec3::new(300.0, 0.0, 0.0);
+        let mut p = prim();
+        p.position += origin;
+        assert_eq!(validate_prim(&p, origin), Ok(()));
+
+        let outside = PrimDto {
+            position: origin + Vec3::new(200.0, 0.0, 0.0),
+            ..p.clone()
+        };
+        assert!(validate_prim(&outside, origin).is_err());
+        let flat = PrimDto {
+            scale: Vec3::new(1.0, 0.0, 1.0),
+            ..p.clone()
+        };
+        assert!(validate_prim(&flat, origin).is_err());
+        let odd = PrimDto {
+            shape: "teapot".into(),
+            ..p.clone()
+        };
+        assert!(validate_prim(&odd, origin).is_err());
+        let unnamed = PrimDto {
+            name: "  ".into(),
+            ..p
+        };
+        assert!(validate_prim(&unnamed, origin).is_err());
+    }
+}
@@ -6,6 +6,8 @@ pub enum ProtocolError {
     Encode(postcard::Error),
     #[error("unsupported protocol version {0}")]
     UnsupportedVersion(u16),
+    #[error("message kind {kind} has no encoding in protocol version {version}")]
+    NotInVersion { kind: u16, version: u16 },
     #[error("expected ClientHello first, got {0:?}")]
     ExpectedHello(Box<str>),
     #[error("session not authenticated")]
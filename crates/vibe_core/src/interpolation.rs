@@ -0,0 +1,235 @@
+//! Remote avatar playback (ADR-010/011): replicated states are buffered by sim tick and rendered a
+//! fixed delay behind the estimated current tick, so tick jitter and packet bunching are absorbed
+//! instead of showing up as rubber-banding. When the buffer runs dry the last state is extrapolated
+//! from its velocity for a bounded time, then held.
+
+use std::collections::VecDeque;
+
+use glam::Vec3;
+
+use crate::protocol::{AvatarAnim, AvatarStateDto};
+use crate::yaw::wrap_angle_pi;
+
+/// How far behind the estimated sim tick remote avatars are shown.
+pub const INTERPOLATION_DELAY_SECS: f64 = 0.1;
+/// Longest a remote avatar keeps moving on its last velocity without new states.
+pub const MAX_EXTRAPOLATION_SECS: f64 = 0.25;
+/// States kept per avatar; at 20 Hz this is well beyond the interpolation delay.
+pub const MAX_BUFFERED_STATES: usize = 32;
+/// Tick estimate error past which [`TickClock`] jumps instead of drifting (sim restart, long stall).
+const RESYNC_TICKS: f64 = 10.0;
+/// Weight of each arrival in the smoothed tick offset.
+const SMOOTHING: f64 = 0.05;
+
+/// One replicated avatar state, without the id.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AvatarSample {
+    pub position: Vec3,
+    pub yaw: f32,
+    pub velocity: Vec3,
+    pub anim: AvatarAnim,
+}
+
+impl From<&AvatarStateDto> for AvatarSample {
+    fn from(a: &AvatarStateDto) -> Self {
+        Self {
+            position: a.position,
+            yaw: a.yaw,
+            velocity: a.velocity,
+            anim: a.anim,
+        }
+    }
+}
+
+/// Time-ordered states of one remote avatar, keyed by sim tick.
+#[derive(Debug, Clone, Default)]
+pub struct InterpolationBuffer {
+    states: VecDeque<(u64, AvatarSample)>,
+}
+
+impl InterpolationBuffer {
+    /// Add the full state at `tick`. The sim only sends changes, so a gap since the previous state
+    /// means the avatar held still: that state is repeated at `tick − 1` before the new one.
+    /// A state for an already buffered tick replaces it; older ticks are ignored.
+    pub fn push(&mut self, tick: u64, sample: AvatarSample) {
+        match self.states.back_mut() {
+            Some((last, s)) if *last == tick => {
+                *s = sample;
+                return;
+            }
+            Some((last, _)) if *last > tick => return,
+            Some(&mut (last, s)) if last + 1 < tick => self.states.push_back((tick - 1, s)),
+            _ => {}
+        }
+        self.states.push_back((tick, sample));
+        while self.states.len() > MAX_BUFFERED_STATES {
+            self.states.pop_front();
+        }
+    }
+
+    /// Newest buffered state.
+    #[must_use]
+    pub fn latest(&self) -> Option<&AvatarSample> {
+        self.states.back().map(|(_, s)| s)
+    }
+
+    /// State at fractional `tick`: interpolated between neighbours, clamped to the oldest state,
+    /// or extrapolated past the newest for at most [`MAX_EXTRAPOLATION_SECS`].
+    #[must_use]
+    pub fn sample(&self, tick: f64, tick_hz: f32) -> Option<AvatarSample> {
+        let &(first_tick, first) = self.states.front()?;
+        if tick <= first_tick as f64 {
+            return Some(first);
+        }
+        let next = self.states.iter().position(|(t, _)| *t as f64 > tick);
+        let Some(i) = next else {
+            let &(last_tick, last) = self.states.back()?;
+            let ahead = ((tick - last_tick as f64) / f64::from(tick_hz.max(1e-3)))
+                .min(MAX_EXTRAPOLATION_SECS);
+            return Some(AvatarSample {
+                position: last.position + last.velocity * ahead as f32,
+                ..last
+            });
+        };
+        let (t0, a) = self.states[i - 1];
+        let (t1, b) = self.states[i];
+        let f = ((tick - t0 as f64) / (t1 - t0) as f64) as f32;
+        Some(AvatarSample {
+            position: a.position.lerp(b.position, f),
+            yaw: a.yaw + wrap_angle_pi(b.yaw - a.yaw) * f,
+            velocity: a.velocity.lerp(b.velocity, f),
+            anim: if f < 0.5 { a.anim } else { b.anim },
+        })
+    }
+
+    /// Drop states no longer needed to sample at `tick` or later (keeps the one at or before it).
+    pub fn discard_before(&mut self, tick: f64) {
+        while self.states.len() >= 2 && self.states[1].0 as f64 <= tick {
+            self.states.pop_front();
+        }
+    }
+}
+
+/// Estimate of the sim's current (fractional) tick from message arrivals on the local clock.
+#[derive(Debug, Clone, Copy)]
+pub struct TickClock {
+    tick_hz: f32,
+    /// Smoothed `tick − local_secs · tick_hz`; `None` until the first observation.
+    offset: Option<f64>,
+}
+
+impl TickClock {
+    #[must_use]
+    pub fn new(tick_hz: f32) -> Self {
+        Self {
+            tick_hz: tick_hz.max(1e-3),
+            offset: None,
+        }
+    }
+
+    #[must_use]
+    pub fn tick_hz(&self) -> f32 {
+        self.tick_hz
+    }
+
+    /// A message for `tick` arrived at `local_secs`.
+    pub fn observe(&mut self, tick: u64, local_secs: f64) {
+        let sample = tick as f64 - local_secs * f64::from(self.tick_hz);
+        self.offset = Some(match self.offset {
+            Some(o) if (sample - o).abs() <= RESYNC_TICKS => o + SMOOTHING * (sample - o),
+            _ => sample,
+        });
+    }
+
+    /// Estimated sim tick at `local_secs`.
+    #[must_use]
+    pub fn now(&self, local_secs: f64) -> Option<f64> {
+        self.offset
+            .map(|o| local_secs * f64::from(self.tick_hz) + o)
+    }
+
+    /// Tick at which remote avatars are rendered: [`INTERPOLATION_DELAY_SECS`] behind [`Self::now`].
+    #[must_use]
+    pub fn render_tick(&self, local_secs: f64) -> Option<f64> {
+        self.now(local_secs)
+            .map(|t| t - INTERPOLATION_DELAY_SECS * f64::from(self.tick_hz))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn at(x: f32, vx: f32) -> AvatarSample {
+        AvatarSample {
+            position: Vec3::new(x, 0.0, 0.0),
+            yaw: 0.0,
+            velocity: Vec3::new(vx, 0.0, 0.0),
+            anim: AvatarAnim::Run,
+        }
+    }
+
+    #[test]
+    fn interpolates_between_ticks() {
+        let mut b = InterpolationBuffer::default();
+        b.push(10, at(0.0, 1.0));
+        b.push(11, at(1.0, 1.0));
+        assert_relative_eq!(b.sample(10.25, 20.0).unwrap().position.x, 0.25);
+    }
+
+    #[test]
+    fn gap_is_a_hold_not_a_slow_move() {
+        let mut b = InterpolationBuffer::default();
+        b.push(10, at(0.0, 0.0));
+        b.push(20, at(1.0, 20.0));
+        assert_relative_eq!(b.sample(15.0, 20.0).unwrap().position.x, 0.0);
+        assert_relative_eq!(b.sample(19.5, 20.0).unwrap().position.x, 0.5);
+    }
+
+    #[test]
+    fn extrapolation_is_bounded() {
+        let mut b = InterpolationBuffer::default();
+        b.push(10, at(0.0, 4.0));
+        // 2 ticks at 20 Hz = 0.1 s ahead.
+        assert_relative_eq!(
+            b.sample(12.0, 20.0).unwrap().position.x,
+            0.4,
+            epsilon = 1e-5
+        );
+        let far = b.sample(100.0, 20.0).unwrap().position.x;
+        assert_relative_eq!(far, 4.0 * MAX_EXTRAPOLATION_SECS as f32, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn yaw_takes_the_short_way_round() {
+        let mut b = InterpolationBuffer::default();
+        b.push(
+            1,
+            AvatarSample {
+                yaw: 3.0,
+                ..at(0.0, 0.0)
+            },
+        );
+        b.push(
+            2,
+            AvatarSample {
+                yaw: -3.0,
+                ..at(0.0, 0.0)
+            },
+        );
+        let yaw = b.sample(1.5, 20.0).unwrap().yaw;
+        assert!(wrap_angle_pi(yaw).abs() > 3.0);
+    }
+
+    #[test]
+    fn tick_clock_tracks_arrivals() {
+        let mut c = TickClock::new(20.0);
+        c.observe(100, 5.0);
+        assert_relative_eq!(c.now(5.5).unwrap(), 110.0);
+        assert_relative_eq!(c.render_tick(5.5).unwrap(), 108.0);
+        // A sim restart resynchronises at once.
+        c.observe(3, 6.0);
+        assert_relative_eq!(c.now(6.0).unwrap(), 3.0);
+    }
+}
@@ -0,0 +1,219 @@
+//! Avatar movement shared by the sim and the client's local prediction (ADR-010).
+//!
+//! The sim applies each [`NetMessage::ClientIntent`](crate::NetMessage::ClientIntent) with
+//! [`step_position`]; the client runs the same function ahead of the server and, when a snapshot
+//! echoes the last input the sim processed, replays the inputs still in flight ([`Predictor`]).
+//! Motion is piecewise linear with a ground clamp, so one long step equals many short ones.
+
+use std::collections::VecDeque;
+
+use glam::Vec3;
+
+pub const WALK_SPEED: f32 = 8.0;
+/// Horizontal and vertical speed while flying.
+pub const FLY_SPEED: f32 = 40.0;
+/// Constant fall speed when not flying (no acceleration, matches the original offline feel).
+pub const FALL_SPEED: f32 = 9.8;
+/// Height of the avatar origin above the ground plane.
+pub const GROUND_CLEARANCE: f32 = 0.45;
+/// Longest single input step; longer frames (hitches, sleeping laptops) are clamped.
+pub const MAX_INPUT_DT: f32 = 0.25;
+/// The client coalesces identical frames into one command of at most this many seconds.
+pub const INPUT_SEND_INTERVAL: f32 = 1.0 / 30.0;
+/// Unacknowledged commands kept for replay; beyond this the oldest are forgotten.
+pub const MAX_PENDING_INPUTS: usize = 256;
+/// First protocol version whose intents carry `input_seq`/`dt` and whose snapshots echo it.
+pub const PREDICTION_PROTOCOL_VERSION: u16 = 8;
+
+/// One sampled frame of movement input, in world space.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MoveInput {
+    /// Camera-relative wish direction on XZ, length ≤ 1.
+    pub move_x: f32,
+    pub move_z: f32,
+    /// World yaw the avatar faces (replicated to others).
+    pub display_yaw: f32,
+    pub fly_up: bool,
+    pub fly_down: bool,
+    /// Fly mode: no falling, vertical control, [`FLY_SPEED`] horizontally.
+    pub flying: bool,
+}
+
+/// Advance `position` by `input` held for `dt` seconds (clamped to [`MAX_INPUT_DT`]).
+#[must_use]
+pub fn step_position(position: Vec3, input: &MoveInput, dt: f32) -> Vec3 {
+    let dt = dt.clamp(0.0, MAX_INPUT_DT);
+    let mut wish = Vec3::new(input.move_x, 0.0, input.move_z);
+    if wish.length_squared() > 1.0 {
+        wish = wish.normalize();
+    }
+    let speed = if input.flying { FLY_SPEED } else { WALK_SPEED };
+    let mut p = position + wish * speed * dt;
+    if input.flying {
+        if input.fly_up {
+            p.y += FLY_SPEED * dt;
+        } else if input.fly_down {
+            p.y -= FLY_SPEED * dt;
+        }
+    } else {
+        p.y -= FALL_SPEED * dt;
+    }
+    p.y = p.y.max(GROUND_CLEARANCE);
+    p
+}
+
+/// An input command as sent to the sim: `input` held for `dt` seconds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct InputCommand {
+    pub seq: u32,
+    pub input: MoveInput,
+    pub dt: f32,
+}
+
+/// Client-side prediction: authoritative position + replay of inputs the sim has not echoed yet.
+#[derive(Debug, Clone, Default)]
+pub struct Predictor {
+    authoritative: Vec3,
+    acked_seq: u32,
+    last_seq: u32,
+    /// Sent, not yet echoed by the sim; oldest first.
+    pending: VecDeque<InputCommand>,
+    /// Being accumulated from frames with identical input; not sent yet.
+    open: Option<(MoveInput, f32)>,
+}
+
+impl Predictor {
+    /// Start over from an authoritative state (join snapshot).
+    pub fn reset(&mut self, position: Vec3, acked_seq: u32) {
+        self.authoritative = position;
+        self.acked_seq = acked_seq;
+        self.last_seq = self.last_seq.max(acked_seq);
+        self.pending.clear();
+        self.open = None;
+    }
+
+    /// Add one frame of input. Returns a finished command to send when the accumulated one closes
+    /// (input changed or [`INPUT_SEND_INTERVAL`] reached).
+    pub fn push(&mut self, input: MoveInput, dt: f32) -> Option<InputCommand> {
+        let dt = dt.clamp(0.0, MAX_INPUT_DT);
+        match self.open {
+            Some((open, held)) if open == input && held + dt <= INPUT_SEND_INTERVAL => {
+                self.open = Some((open, held + dt));
+                None
+            }
+            _ => {
+                let closed = self.close();
+                self.open = Some((input, dt));
+                closed
+            }
+        }
+    }
+
+    /// Close the accumulating command now (e.g. before going idle) and return it for sending.
+    pub fn close(&mut self) -> Option<InputCommand> {
+        let (input, dt) = self.open.take()?;
+        self.last_seq = self.last_seq.wrapping_add(1);
+        let cmd = InputCommand {
+            seq: self.last_seq,
+            input,
+            dt,
+        };
+        self.pending.push_back(cmd);
+        while self.pending.len() > MAX_PENDING_INPUTS {
+            self.pending.pop_front();
+        }
+        Some(cmd)
+    }
+
+    /// The sim reports `position` after processing every input up to `acked_seq`.
+    pub fn reconcile(&mut self, position: Vec3, acked_seq: u32) {
+        self.authoritative = position;
+        if acked_seq > self.acked_seq {
+            self.acked_seq = acked_seq;
+        }
+        while self
+            .pending
+            .front()
+            .is_some_and(|c| c.seq <= self.acked_seq)
+        {
+            self.pending.pop_front();
+        }
+    }
+
+    /// Authoritative position with every unacknowledged (and the accumulating) input replayed.
+    #[must_use]
+    pub fn predicted(&self) -> Vec3 {
+        let replayed = self
+            .pending
+            .iter()
+            .fold(self.authoritative, |p, c| step_position(p, &c.input, c.dt));
+        match &self.open {
+            Some((input, held)) => step_position(replayed, input, *held),
+            None => replayed,
+        }
+    }
+
+    #[must_use]
+    pub fn pending_len(&self) -> usize {
+        self.pending.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn walk_x() -> MoveInput {
+        MoveInput {
+            move_x: 1.0,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn split_steps_match_one_step() {
+        let start = Vec3::new(0.0, 3.0, 0.0);
+        let whole = step_position(start, &walk_x(), 0.2);
+        let split = (0..4).fold(start, |p, _| step_position(p, &walk_x(), 0.05));
+        assert_relative_eq!(whole.x, split.x, epsilon = 1e-5);
+        assert_relative_eq!(whole.y, split.y, epsilon = 1e-5);
+    }
+
+    #[test]
+    fn walking_falls_to_ground() {
+        let p = step_position(Vec3::new(0.0, 1.0, 0.0), &MoveInput::default(), 0.25);
+        assert_relative_eq!(p.y, GROUND_CLEARANCE);
+    }
+
+    #[test]
+    fn identical_frames_coalesce_into_one_command() {
+        let mut pr = Predictor::default();
+        assert!(pr.push(walk_x(), 0.01).is_none());
+        assert!(pr.push(walk_x(), 0.01).is_none());
+        let cmd = pr.push(MoveInput::default(), 0.01).unwrap();
+        assert_eq!(cmd.seq, 1);
+        assert_relative_eq!(cmd.dt, 0.02);
+    }
+
+    #[test]
+    fn reconcile_replays_unacknowledged_inputs() {
+        let mut pr = Predictor::default();
+        pr.reset(Vec3::new(0.0, GROUND_CLEARANCE, 0.0), 0);
+        pr.push(walk_x(), 0.03);
+        let first = pr.push(MoveInput::default(), 0.0).unwrap();
+        pr.push(walk_x(), 0.03);
+        pr.close();
+        // walk, the zero-length idle command, walk.
+        assert_eq!(pr.pending_len(), 3);
+        // Sim processed only the first command but ended up 1 m further (e.g. a push).
+        let server = step_position(Vec3::new(1.0, GROUND_CLEARANCE, 0.0), &walk_x(), 0.03);
+        pr.reconcile(server, first.seq);
+        assert_eq!(pr.pending_len(), 2);
+        assert_relative_eq!(
+            pr.predicted().x,
+            1.0 + 2.0 * WALK_SPEED * 0.03,
+            epsilon = 1e-5
+        );
+    }
+}
@@ -0,0 +1,605 @@
+//! Wire schemas of older protocol versions this build still speaks (ADR-009).
+//!
+//! Each supported old version keeps a frozen copy of its `NetMessage` enum and the payload structs
+//! in it. Decoding upgrades to the current [`NetMessage`]; encoding downgrades and fails with
+//! [`ProtocolError::NotInVersion`] for messages the old version cannot express. Drop a module once
+//! no deployed client needs it, and raise [`crate::protocol::MIN_PROTOCOL_VERSION`] with it.
+
+use crate::error::ProtocolError;
+use crate::protocol::{message_kind, NetMessage};
+
+pub(crate) fn encode_body(version: u16, msg: &NetMessage) -> Result<Vec<u8>, ProtocolError> {
+    match version {
+        9 => {
+            let old = v9::NetMessage::try_from(msg.clone()).map_err(|()| {
+                ProtocolError::NotInVersion {
+                    kind: message_kind(msg) as u16,
+                    version,
+                }
+            })?;
+            postcard::to_allocvec(&old).map_err(ProtocolError::encode)
+        }
+        _ => Err(ProtocolError::UnsupportedVersion(version)),
+    }
+}
+
+pub(crate) fn decode_body(version: u16, bytes: &[u8]) -> Result<NetMessage, ProtocolError> {
+    match version {
+        9 => Ok(postcard::from_bytes::<v9::NetMessage>(bytes)?.into()),
+        _ => Err(ProtocolError::UnsupportedVersion(version)),
+    }
+}
+
+/// Version 9: no login; the hello carried no public key and the ack no user id.
+mod v9 {
+    use glam::Vec3;
+    use serde::{Deserialize, Serialize};
+    use uuid::Uuid;
+
+    use crate::protocol::{self as current, Capabilities};
+
+    // Payload structs as of v9, so later changes to the current ones cannot leak into this schema.
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub(super) struct RegionDto {
+        id: i64,
+        name: String,
+        latitude: f64,
+        longitude: f64,
+        tile_x: i64,
+        tile_y: i64,
+        tile_z: i64,
+        sim_x: f32,
+        sim_y: f32,
+        sim_z: f32,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub(super) struct PrimDto {
+        id: i64,
+        region_id: i64,
+        name: String,
+        shape: String,
+        position: Vec3,
+        rotation: Vec3,
+        scale: Vec3,
+        color: [f32; 3],
+    }
+
+    #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+    pub(super) enum AvatarAnim {
+        Idle,
+        Run,
+        Hover,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub(super) struct AvatarStateDto {
+        id: u64,
+        position: Vec3,
+        yaw: f32,
+        velocity: Vec3,
+        anim: AvatarAnim,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub(super) struct AvatarDeltaDto {
+        id: u64,
+        position: Option<Vec3>,
+        yaw: Option<f32>,
+        velocity: Option<Vec3>,
+        anim: Option<AvatarAnim>,
+    }
+
+    #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+    pub(super) enum NetMessage {
+        ClientHello {
+            min_protocol_version: u16,
+            max_protocol_version: u16,
+            capabilities: Capabilities,
+            client_token: String,
+        },
+        ServerHelloAck {
+            session_id: Uuid,
+            tick_hz: f32,
+            your_avatar_id: u64,
+            osm_tile_url_template: String,
+            protocol_version: u16,
+            capabilities: Capabilities,
+        },
+        ServerError {
+            request_id: u32,
+            code: u32,
+            message: String,
+        },
+        ClientIntent {
+            request_id: u32,
+            input_seq: u32,
+            dt: f32,
+            move_x: f32,
+            move_z: f32,
+            display_yaw: f32,
+            fly_up: bool,
+            fly_down: bool,
+            flying: bool,
+        },
+        ObserverUpdate {
+            position: Vec3,
+        },
+        WorldSnapshot {
+            tick: u64,
+            regions: Vec<RegionDto>,
+            prims: Vec<PrimDto>,
+            avatars: Vec<AvatarStateDto>,
+            input_seq: u32,
+        },
+        PrimRemoved {
+            id: i64,
+        },
+        AvatarsAdded {
+            tick: u64,
+            avatars: Vec<AvatarStateDto>,
+        },
+        AvatarsUpdated {
+            tick: u64,
+            updates: Vec<AvatarDeltaDto>,
+            input_seq: u32,
+        },
+        AvatarsRemoved {
+            tick: u64,
+            ids: Vec<u64>,
+        },
+        SnapshotAck {
+            tick: u64,
+        },
+        RegionEntered {
+            region: RegionDto,
+            prims: Vec<PrimDto>,
+        },
+        RegionLeft {
+            region_id: i64,
+        },
+        Ping {
+            sent_at_us: u64,
+        },
+        Pong {
+            ping_sent_at_us: u64,
+            pong_sent_at_us: u64,
+        },
+    }
+
+    impl From<NetMessage> for current::NetMessage {
+        fn from(m: NetMessage) -> Self {
+            match m {
+                NetMessage::ClientHello {
+                    min_protocol_version,
+                    max_protocol_version,
+                    capabilities,
+                    client_token,
+                } => Self::ClientHello {
+                    min_protocol_version,
+                    max_protocol_version,
+                    capabilities,
+                    client_token,
+                    public_key: Vec::new(),
+                },
+                NetMessage::ServerHelloAck {
+                    session_id,
+                    tick_hz,
+                    your_avatar_id,
+                    osm_tile_url_template,
+                    protocol_version,
+                    capabilities,
+                } => Self::ServerHelloAck {
+                    session_id,
+                    tick_hz,
+                    your_avatar_id,
+                    osm_tile_url_template,
+                    protocol_version,
+                    capabilities,
+                    user_id: 0,
+                },
+                NetMessage::ServerError {
+                    request_id,
+                    code,
+                    message,
+                } => Self::ServerError {
+                    request_id,
+                    code,
+                    message,
+                },
+                NetMessage::ClientIntent {
+                    request_id,
+                    input_seq,
+                    dt,
+                    move_x,
+                    move_z,
+                    display_yaw,
+                    fly_up,
+                    fly_down,
+                    flying,
+                } => Self::ClientIntent {
+                    request_id,
+                    input_seq,
+                    dt,
+                    move_x,
+                    move_z,
+                    display_yaw,
+                    fly_up,
+                    fly_down,
+                    flying,
+                },
+                NetMessage::ObserverUpdate { position } => Self::ObserverUpdate { position },
+                NetMessage::WorldSnapshot {
+                    tick,
+                    regions,
+                    prims,
+                    avatars,
+                    input_seq,
+                } => Self::WorldSnapshot {
+                    tick,
+                    regions: convert(regions),
+                    prims: convert(prims),
+                    avatars: convert(avatars),
+                    input_seq,
+                },
+                NetMessage::PrimRemoved { id } => Self::PrimRemoved { id },
+                NetMessage::AvatarsAdded { tick, avatars } => Self::AvatarsAdded {
+                    tick,
+                    avatars: convert(avatars),
+                },
+                NetMessage::AvatarsUpdated {
+                    tick,
+                    updates,
+                    input_seq,
+                } => Self::AvatarsUpdated {
+                    tick,
+                    updates: convert(updates),
+                    input_seq,
+                },
+                NetMessage::AvatarsRemoved { tick, ids } => Self::AvatarsRemoved { tick, ids },
+                NetMessage::SnapshotAck { tick } => Self::SnapshotAck { tick },
+                NetMessage::RegionEntered { region, prims } => Self::RegionEntered {
+                    region: region.into(),
+                    prims: convert(prims),
+                },
+                NetMessage::RegionLeft { region_id } => Self::RegionLeft { region_id },
+                NetMessage::Ping { sent_at_us } => Self::Ping { sent_at_us },
+                NetMessage::Pong {
+                    ping_sent_at_us,
+                    pong_sent_at_us,
+                } => Self::Pong {
+                    ping_sent_at_us,
+                    pong_sent_at_us,
+                },
+            }
+        }
+    }
+
+    impl TryFrom<current::NetMessage> for NetMessage {
+        type Error = ();
+
+        fn try_from(m: current::NetMessage) -> Result<Self, ()> {
+            use current::NetMessage as C;
+            Ok(match m {
+                C::ClientHello {
+                    min_protocol_version,
+                    max_protocol_version,
+                    capabilities,
+                    client_token,
+                    public_key: _,
+                } => Self::ClientHello {
+                    min_protocol_version,
+                    max_protocol_version,
+                    capabilities,
+                    client_token,
+                },
+                C::ServerHelloAck {
+                    session_id,
+                    tick_hz,
+                    your_avatar_id,
+                    osm_tile_url_template,
+                    protocol_version,
+                    capabilities,
+                    user_id: _,
+                } => Self::ServerHelloAck {
+                    session_id,
+                    tick_hz,
+                    your_avatar_id,
+                    osm_tile_url_template,
+                    protocol_version,
+                    capabilities,
+                },
+                C::ServerError {
+                    request_id,
+                    code,
+                    message,
+                } => Self::ServerError {
+                    request_id,
+                    code,
+                    message,
+                },
+                C::ClientIntent {
+                    request_id,
+                    input_seq,
+                    dt,
+                    move_x,
+                    move_z,
+                    display_yaw,
+                    fly_up,
+                    fly_down,
+                    flying,
+                } => Self::ClientIntent {
+                    request_id,
+                    input_seq,
+                    dt,
+                    move_x,
+                    move_z,
+                    display_yaw,
+                    fly_up,
+                    fly_down,
+                    flying,
+                },
+                C::ObserverUpdate { position } => Self::ObserverUpdate { position },
+                C::WorldSnapshot {
+                    tick,
+                    regions,
+                    prims,
+                    avatars,
+                    input_seq,
+                } => Self::WorldSnapshot {
+                    tick,
+                    regions: convert(regions),
+                    prims: convert(prims),
+                    avatars: convert(avatars),
+                    input_seq,
+                },
+                C::PrimRemoved { id } => Self::PrimRemoved { id },
+                C::AvatarsAdded { tick, avatars } => Self::AvatarsAdded {
+                    tick,
+                    avatars: convert(avatars),
+                },
+                C::AvatarsUpdated {
+                    tick,
+                    updates,
+                    input_seq,
+                } => Self::AvatarsUpdated {
+                    tick,
+                    updates: convert(updates),
+                    input_seq,
+                },
+                C::AvatarsRemoved { tick, ids } => Self::AvatarsRemoved { tick, ids },
+                C::SnapshotAck { tick } => Self::SnapshotAck { tick },
+                C::RegionEntered { region, prims } => Self::RegionEntered {
+                    region: region.into(),
+                    prims: convert(prims),
+                },
+                C::RegionLeft { region_id } => Self::RegionLeft { region_id },
+                C::Ping { sent_at_us } => Self::Ping { sent_at_us },
+                C::Pong {
+                    ping_sent_at_us,
+                    pong_sent_at_us,
+                } => Self::Pong {
+                    ping_sent_at_us,
+                    pong_sent_at_us,
+                },
+                C::AuthChallenge { .. }
+                | C::AuthResponse { .. }
+                | C::SetRegionRole { .. }
+                | C::EditPrim { .. }
+                | C::PrimUpdated { .. }
+                | C::PrimEditAck { .. }
+                | C::SendChat { .. }
+                | C::ChatMessage { .. }
+                | C::SetDisplayName { .. }
+                | C::AvatarProfiles { .. }
+                | C::SetAppearance { .. }
+                | C::AvatarAppearances { .. }
+                | C::RollbackRegion { .. } => return Err(()),
+            })
+        }
+    }
+
+    fn convert<A: Into<B>, B>(items: Vec<A>) -> Vec<B> {
+        items.into_iter().map(Into::into).collect()
+    }
+
+    impl From<RegionDto> for current::RegionDto {
+        fn from(r: RegionDto) -> Self {
+            Self {
+                id: r.id,
+                name: r.name,
+                latitude: r.latitude,
+                longitude: r.longitude,
+                tile_x: r.tile_x,
+                tile_y: r.tile_y,
+                tile_z: r.tile_z,
+                sim_x: r.sim_x,
+                sim_y: r.sim_y,
+                sim_z: r.sim_z,
+            }
+        }
+    }
+
+    impl From<current::RegionDto> for RegionDto {
+        fn from(r: current::RegionDto) -> Self {
+            Self {
+                id: r.id,
+                name: r.name,
+                latitude: r.latitude,
+                longitude: r.longitude,
+                tile_x: r.tile_x,
+                tile_y: r.tile_y,
+                tile_z: r.tile_z,
+                sim_x: r.sim_x,
+                sim_y: r.sim_y,
+                sim_z: r.sim_z,
+            }
+        }
+    }
+
+    impl From<PrimDto> for current::PrimDto {
+        fn from(p: PrimDto) -> Self {
+            Self {
+                id: p.id,
+                region_id: p.region_id,
+                name: p.name,
+                shape: p.shape,
+                position: p.position,
+                rotation: p.rotation,
+                scale: p.scale,
+                color: p.color,
+            }
+        }
+    }
+
+    impl From<current::PrimDto> for PrimDto {
+        fn from(p: current::PrimDto) -> Self {
+            Self {
+                id: p.id,
+                region_id: p.region_id,
+                name: p.name,
+                shape: p.shape,
+                position: p.position,
+                rotation: p.rotation,
+                scale: p.scale,
+                color: p.color,
+            }
+        }
+    }
+
+    impl From<AvatarAnim> for current::AvatarAnim {
+        fn from(a: AvatarAnim) -> Self {
+            match a {
+                AvatarAnim::Idle => Self::Idle,
+                AvatarAnim::Run => Self::Run,
+                AvatarAnim::Hover => Self::Hover,
+            }
+        }
+    }
+
+    impl From<current::AvatarAnim> for AvatarAnim {
+        fn from(a: current::AvatarAnim) -> Self {
+            match a {
+                current::AvatarAnim::Idle => Self::Idle,
+                current::AvatarAnim::Run => Self::Run,
+                current::AvatarAnim::Hover => Self::Hover,
+            }
+        }
+    }
+
+    impl From<AvatarStateDto> for current::AvatarStateDto {
+        fn from(a: AvatarStateDto) -> Self {
+            Self {
+                id: a.id,
+                position: a.position,
+                yaw: a.yaw,
+                velocity: a.velocity,
+                anim: a.anim.into(),
+            }
+        }
+    }
+
+    impl From<current::AvatarStateDto> for AvatarStateDto {
+        fn from(a: current::AvatarStateDto) -> Self {
+            Self {
+                id: a.id,
+                position: a.position,
+                yaw: a.yaw,
+                velocity: a.velocity,
+                anim: a.anim.into(),
+            }
+        }
+    }
+
+    impl From<AvatarDeltaDto> for current::AvatarDeltaDto {
+        fn from(d: AvatarDeltaDto) -> Self {
+            Self {
+                id: d.id,
+                position: d.position,
+                yaw: d.yaw,
+                velocity: d.velocity,
+                anim: d.anim.map(Into::into),
+            }
+        }
+    }
+
+    impl From<current::AvatarDeltaDto> for AvatarDeltaDto {
+        fn from(d: current::AvatarDeltaDto) -> Self {
+            Self {
+                id: d.id,
+                position: d.position,
+                yaw: d.yaw,
+                velocity: d.velocity,
+                anim: d.anim.map(Into::into),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::{
+        decode_app_frame_versioned, encode_app_frame_as, AvatarAnim, AvatarDeltaDto, Capabilities,
+        MIN_PROTOCOL_VERSION,
+    };
+    use glam::Vec3;
+
+    fn v9_frame(kind: u16, msg: &v9::NetMessage) -> Vec<u8> {
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&9u16.to_le_bytes());
+        frame.extend_from_slice(&kind.to_le_bytes());
+        frame.extend_from_slice(&0u32.to_le_bytes());
+        frame.extend_from_slice(&postcard::to_allocvec(msg).unwrap());
+        frame
+    }
+
+    #[test]
+    fn v9_hello_upgrades_without_key() {
+        let old = v9::NetMessage::ClientHello {
+            min_protocol_version: 9,
+            max_protocol_version: 9,
+            capabilities: Capabilities::HEARTBEAT,
+            client_token: "old".into(),
+        };
+        let (ver, msg) = decode_app_frame_versioned(&v9_frame(1, &old)).unwrap();
+        assert_eq!(ver, 9);
+        assert_eq!(
+            msg,
+            NetMessage::ClientHello {
+                min_protocol_version: 9,
+                max_protocol_version: 9,
+                capabilities: Capabilities::HEARTBEAT,
+                client_token: "old".into(),
+                public_key: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn v9_snapshot_roundtrips_through_frozen_dtos() {
+        let m = NetMessage::AvatarsUpdated {
+            tick: 4,
+            updates: vec![AvatarDeltaDto {
+                id: 2,
+                position: Some(Vec3::Y),
+                yaw: None,
+                velocity: None,
+                anim: Some(AvatarAnim::Hover),
+            }],
+            input_seq: 8,
+        };
+        let frame = encode_app_frame_as(&m, 9).unwrap();
+        assert_eq!(decode_app_frame_versioned(&frame).unwrap(), (9, m));
+    }
+
+    #[test]
+    fn auth_messages_are_not_in_v9() {
+        let m = NetMessage::AuthChallenge { nonce: vec![1; 32] };
+        assert!(matches!(
+            encode_app_frame_as(&m, MIN_PROTOCOL_VERSION),
+            Err(ProtocolError::NotInVersion { version: 9, .. })
+        ));
+    }
+}
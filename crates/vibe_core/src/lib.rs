@@ -1,16 +1,32 @@
 //! Shared types for vibers sim and client (ADR-006, ADR-009, ADR-015).
 
+pub mod appearance;
+pub mod auth;
+pub mod chat;
+pub mod clock;
+pub mod edit;
 pub mod error;
+pub mod interpolation;
+pub mod kinematics;
+mod legacy;
+pub mod profile;
 pub mod protocol;
+pub mod replication;
 pub mod world;
 pub mod yaw;
 
+pub use clock::{now_micros, ClockSync};
 pub use error::ProtocolError;
+pub use interpolation::{AvatarSample, InterpolationBuffer, TickClock};
+pub use kinematics::{step_position, InputCommand, MoveInput, Predictor};
 pub use protocol::{
-    decode_app_frame, decode_message, encode_app_frame, encode_message, message_kind,
-    message_request_id, AvatarStateDto, MessageKind, NetMessage, PrimDto, RegionDto,
-    PROTOCOL_VERSION,
+    decode_app_frame, decode_app_frame_versioned, decode_message, encode_app_frame,
+    encode_app_frame_as, encode_message, message_kind, message_request_id, message_tick,
+    negotiate_version, AvatarAnim, AvatarAppearance, AvatarAppearanceDto, AvatarDeltaDto,
+    AvatarProfileDto, AvatarStateDto, Capabilities, ChatScope, ErrorCode, MessageKind, NetMessage,
+    PrimDto, PrimEdit, RegionDto, RegionRole, MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
 };
+pub use replication::AvatarReplicator;
 pub use world::{
     find_optimal_zoom, lat_lng_to_tile, tile_to_lat_lng, tile_to_meters, TileKey,
     REGION_SIZE_METERS, REGION_ZOOM_LEVEL,
@@ -0,0 +1,46 @@
+//! Avatar profile rules (ADR-019): what a display name may be, and what an account is called until
+//! it picks one. The sim enforces them; clients can check before sending.
+
+use crate::auth::GUEST_USER_ID;
+
+/// Longest accepted display name, in characters, after trimming.
+pub const MAX_DISPLAY_NAME_LEN: usize = 32;
+
+/// Name of an account that never set one.
+#[must_use]
+pub fn default_display_name(user_id: i64) -> String {
+    if user_id == GUEST_USER_ID {
+        return "Guest".into();
+    }
+    format!("Resident {user_id}")
+}
+
+/// The name as it will be stored: trimmed, 1..=[`MAX_DISPLAY_NAME_LEN`] characters and free of
+/// control characters (a name tag is one line). `Err` is user-facing.
+pub fn validate_display_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    if name.is_empty() || name.chars().count() > MAX_DISPLAY_NAME_LEN {
+        return Err(format!(
+            "display name must be 1..={MAX_DISPLAY_NAME_LEN} characters"
+        ));
+    }
+    if name.chars().any(char::is_control) {
+        return Err("display name contains control characters".into());
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_names_are_trimmed_single_lines() {
+        assert_eq!(validate_display_name("  Ada Lovelace "), Ok("Ada Lovelace"));
+        assert!(validate_display_name("").is_err());
+        assert!(validate_display_name("Ada\nLovelace").is_err());
+        assert!(validate_display_name(&"x".repeat(MAX_DISPLAY_NAME_LEN + 1)).is_err());
+        assert_eq!(default_display_name(7), "Resident 7");
+        assert_eq!(default_display_name(GUEST_USER_ID), "Guest");
+    }
+}
@@ -6,9 +6,14 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::error::ProtocolError;
+use crate::legacy;
 
-/// Bump when the app-frame layout or postcard schema changes incompatibly.
-pub const PROTOCOL_VERSION: u16 = 4;
+/// Bump when the app-frame layout or an existing message's postcard schema changes. Variants appended
+/// to [`NetMessage`] behind a [`Capabilities`] bit need no bump: peers without the bit never see them.
+pub const PROTOCOL_VERSION: u16 = 10;
+
+/// Oldest version this build still encodes and decodes (schemas in [`crate::legacy`]).
+pub const MIN_PROTOCOL_VERSION: u16 = 9;
 
 const APP_HEADER_LEN: usize = 8;
 
@@ -22,6 +27,27 @@ pub enum MessageKind {
     ObserverUpdate = 5,
     WorldSnapshot = 6,
     PrimRemoved = 7,
+    AvatarsAdded = 8,
+    AvatarsUpdated = 9,
+    AvatarsRemoved = 10,
+    SnapshotAck = 11,
+    RegionEntered = 12,
+    RegionLeft = 13,
+    Ping = 14,
+    Pong = 15,
+    AuthChallenge = 16,
+    AuthResponse = 17,
+    SetRegionRole = 18,
+    EditPrim = 19,
+    PrimUpdated = 20,
+    PrimEditAck = 21,
+    SendChat = 22,
+    ChatMessage = 23,
+    SetDisplayName = 24,
+    AvatarProfiles = 25,
+    SetAppearance = 26,
+    AvatarAppearances = 27,
+    RollbackRegion = 28,
 }
 
 impl MessageKind {
@@ -35,9 +61,178 @@ impl MessageKind {
             5 => Some(Self::ObserverUpdate),
             6 => Some(Self::WorldSnapshot),
             7 => Some(Self::PrimRemoved),
+            8 => Some(Self::AvatarsAdded),
+            9 => Some(Self::AvatarsUpdated),
+            10 => Some(Self::AvatarsRemoved),
+            11 => Some(Self::SnapshotAck),
+            12 => Some(Self::RegionEntered),
+            13 => Some(Self::RegionLeft),
+            14 => Some(Self::Ping),
+            15 => Some(Self::Pong),
+            16 => Some(Self::AuthChallenge),
+            17 => Some(Self::AuthResponse),
+            18 => Some(Self::SetRegionRole),
+            19 => Some(Self::EditPrim),
+            20 => Some(Self::PrimUpdated),
+            21 => Some(Self::PrimEditAck),
+            22 => Some(Self::SendChat),
+            23 => Some(Self::ChatMessage),
+            24 => Some(Self::SetDisplayName),
+            25 => Some(Self::AvatarProfiles),
+            26 => Some(Self::SetAppearance),
+            27 => Some(Self::AvatarAppearances),
+            28 => Some(Self::RollbackRegion),
+            _ => None,
+        }
+    }
+}
+
+/// Stable `ServerError::code` values. Never renumber; append new codes and treat unknown ones as
+/// opaque (older peers see them as raw numbers).
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// No protocol version in common (ADR-009 hello).
+    VersionMismatch = 1,
+    /// Malformed frame, wrong message for the session state, or wrong frame version.
+    BadRequest = 2,
+    AuthFailed = 3,
+    RateLimited = 4,
+    PermissionDenied = 5,
+    /// A world edit was well-formed but not applicable (unknown prim, out of bounds, …).
+    InvalidEdit = 6,
+    ShuttingDown = 7,
+    /// Disconnected by an operator.
+    Kicked = 8,
+    Internal = 9,
+    /// Nothing received from the peer within the idle timeout.
+    IdleTimeout = 10,
+    /// The peer read too slowly: its outbound queue stayed behind past the sim's threshold.
+    SlowConsumer = 11,
+    /// A chat line was refused (empty, too long, unknown recipient, …); the session stays open.
+    InvalidChat = 12,
+    /// A display name or avatar appearance was refused; the session stays open.
+    InvalidProfile = 13,
+}
+
+impl ErrorCode {
+    #[must_use]
+    pub fn from_wire(v: u32) -> Option<Self> {
+        match v {
+            1 => Some(Self::VersionMismatch),
+            2 => Some(Self::BadRequest),
+            3 => Some(Self::AuthFailed),
+            4 => Some(Self::RateLimited),
+            5 => Some(Self::PermissionDenied),
+            6 => Some(Self::InvalidEdit),
+            7 => Some(Self::ShuttingDown),
+            8 => Some(Self::Kicked),
+            9 => Some(Self::Internal),
+            10 => Some(Self::IdleTimeout),
+            11 => Some(Self::SlowConsumer),
+            12 => Some(Self::InvalidChat),
+            13 => Some(Self::InvalidProfile),
             _ => None,
         }
     }
+
+    /// Whether the sim closes the connection after sending this code.
+    #[must_use]
+    pub fn ends_session(self) -> bool {
+        matches!(
+            self,
+            Self::VersionMismatch
+                | Self::BadRequest
+                | Self::AuthFailed
+                | Self::ShuttingDown
+                | Self::Kicked
+                | Self::Internal
+                | Self::IdleTimeout
+                | Self::SlowConsumer
+        )
+    }
+
+    /// Short human-readable reason, for logs and the client window.
+    #[must_use]
+    pub fn describe(self) -> &'static str {
+        match self {
+            Self::VersionMismatch => "incompatible protocol version",
+            Self::BadRequest => "bad request",
+            Self::AuthFailed => "authentication failed",
+            Self::RateLimited => "rate limited",
+            Self::PermissionDenied => "permission denied",
+            Self::InvalidEdit => "invalid edit",
+            Self::ShuttingDown => "server shutting down",
+            Self::Kicked => "kicked",
+            Self::Internal => "internal server error",
+            Self::IdleTimeout => "idle timeout",
+            Self::SlowConsumer => "connection too slow",
+            Self::InvalidChat => "invalid chat message",
+            Self::InvalidProfile => "invalid profile",
+        }
+    }
+}
+
+impl NetMessage {
+    /// `ServerError` with a typed code.
+    #[must_use]
+    pub fn error(code: ErrorCode, request_id: u32, message: impl Into<String>) -> Self {
+        Self::ServerError {
+            request_id,
+            code: code as u32,
+            message: message.into(),
+        }
+    }
+}
+
+/// Optional features advertised in the hello; the session uses the intersection of both sides.
+/// Unknown bits from newer peers are carried but never acted on.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Capabilities(pub u32);
+
+impl Capabilities {
+    /// `RegionEntered` / `RegionLeft` as the session's AOI moves (ADR-012).
+    pub const REGION_STREAMING: Self = Self(1 << 0);
+    /// `Ping` / `Pong` may be sent; the receiver always answers a `Ping` (ADR-008).
+    pub const HEARTBEAT: Self = Self(1 << 1);
+    /// The client may send `SetRegionRole` and `RollbackRegion` (owners' tools, ADR-016/017).
+    pub const REGION_ADMIN: Self = Self(1 << 2);
+    /// The client may send `EditPrim` and receives `PrimUpdated` / `PrimEditAck` (ADR-011).
+    pub const PRIM_EDITING: Self = Self(1 << 3);
+    /// The client may send `SendChat` and receives `ChatMessage` (ADR-018).
+    pub const CHAT: Self = Self(1 << 4);
+    /// The client may send `SetDisplayName` and receives `AvatarProfiles` (ADR-019).
+    pub const DISPLAY_NAMES: Self = Self(1 << 5);
+    /// The client may send `SetAppearance` and receives `AvatarAppearances` (ADR-020).
+    pub const APPEARANCE: Self = Self(1 << 6);
+
+    #[must_use]
+    pub const fn empty() -> Self {
+        Self(0)
+    }
+
+    #[must_use]
+    pub const fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    #[must_use]
+    pub const fn union(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+
+    #[must_use]
+    pub const fn intersection(self, other: Self) -> Self {
+        Self(self.0 & other.0)
+    }
+}
+
+/// Highest version in both `client_min..=client_max` and what this build speaks, if any.
+#[must_use]
+pub fn negotiate_version(client_min: u16, client_max: u16) -> Option<u16> {
+    let hi = client_max.min(PROTOCOL_VERSION);
+    let lo = client_min.max(MIN_PROTOCOL_VERSION);
+    (lo <= hi).then_some(hi)
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -67,18 +262,145 @@ pub struct PrimDto {
     pub color: [f32; 3],
 }
 
+/// One change to the world's prims, as requested by a client (ADR-011 write path). Checked by
+/// [`crate::edit::validate_prim`]; the sim answers with `PrimEditAck` or a `ServerError`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub enum PrimEdit {
+    /// `prim.id` is ignored: the sim assigns the id and returns it in `PrimEditAck`.
+    Create(PrimDto),
+    Transform {
+        id: i64,
+        position: Vec3,
+        rotation: Vec3,
+        scale: Vec3,
+    },
+    Recolor {
+        id: i64,
+        color: [f32; 3],
+    },
+    Rename {
+        id: i64,
+        name: String,
+    },
+    Delete {
+        id: i64,
+    },
+}
+
+/// Standing of an account in one region (ADR-016). Accounts without a grant are visitors.
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+pub enum RegionRole {
+    #[default]
+    Visitor,
+    /// May edit the region's prims.
+    Manager,
+    /// May also grant and revoke roles.
+    Owner,
+}
+
+/// Who hears a chat line (ADR-018).
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ChatScope {
+    /// Avatars within [`crate::chat::LOCAL_CHAT_RADIUS`] of the speaker.
+    Local,
+    /// Avatars standing in the speaker's region.
+    Region,
+    /// One avatar; the speaker gets a copy.
+    Direct {
+        avatar_id: u64,
+    },
+}
+
+/// What an avatar is doing, as decided by the sim; remote clients pick the animation from it.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub enum AvatarAnim {
+    #[default]
+    Idle,
+    /// Moving horizontally (walking or flying).
+    Run,
+    /// Flying without horizontal motion.
+    Hover,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct AvatarStateDto {
     pub id: u64,
     pub position: Vec3,
     pub yaw: f32,
+    /// Metres per second from the last applied input; clients extrapolate with it when starved.
+    pub velocity: Vec3,
+    pub anim: AvatarAnim,
+}
+
+impl AvatarStateDto {
+    /// Overwrite the fields present in `delta` (ids are not checked).
+    pub fn apply_delta(&mut self, delta: &AvatarDeltaDto) {
+        if let Some(p) = delta.position {
+            self.position = p;
+        }
+        if let Some(y) = delta.yaw {
+            self.yaw = y;
+        }
+        if let Some(v) = delta.velocity {
+            self.velocity = v;
+        }
+        if let Some(a) = delta.anim {
+            self.anim = a;
+        }
+    }
+}
+
+/// Who an avatar is (ADR-019). Sent once when the avatar first comes into a session's view and
+/// again when it changes, not with every tick.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AvatarProfileDto {
+    pub id: u64,
+    /// Account the avatar belongs to.
+    pub user_id: i64,
+    pub display_name: String,
+}
+
+/// What an avatar looks like (ADR-020): a model from the client's avatar catalog, tinted and
+/// scaled. Rules are in `vibe_core::appearance`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AvatarAppearance {
+    /// Catalog id; clients without the model show the default one.
+    pub model: String,
+    /// sRGB color (channels 0..=1) multiplied into the model's; white leaves it as it is.
+    pub tint: [f32; 3],
+    /// Relative to the model's own size.
+    pub scale: f32,
+}
+
+/// One avatar's appearance, replicated like [`AvatarProfileDto`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AvatarAppearanceDto {
+    pub id: u64,
+    pub appearance: AvatarAppearance,
+}
+
+/// Changed fields of one avatar since the client's last acknowledged tick; `None` = unchanged.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AvatarDeltaDto {
+    pub id: u64,
+    pub position: Option<Vec3>,
+    pub yaw: Option<f32>,
+    pub velocity: Option<Vec3>,
+    pub anim: Option<AvatarAnim>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub enum NetMessage {
+    /// Sent in the client's newest frame version; the sim answers in the negotiated one.
     ClientHello {
-        protocol_version: u16,
+        min_protocol_version: u16,
+        max_protocol_version: u16,
+        capabilities: Capabilities,
         client_token: String,
+        /// Ed25519 public key of the account logging in ([`crate::auth`]); the sim challenges it.
+        public_key: Vec<u8>,
     },
     ServerHelloAck {
         session_id: Uuid,
@@ -87,21 +409,35 @@ pub enum NetMessage {
         /// ADR-014: operator tile URL; `{z}/{x}/{y}` placeholders. Empty = client default.
         #[serde(default)]
         osm_tile_url_template: String,
+        /// Highest version both sides speak; every later frame in either direction uses it.
+        protocol_version: u16,
+        /// Features enabled for this session (client ∩ sim).
+        capabilities: Capabilities,
+        /// Persistent account the session is bound to; 0 from sims older than v10 (no login).
+        user_id: i64,
     },
     ServerError {
         request_id: u32,
+        /// [`ErrorCode`] on the wire; unknown values come from newer peers.
         code: u32,
         message: String,
     },
+    /// One input command (ADR-010): held for `dt` seconds, applied by the sim with
+    /// [`crate::kinematics::step_position`]. `dt == 0` means "since the previous intent" (pre-v8 clients).
     ClientIntent {
         request_id: u32,
+        /// Client-assigned, increasing; echoed back as `input_seq` once processed.
+        input_seq: u32,
+        dt: f32,
         move_x: f32,
         move_z: f32,
         /// World yaw replicated to others (fox / travel facing). Server applies this when moving instead of `atan2(velocity)`.
         display_yaw: f32,
         fly_up: bool,
         fly_down: bool,
+        flying: bool,
     },
+    /// Ignored by the sim: AOI is centred on the session's own avatar (ADR-012). Kept for wire compatibility.
     ObserverUpdate {
         position: Vec3,
     },
@@ -110,11 +446,127 @@ pub enum NetMessage {
         regions: Vec<RegionDto>,
         prims: Vec<PrimDto>,
         avatars: Vec<AvatarStateDto>,
+        /// Last `ClientIntent::input_seq` of this session the sim had applied at `tick`.
+        input_seq: u32,
     },
     /// ADR-011 delta v0: remove prim by stable id (server-assigned).
     PrimRemoved {
         id: i64,
     },
+    /// Avatars that entered this session's view (full state). Idempotent: an existing id is replaced.
+    AvatarsAdded {
+        tick: u64,
+        avatars: Vec<AvatarStateDto>,
+    },
+    /// Field-level changes since the last [`NetMessage::SnapshotAck`] the sim received. Also sent
+    /// with no updates whenever `input_seq` advances, so the client can reconcile its prediction.
+    AvatarsUpdated {
+        tick: u64,
+        updates: Vec<AvatarDeltaDto>,
+        /// Last `ClientIntent::input_seq` of this session the sim had applied at `tick`.
+        input_seq: u32,
+    },
+    /// Avatars that left this session's view (or disconnected). Unknown ids are ignored.
+    AvatarsRemoved {
+        tick: u64,
+        ids: Vec<u64>,
+    },
+    /// Client → sim: highest tick applied; the sim diffs avatar state against it.
+    SnapshotAck {
+        tick: u64,
+    },
+    /// ADR-011/012: a region came into this session's AOI; carries the region and all its prims.
+    RegionEntered {
+        region: RegionDto,
+        prims: Vec<PrimDto>,
+    },
+    /// ADR-011/012: a region left this session's AOI; the client drops it and its prims.
+    RegionLeft {
+        region_id: i64,
+    },
+    /// Heartbeat; the receiver answers with [`NetMessage::Pong`] echoing `sent_at_us`.
+    /// Timestamps are microseconds on the sender's wall clock ([`crate::clock::now_micros`]).
+    Ping {
+        sent_at_us: u64,
+    },
+    Pong {
+        ping_sent_at_us: u64,
+        /// Responder's wall clock when it answered; with the echo this yields RTT and clock offset.
+        pong_sent_at_us: u64,
+    },
+    /// Sim → client after the hello: sign [`crate::auth::challenge_payload`] for this nonce.
+    AuthChallenge {
+        nonce: Vec<u8>,
+    },
+    /// Client → sim: Ed25519 signature over the challenge payload with the hello's key.
+    AuthResponse {
+        signature: Vec<u8>,
+    },
+    /// Give an account a role in a region (`Visitor` revokes); owners only, with `REGION_ADMIN`.
+    /// Success is silent; refusals come back as `ServerError` with this `request_id`.
+    SetRegionRole {
+        request_id: u32,
+        region_id: i64,
+        user_id: i64,
+        role: RegionRole,
+    },
+    /// Client → sim: change a prim; needs `PRIM_EDITING` and the region's manager role (ADR-016).
+    EditPrim {
+        request_id: u32,
+        edit: PrimEdit,
+    },
+    /// ADR-011 delta: full state of a created or changed prim; replaces any prim with this id.
+    PrimUpdated {
+        prim: PrimDto,
+    },
+    /// Sim → editing client: the edit with `request_id` was applied and stored as prim `id`.
+    PrimEditAck {
+        request_id: u32,
+        id: i64,
+    },
+    /// Client → sim: say something; needs `CHAT`. Success is silent (the speaker hears its own
+    /// line); refusals come back as `ServerError` with this `request_id`.
+    SendChat {
+        request_id: u32,
+        scope: ChatScope,
+        text: String,
+    },
+    /// Sim → client: a chat line this session's avatar can hear, its own included.
+    ChatMessage {
+        from_avatar_id: u64,
+        /// Account of the speaker.
+        from_user_id: i64,
+        scope: ChatScope,
+        text: String,
+    },
+    /// Client → sim: name this account's avatars; needs `DISPLAY_NAMES`. Sent right after the
+    /// hello and stored on the account; refusals come back as `ServerError` with this `request_id`.
+    SetDisplayName {
+        request_id: u32,
+        name: String,
+    },
+    /// Sim → client: profiles of avatars that came into view or changed, its own avatar's too.
+    AvatarProfiles {
+        profiles: Vec<AvatarProfileDto>,
+    },
+    /// Client → sim: how this session's avatar looks; needs `APPEARANCE`. Sent right after the
+    /// hello and kept for the session; refusals come back as `ServerError` with this `request_id`.
+    SetAppearance {
+        request_id: u32,
+        appearance: AvatarAppearance,
+    },
+    /// Sim → client: appearances of avatars that came into view or changed, its own avatar's too.
+    AvatarAppearances {
+        appearances: Vec<AvatarAppearanceDto>,
+    },
+    /// Put every prim of a region back as it was at `to` (UTC, any SQLite date/time string);
+    /// owners only, with `REGION_ADMIN` (ADR-017). The restored prims reach every session as
+    /// `PrimUpdated` / `PrimRemoved`; refusals come back as `ServerError` with this `request_id`.
+    RollbackRegion {
+        request_id: u32,
+        region_id: i64,
+        to: String,
+    },
 }
 
 #[must_use]
@@ -127,6 +579,27 @@ pub fn message_kind(msg: &NetMessage) -> MessageKind {
         NetMessage::ObserverUpdate { .. } => MessageKind::ObserverUpdate,
         NetMessage::WorldSnapshot { .. } => MessageKind::WorldSnapshot,
         NetMessage::PrimRemoved { .. } => MessageKind::PrimRemoved,
+        NetMessage::AvatarsAdded { .. } => MessageKind::AvatarsAdded,
+        NetMessage::AvatarsUpdated { .. } => MessageKind::AvatarsUpdated,
+        NetMessage::AvatarsRemoved { .. } => MessageKind::AvatarsRemoved,
+        NetMessage::SnapshotAck { .. } => MessageKind::SnapshotAck,
+        NetMessage::RegionEntered { .. } => MessageKind::RegionEntered,
+        NetMessage::RegionLeft { .. } => MessageKind::RegionLeft,
+        NetMessage::Ping { .. } => MessageKind::Ping,
+        NetMessage::Pong { .. } => MessageKind::Pong,
+        NetMessage::AuthChallenge { .. } => MessageKind::AuthChallenge,
+        NetMessage::AuthResponse { .. } => MessageKind::AuthResponse,
+        NetMessage::SetRegionRole { .. } => MessageKind::SetRegionRole,
+        NetMessage::EditPrim { .. } => MessageKind::EditPrim,
+        NetMessage::PrimUpdated { .. } => MessageKind::PrimUpdated,
+        NetMessage::PrimEditAck { .. } => MessageKind::PrimEditAck,
+        NetMessage::SendChat { .. } => MessageKind::SendChat,
+        NetMessage::ChatMessage { .. } => MessageKind::ChatMessage,
+        NetMessage::SetDisplayName { .. } => MessageKind::SetDisplayName,
+        NetMessage::AvatarProfiles { .. } => MessageKind::AvatarProfiles,
+        NetMessage::SetAppearance { .. } => MessageKind::SetAppearance,
+        NetMessage::AvatarAppearances { .. } => MessageKind::AvatarAppearances,
+        NetMessage::RollbackRegion { .. } => MessageKind::RollbackRegion,
     }
 }
 
@@ -135,10 +608,29 @@ pub fn message_request_id(msg: &NetMessage) -> u32 {
     match msg {
         NetMessage::ClientIntent { request_id, .. } => *request_id,
         NetMessage::ServerError { request_id, .. } => *request_id,
+        NetMessage::SetRegionRole { request_id, .. } => *request_id,
+        NetMessage::EditPrim { request_id, .. } => *request_id,
+        NetMessage::PrimEditAck { request_id, .. } => *request_id,
+        NetMessage::SendChat { request_id, .. } => *request_id,
+        NetMessage::SetDisplayName { request_id, .. } => *request_id,
+        NetMessage::SetAppearance { request_id, .. } => *request_id,
+        NetMessage::RollbackRegion { request_id, .. } => *request_id,
         _ => 0,
     }
 }
 
+/// Sim tick carried by world/avatar replication messages (what the client acknowledges).
+#[must_use]
+pub fn message_tick(msg: &NetMessage) -> Option<u64> {
+    match msg {
+        NetMessage::WorldSnapshot { tick, .. }
+        | NetMessage::AvatarsAdded { tick, .. }
+        | NetMessage::AvatarsUpdated { tick, .. }
+        | NetMessage::AvatarsRemoved { tick, .. } => Some(*tick),
+        _ => None,
+    }
+}
+
 /// Raw postcard body (no app header). Prefer [`encode_app_frame`] / [`decode_app_frame`] on the wire.
 #[must_use]
 pub fn encode_message(msg: &NetMessage) -> Result<Vec<u8>, postcard::Error> {
@@ -150,27 +642,48 @@ pub fn decode_message(bytes: &[u8]) -> Result<NetMessage, postcard::Error> {
     postcard::from_bytes(bytes)
 }
 
-/// Full ADR-009 app frame: version + kind + request_id + postcard payload.
+/// Full ADR-009 app frame at [`PROTOCOL_VERSION`]: version + kind + request_id + postcard payload.
 #[must_use]
 pub fn encode_app_frame(msg: &NetMessage) -> Result<Vec<u8>, postcard::Error> {
     let body = encode_message(msg)?;
+    Ok(app_frame(PROTOCOL_VERSION, msg, &body))
+}
+
+/// App frame for a session that negotiated `version`. Fails with
+/// [`ProtocolError::NotInVersion`] when the message has no equivalent in that version.
+pub fn encode_app_frame_as(msg: &NetMessage, version: u16) -> Result<Vec<u8>, ProtocolError> {
+    let body = if version == PROTOCOL_VERSION {
+        encode_message(msg).map_err(ProtocolError::encode)?
+    } else {
+        legacy::encode_body(version, msg)?
+    };
+    Ok(app_frame(version, msg, &body))
+}
+
+fn app_frame(version: u16, msg: &NetMessage, body: &[u8]) -> Vec<u8> {
     let kind = message_kind(msg) as u16;
     let rid = message_request_id(msg);
     let mut out = Vec::with_capacity(APP_HEADER_LEN + body.len());
-    out.extend_from_slice(&PROTOCOL_VERSION.to_le_bytes());
+    out.extend_from_slice(&version.to_le_bytes());
     out.extend_from_slice(&kind.to_le_bytes());
     out.extend_from_slice(&rid.to_le_bytes());
-    out.extend_from_slice(&body);
-    Ok(out)
+    out.extend_from_slice(body);
+    out
 }
 
 #[must_use]
 pub fn decode_app_frame(bytes: &[u8]) -> Result<NetMessage, ProtocolError> {
+    decode_app_frame_versioned(bytes).map(|(_, msg)| msg)
+}
+
+/// Decode a frame of any version in `MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION`, returning the
+/// header version alongside the message (upgraded to the current schema).
+pub fn decode_app_frame_versioned(bytes: &[u8]) -> Result<(u16, NetMessage), ProtocolError> {
     if bytes.len() < APP_HEADER_LEN {
         return Err(ProtocolError::FrameTooShort(bytes.len()));
     }
     let ver = u16::from_le_bytes(bytes[0..2].try_into().unwrap());
-    if ver != PROTOCOL_VERSION {
+    if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&ver) {
         return Err(ProtocolError::UnsupportedVersion(ver));
     }
     let kind_wire = u16::from_le_bytes(bytes[2..4].try_into().unwrap());
@@ -178,14 +691,19 @@ pub fn decode_app_frame(bytes: &[u8]) -> Result<NetMessage, ProtocolError> {
         return Err(ProtocolError::UnknownMessageKind(kind_wire));
     };
     let _request_id = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
-    let msg = decode_message(&bytes[APP_HEADER_LEN..])?;
+    let body = &bytes[APP_HEADER_LEN..];
+    let msg = if ver == PROTOCOL_VERSION {
+        decode_message(body)?
+    } else {
+        legacy::decode_body(ver, body)?
+    };
     if message_kind(&msg) != kind {
         return Err(ProtocolError::KindMismatch {
             header: kind_wire,
             body: message_kind(&msg) as u16,
         });
     }
-    Ok(msg)
+    Ok((ver, msg))
 }
 
 #[cfg(test)]
@@ -195,8 +713,11 @@ mod tests {
     #[test]
     fn roundtrip_hello_app_frame() {
         let m = NetMessage::ClientHello {
-            protocol_version: PROTOCOL_VERSION,
+            min_protocol_version: MIN_PROTOCOL_VERSION,
+            max_protocol_version: PROTOCOL_VERSION,
+            capabilities: Capabilities::REGION_STREAMING,
             client_token: "test".into(),
+            public_key: vec![7; 32],
         };
         let b = encode_app_frame(&m).unwrap();
         let m2 = decode_app_frame(&b).unwrap();
@@ -210,9 +731,118 @@ mod tests {
             regions: vec![],
             prims: vec![],
             avatars: vec![],
+            input_seq: 3,
         };
         let b = encode_app_frame(&m).unwrap();
         let m2 = decode_app_frame(&b).unwrap();
         assert_eq!(m, m2);
     }
+
+    #[test]
+    fn roundtrip_avatar_update_app_frame() {
+        let m = NetMessage::AvatarsUpdated {
+            tick: 7,
+            updates: vec![AvatarDeltaDto {
+                id: 3,
+                position: Some(Vec3::new(1.0, 2.0, 3.0)),
+                yaw: None,
+                velocity: Some(Vec3::X),
+                anim: Some(AvatarAnim::Run),
+            }],
+            input_seq: 12,
+        };
+        let b = encode_app_frame(&m).unwrap();
+        let m2 = decode_app_frame(&b).unwrap();
+        assert_eq!(m, m2);
+    }
+
+    #[test]
+    fn roundtrip_prim_edit_app_frame() {
+        let m = NetMessage::EditPrim {
+            request_id: 9,
+            edit: PrimEdit::Recolor {
+                id: 5,
+                color: [0.1, 0.2, 0.3],
+            },
+        };
+        let b = encode_app_frame(&m).unwrap();
+        assert_eq!(message_request_id(&decode_app_frame(&b).unwrap()), 9);
+        assert_eq!(decode_app_frame(&b).unwrap(), m);
+    }
+
+    #[test]
+    fn roundtrip_chat_app_frame() {
+        let m = NetMessage::SendChat {
+            request_id: 4,
+            scope: ChatScope::Direct { avatar_id: 2 },
+            text: "hello there".into(),
+        };
+        let b = encode_app_frame(&m).unwrap();
+        assert_eq!(message_request_id(&decode_app_frame(&b).unwrap()), 4);
+        assert_eq!(decode_app_frame(&b).unwrap(), m);
+    }
+
+    #[test]
+    fn roundtrip_profiles_app_frame() {
+        let m = NetMessage::AvatarProfiles {
+            profiles: vec![AvatarProfileDto {
+                id: 3,
+                user_id: 12,
+                display_name: "Ada".into(),
+            }],
+        };
+        let b = encode_app_frame(&m).unwrap();
+        assert_eq!(decode_app_frame(&b).unwrap(), m);
+    }
+
+    #[test]
+    fn roundtrip_appearance_app_frame() {
+        let m = NetMessage::SetAppearance {
+            request_id: 9,
+            appearance: AvatarAppearance {
+                model: "fox".into(),
+                tint: [1.0, 0.5, 0.25],
+                scale: 1.5,
+            },
+        };
+        let b = encode_app_frame(&m).unwrap();
+        assert_eq!(message_request_id(&decode_app_frame(&b).unwrap()), 9);
+        assert_eq!(decode_app_frame(&b).unwrap(), m);
+    }
+
+    #[test]
+    fn negotiates_highest_common_version() {
+        assert_eq!(
+            negotiate_version(MIN_PROTOCOL_VERSION, PROTOCOL_VERSION + 3),
+            Some(PROTOCOL_VERSION)
+        );
+        assert_eq!(
+            negotiate_version(MIN_PROTOCOL_VERSION, MIN_PROTOCOL_VERSION),
+            Some(MIN_PROTOCOL_VERSION)
+        );
+        assert_eq!(negotiate_version(PROTOCOL_VERSION + 1, PROTOCOL_VERSION + 2), None);
+        assert_eq!(negotiate_version(1, MIN_PROTOCOL_VERSION - 1), None);
+    }
+
+    #[test]
+    fn rejects_frame_outside_supported_range() {
+        let mut b = encode_app_frame(&NetMessage::SnapshotAck { tick: 1 }).unwrap();
+        b[0..2].copy_from_slice(&(PROTOCOL_VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            decode_app_frame(&b),
+            Err(ProtocolError::UnsupportedVersion(v)) if v == PROTOCOL_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn error_codes_roundtrip_wire_values() {
+        for v in 0..32 {
+            if let Some(code) = ErrorCode::from_wire(v) {
+                assert_eq!(code as u32, v);
+            }
+        }
+        assert_eq!(ErrorCode::from_wire(1), Some(ErrorCode::VersionMismatch));
+        assert_eq!(ErrorCode::from_wire(8), Some(ErrorCode::Kicked));
+        assert_eq!(ErrorCode::from_wire(0), None);
+    }
 }
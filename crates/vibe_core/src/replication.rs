@@ -0,0 +1,223 @@
+//! Per-session avatar delta replication (ADR-010, ADR-011).
+//!
+//! The sim diffs each session's visible avatars against the **last tick the client acknowledged**
+//! ([`NetMessage::SnapshotAck`]). A field is resent when it differs from its value in *any* state sent
+//! since that tick, so the client can apply messages to whatever state it holds (any tick ≥ the ack)
+//! and still converge, even if intermediate frames were never delivered.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use crate::protocol::{AvatarDeltaDto, AvatarStateDto, NetMessage};
+
+/// Sent-but-unacknowledged states kept per session; past this the client gets a full resend.
+pub const MAX_UNACKED_TICKS: usize = 64;
+
+#[derive(Debug, Default)]
+pub struct AvatarReplicator {
+    acked_tick: u64,
+    /// States sent to the client, oldest first; the front entry is the acked baseline when known.
+    sent: VecDeque<(u64, HashMap<u64, AvatarStateDto>)>,
+}
+
+impl AvatarReplicator {
+    /// Record the client's highest applied tick. Acks never move backwards.
+    pub fn ack(&mut self, tick: u64) {
+        self.acked_tick = self.acked_tick.max(tick);
+        while self.sent.len() >= 2 && self.sent[1].0 <= self.acked_tick {
+            self.sent.pop_front();
+        }
+    }
+
+    /// Remember a full state sent out of band (e.g. the join [`NetMessage::WorldSnapshot`]).
+    pub fn record(&mut self, tick: u64, avatars: &[AvatarStateDto]) {
+        self.sent
+            .push_back((tick, avatars.iter().map(|a| (a.id, a.clone())).collect()));
+        while self.sent.len() > MAX_UNACKED_TICKS {
+            self.sent.pop_front();
+        }
+    }
+
+    /// Messages that bring the client from any state since its ack to `current` (ordered: removed,
+    /// added, updated; empty ones are omitted). Records `current` as sent when anything is emitted.
+    /// `input_seq` is echoed in `AvatarsUpdated` (ADR-010 prediction).
+    pub fn diff(
+        &mut self,
+        tick: u64,
+        input_seq: u32,
+        current: &[AvatarStateDto],
+    ) -> Vec<NetMessage> {
+        let baseline_known = self
+            .sent
+            .front()
+            .is_some_and(|(t, _)| *t <= self.acked_tick);
+        let window: Vec<&HashMap<u64, AvatarStateDto>> =
+            self.sent.iter().map(|(_, states)| states).collect();
+
+        let current_ids: BTreeSet<u64> = current.iter().map(|a| a.id).collect();
+        let removed: Vec<u64> = window
+            .iter()
+            .flat_map(|states| states.keys().copied())
+            .filter(|id| !current_ids.contains(id))
+            .collect::<BTreeSet<u64>>()
+            .into_iter()
+            .collect();
+
+        let mut added = Vec::new();
+        let mut updates = Vec::new();
+        for a in current {
+            let seen_throughout =
+                baseline_known && window.iter().all(|states| states.contains_key(&a.id));
+            if !seen_throughout {
+                added.push(a.clone());
+                continue;
+            }
+            let changed = |same: fn(&AvatarStateDto, &AvatarStateDto) -> bool| {
+                window.iter().any(|s| !same(&s[&a.id], a))
+            };
+            let position_changed = changed(|x, y| x.position == y.position);
+            let yaw_changed = changed(|x, y| x.yaw == y.yaw);
+            let velocity_changed = changed(|x, y| x.velocity == y.velocity);
+            let anim_changed = changed(|x, y| x.anim == y.anim);
+            if position_changed || yaw_changed || velocity_changed || anim_changed {
+                updates.push(AvatarDeltaDto {
+                    id: a.id,
+                    position: position_changed.then_some(a.position),
+                    yaw: yaw_changed.then_some(a.yaw),
+                    velocity: velocity_changed.then_some(a.velocity),
+                    anim: anim_changed.then_some(a.anim),
+                });
+            }
+        }
+
+        let mut out = Vec::new();
+        if !removed.is_empty() {
+            out.push(NetMessage::AvatarsRemoved { tick, ids: removed });
+        }
+        if !added.is_empty() {
+            out.push(NetMessage::AvatarsAdded {
+                tick,
+                avatars: added,
+            });
+        }
+        if !updates.is_empty() {
+            out.push(NetMessage::AvatarsUpdated {
+                tick,
+                updates,
+                input_seq,
+            });
+        }
+        if !out.is_empty() {
+            self.record(tick, current);
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protocol::AvatarAnim;
+    use glam::Vec3;
+
+    fn av(id: u64, x: f32, yaw: f32) -> AvatarStateDto {
+        AvatarStateDto {
+            id,
+            position: Vec3::new(x, 0.0, 0.0),
+            yaw,
+            velocity: Vec3::ZERO,
+            anim: AvatarAnim::Idle,
+        }
+    }
+
+    #[test]
+    fn first_diff_adds_everything() {
+        let mut r = AvatarReplicator::default();
+        let out = r.diff(1, 0, &[av(1, 0.0, 0.0), av(2, 1.0, 0.0)]);
+        assert_eq!(out.len(), 1);
+        assert!(matches!(&out[0], NetMessage::AvatarsAdded { avatars, .. } if avatars.len() == 2));
+    }
+
+    #[test]
+    fn acked_baseline_sends_only_changed_fields() {
+        let mut r = AvatarReplicator::default();
+        r.diff(1, 0, &[av(1, 0.0, 0.0), av(2, 1.0, 0.0)]);
+        r.ack(1);
+        assert!(r.diff(2, 0, &[av(1, 0.0, 0.0), av(2, 1.0, 0.0)]).is_empty());
+        let out = r.diff(3, 0, &[av(1, 5.0, 0.0), av(2, 1.0, 0.0)]);
+        assert_eq!(
+            out,
+            vec![NetMessage::AvatarsUpdated {
+                tick: 3,
+                updates: vec![AvatarDeltaDto {
+                    id: 1,
+                    position: Some(Vec3::new(5.0, 0.0, 0.0)),
+                    yaw: None,
+                    velocity: None,
+                    anim: None,
+                }],
+                input_seq: 0,
+            }]
+        );
+    }
+
+    #[test]
+    fn value_that_changed_and_reverted_is_resent_until_acked() {
+        let mut r = AvatarReplicator::default();
+        r.diff(1, 0, &[av(1, 0.0, 0.0)]);
+        r.ack(1);
+        r.diff(2, 0, &[av(1, 0.0, 1.0)]);
+        // Client may or may not have seen tick 2: yaw must be restated.
+        let out = r.diff(3, 0, &[av(1, 0.0, 0.0)]);
+        assert!(
+            matches!(&out[..], [NetMessage::AvatarsUpdated { updates, .. }] if updates[0].yaw == Some(0.0))
+        );
+        r.ack(3);
+        assert!(r.diff(4, 0, &[av(1, 0.0, 0.0)]).is_empty());
+    }
+
+    #[test]
+    fn motion_state_change_is_replicated() {
+        let mut r = AvatarReplicator::default();
+        r.diff(1, 0, &[av(1, 0.0, 0.0)]);
+        r.ack(1);
+        let mut running = av(1, 0.0, 0.0);
+        running.velocity = Vec3::X;
+        running.anim = AvatarAnim::Run;
+        let out = r.diff(2, 0, &[running]);
+        assert!(matches!(
+            &out[..],
+            [NetMessage::AvatarsUpdated { updates, .. }]
+                if updates[0].position.is_none()
+                    && updates[0].velocity == Some(Vec3::X)
+                    && updates[0].anim == Some(AvatarAnim::Run)
+        ));
+    }
+
+    #[test]
+    fn departed_avatar_is_removed() {
+        let mut r = AvatarReplicator::default();
+        r.diff(1, 0, &[av(1, 0.0, 0.0), av(2, 1.0, 0.0)]);
+        r.ack(1);
+        let out = r.diff(2, 0, &[av(1, 0.0, 0.0)]);
+        assert_eq!(
+            out,
+            vec![NetMessage::AvatarsRemoved {
+                tick: 2,
+                ids: vec![2]
+            }]
+        );
+    }
+
+    #[test]
+    fn state_ahead_of_ack_is_readded() {
+        let mut r = AvatarReplicator::default();
+        r.diff(1, 0, &[av(1, 0.0, 0.0)]);
+        r.ack(1);
+        r.diff(2, 0, &[av(1, 0.0, 0.0), av(2, 1.0, 0.0)]);
+        // Tick 2 not acked yet: avatar 2 is still "added", not "updated".
+        let out = r.diff(3, 0, &[av(1, 0.0, 0.0), av(2, 2.0, 0.0)]);
+        assert!(
+            matches!(&out[..], [NetMessage::AvatarsAdded { avatars, .. }] if avatars[0].id == 2)
+        );
+    }
+}
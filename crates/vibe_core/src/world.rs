@@ -21,6 +21,35 @@ impl TileKey {
     pub fn to_path(&self) -> String {
         format!("{}/{}/{}", self.z, self.x, self.y)
     }
+
+    /// The tile one zoom level up that covers this one; `None` at zoom 0.
+    #[must_use]
+    pub fn parent(&self) -> Option<TileKey> {
+        let z = self.z.checked_sub(1)?;
+        Some(Self::new(self.x.div_euclid(2), self.y.div_euclid(2), z))
+    }
+
+    /// The four tiles one zoom level down that make up this one: north-west, north-east,
+    /// south-west, south-east (x grows east, y south).
+    #[must_use]
+    pub fn children(&self) -> [TileKey; 4] {
+        let (x, y, z) = (self.x * 2, self.y * 2, self.z + 1);
+        [
+            Self::new(x, y, z),
+            Self::new(x + 1, y, z),
+            Self::new(x, y + 1, z),
+            Self::new(x + 1, y + 1, z),
+        ]
+    }
+
+    /// Whether this tile covers `other`: the same tile or one of its ancestors.
+    #[must_use]
+    pub fn contains(&self, other: &TileKey) -> bool {
+        let Some(depth) = other.z.checked_sub(self.z) else {
+            return false;
+        };
+        depth < 63 && other.x >> depth == self.x && other.y >> depth == self.y
+    }
 }
 
 const EARTH_RADIUS: f64 = 6_378_137.0;
@@ -29,6 +58,8 @@ const EARTH_CIRCUMFERENCE: f64 = 2.0 * std::f64::consts::PI * EARTH_RADIUS;
 /// Fixed zoom for region ground tiles (ADR-004 / ADR-006).
 pub const REGION_ZOOM_LEVEL: u32 = 17;
 pub const REGION_SIZE_METERS: f64 = 256.0;
+/// Deepest zoom region ground is refined to near the camera (ADR-004): 4×4 tiles per region tile.
+pub const MAX_GROUND_ZOOM_LEVEL: u32 = 19;
 
 /// WGS84 → OSM tile index (Web Mercator).
 #[must_use]
@@ -95,4 +126,20 @@ mod tests {
         assert_relative_eq!(lat, lat2, epsilon = 0.05);
         assert_relative_eq!(lng, lng2, epsilon = 0.05);
     }
+
+    #[test]
+    fn tile_quadtree_links() {
+        let key = TileKey::new(68_000, 42_000, REGION_ZOOM_LEVEL);
+        let children = key.children();
+        assert_eq!(children[3], TileKey::new(136_001, 84_001, 18));
+        for child in &children {
+            assert_eq!(child.parent().as_ref(), Some(&key));
+            assert!(key.contains(child) && !child.contains(&key));
+        }
+        let grandchild = children[1].children()[2].clone();
+        assert_eq!(grandchild, TileKey::new(272_002, 168_001, MAX_GROUND_ZOOM_LEVEL));
+        assert!(key.contains(&grandchild) && key.contains(&key));
+        assert!(!children[0].contains(&grandchild));
+        assert_eq!(TileKey::new(0, 0, 0).parent(), None);
+    }
 }
@@ -0,0 +1,30 @@
+-- Map tile cache index (ADR-004, ADR-021): one row per tile file under the cache directory.
+-- Caches from before this crate have `tiles` already, the client's without `content_type`; either
+-- way it is rebuilt in this layout, keeping its entries (tiles without a type are PNG).
+
+CREATE TABLE IF NOT EXISTS tiles (
+    path TEXT PRIMARY KEY,
+    etag TEXT,
+    last_modified TEXT,
+    expires_at INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    last_used INTEGER NOT NULL
+);
+
+CREATE TABLE tiles_v1 (
+    path TEXT PRIMARY KEY,
+    content_type TEXT NOT NULL DEFAULT 'image/png',
+    etag TEXT,
+    last_modified TEXT,
+    expires_at INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    last_used INTEGER NOT NULL
+);
+
+INSERT INTO tiles_v1 (path, etag, last_modified, expires_at, size, last_used)
+SELECT path, etag, last_modified, expires_at, size, last_used FROM tiles;
+
+DROP TABLE tiles;
+ALTER TABLE tiles_v1 RENAME TO tiles;
+
+CREATE INDEX IF NOT EXISTS idx_tiles_last_used ON tiles(last_used);
@@ -0,0 +1,334 @@
+//! On-disk map tile cache shared by the client (ADR-004) and the sim's tile proxy (ADR-021).
+//!
+//! Each tile is a file at `DIR/{path}.png`, where `path` is chosen by the caller. `DIR/index.db`
+//! (SQLite, schema in `migrations/`) keeps each tile's content type, ETag, Last-Modified, expiry
+//! and last use. Files are written aside and renamed into place, and the least recently used tiles
+//! are evicted above the size limit.
+
+use anyhow::Context;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+mod embedded {
+    use refinery::embed_migrations;
+    embed_migrations!("migrations");
+}
+
+/// Lifetime of a tile whose response names none; the OSM tile policy asks for at least 7 days.
+pub const DEFAULT_TTL_SECS: i64 = 7 * 24 * 60 * 60;
+
+/// Validators and expiry of a stored tile, as sent by the tile server.
+#[derive(Debug, Clone)]
+pub struct TileValidity {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+    /// Unix seconds after which the tile must be revalidated.
+    pub expires_at: i64,
+}
+
+impl TileValidity {
+    /// From response headers: `Cache-Control: max-age`, else [`DEFAULT_TTL_SECS`].
+    pub fn from_headers(
+        etag: Option<&str>,
+        last_modified: Option<&str>,
+        cache_control: Option<&str>,
+    ) -> Self {
+        let max_age = cache_control
+            .into_iter()
+            .flat_map(|v| v.split(','))
+            .find_map(|d| d.trim().strip_prefix("max-age=")?.parse::<i64>().ok())
+            .unwrap_or(DEFAULT_TTL_SECS);
+        Self {
+            etag: etag.map(str::to_owned),
+            last_modified: last_modified.map(str::to_owned),
+            expires_at: unix_now().saturating_add(max_age.max(0)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedTile {
+    pub bytes: Vec<u8>,
+    pub content_type: String,
+    pub validity: TileValidity,
+}
+
+impl CachedTile {
+    pub fn is_fresh(&self) -> bool {
+        unix_now() < self.validity.expires_at
+    }
+}
+
+/// The cache; safe to share between threads.
+pub struct TileDiskCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    index: Mutex<Connection>,
+}
+
+impl TileDiskCache {
+    pub fn open(dir: &Path, max_bytes: u64) -> anyhow::Result<Self> {
+        std::fs::create_dir_all(dir).with_context(|| format!("create_dir_all {dir:?}"))?;
+        let index_path = dir.join("index.db");
+        let mut conn = Connection::open(&index_path)
+            .with_context(|| format!("open tile cache index {index_path:?}"))?;
+        embedded::migrations::runner()
+            .run(&mut conn)
+            .context("migrate tile cache index")?;
+        Ok(Self {
+            dir: dir.to_owned(),
+            max_bytes,
+            index: Mutex::new(conn),
+        })
+    }
+
+    /// The tile stored at `path`, fresh or stale, and mark it used.
+    pub fn get(&self, path: &str) -> Option<CachedTile> {
+        let conn = self.index.lock().ok()?;
+        let row = conn
+            .query_row(
+                "SELECT content_type, etag, last_modified, expires_at FROM tiles WHERE path = ?1",
+                params![path],
+                |row| {
+                    let validity = TileValidity {
+                        etag: row.get(1)?,
+                        last_modified: row.get(2)?,
+                        expires_at: row.get(3)?,
+                    };
+                    Ok((row.get(0)?, validity))
+                },
+            )
+            .optional()
+            .unwrap_or_else(|e| {
+                tracing::warn!("tile cache index: {e}");
+                None
+            });
+        let (content_type, validity) = row?;
+        let Ok(bytes) = std::fs::read(self.file(path)) else {
+            // Removed behind our back; forget it.
+            let _ = conn.execute("DELETE FROM tiles WHERE path = ?1", params![path]);
+            return None;
+        };
+        let _ = conn.execute(
+            "UPDATE tiles SET last_used = ?2 WHERE path = ?1",
+            params![path, unix_now()],
+        );
+        Some(CachedTile {
+            bytes,
+            content_type,
+            validity,
+        })
+    }
+
+    /// Store a downloaded tile at `path`, then evict down to the size limit.
+    pub fn put(&self, path: &str, tile: &CachedTile) -> anyhow::Result<()> {
+        let file = self.file(path);
+        if let Some(parent) = file.parent() {
+            std::fs::create_dir_all(parent)
+                .with_context(|| format!("create_dir_all {parent:?}"))?;
+        }
+        // Written aside and renamed, so a crash never leaves half a tile under the real name.
+        let partial = file.with_extension("png.part");
+        std::fs::write(&partial, &tile.bytes).with_context(|| format!("write {partial:?}"))?;
+        std::fs::rename(&partial, &file).with_context(|| format!("rename to {file:?}"))?;
+        let conn = self
+            .index
+            .lock()
+            .map_err(|_| anyhow::anyhow!("tile cache index poisoned"))?;
+        conn.execute(
+            "INSERT OR REPLACE INTO tiles
+                 (path, content_type, etag, last_modified, expires_at, size, last_used)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            params![
+                path,
+                tile.content_type,
+                tile.validity.etag,
+                tile.validity.last_modified,
+                tile.validity.expires_at,
+                tile.bytes.len() as i64,
+                unix_now()
+            ],
+        )?;
+        self.evict(&conn, path)
+    }
+
+    /// Record that a stale tile was revalidated unchanged (HTTP 304).
+    pub fn refresh(&self, path: &str, validity: &TileValidity) -> anyhow::Result<()> {
+        let conn = self
+            .index
+            .lock()
+            .map_err(|_| anyhow::anyhow!("tile cache index poisoned"))?;
+        conn.execute(
+            "UPDATE tiles SET etag = ?2, last_modified = ?3, expires_at = ?4 WHERE path = ?1",
+            params![
+                path,
+                validity.etag,
+                validity.last_modified,
+                validity.expires_at
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Delete least recently used tiles (among those used in the same second, the first stored)
+    /// until the cache fits in `max_bytes`. `kept`, the tile just stored, is never deleted, even when it
+    /// alone is over the limit.
+    fn evict(&self, conn: &Connection, kept: &str) -> anyhow::Result<()> {
+        let total: i64 =
+            conn.query_row("SELECT COALESCE(SUM(size), 0) FROM tiles", [], |r| r.get(0))?;
+        let mut excess = total.saturating_sub(self.max_bytes as i64);
+        if excess <= 0 {
+            return Ok(());
+        }
+        let mut victims = Vec::new();
+        {
+            let mut stmt = conn.prepare(
+                "SELECT path, size FROM tiles WHERE path != ?1 ORDER BY last_used, rowid",
+            )?;
+            let mut rows = stmt.query([kept])?;
+            while excess > 0 {
+                let Some(row) = rows.next()? else { break };
+                let (path, size): (String, i64) = (row.get(0)?, row.get(1)?);
+                excess -= size;
+                victims.push(path);
+            }
+        }
+        for path in &victims {
+            let _ = std::fs::remove_file(self.file(path));
+            conn.execute("DELETE FROM tiles WHERE path = ?1", params![path])?;
+        }
+        tracing::debug!("tile cache evicted {} tiles", victims.len());
+        Ok(())
+    }
+
+    fn file(&self, path: &str) -> PathBuf {
+        self.dir.join(format!("{path}.png"))
+    }
+}
+
+pub fn unix_now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tile(bytes: &[u8]) -> CachedTile {
+        CachedTile {
+            bytes: bytes.to_vec(),
+            content_type: "image/png".into(),
+            validity: TileValidity::from_headers(Some("\"v1\""), None, Some("max-age=60")),
+        }
+    }
+
+    /// A cache of `max_bytes` in a fresh directory named after the test.
+    fn open(name: &str, max_bytes: u64) -> (TileDiskCache, PathBuf) {
+        let dir =
+            std::env::temp_dir().join(format!("vibe-tile-cache-{}-{name}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        (TileDiskCache::open(&dir, max_bytes).unwrap(), dir)
+    }
+
+    fn set_last_used(cache: &TileDiskCache, path: &str, last_used: i64) {
+        let conn = cache.index.lock().unwrap();
+        let sql = "UPDATE tiles SET last_used = ?2 WHERE path = ?1";
+        conn.execute(sql, params![path, last_used]).unwrap();
+    }
+
+    #[test]
+    fn stores_and_reads_back() {
+        let (cache, dir) = open("read", 100);
+        cache.put("17/1/1", &tile(b"aaaa")).unwrap();
+        let got = cache.get("17/1/1").unwrap();
+        assert_eq!(got.bytes, b"aaaa");
+        assert_eq!(got.content_type, "image/png");
+        assert_eq!(got.validity.etag.as_deref(), Some("\"v1\""));
+        assert!(got.is_fresh());
+        assert!(!dir.join("17/1/1.png.part").exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn evicts_the_least_recently_used() {
+        let (cache, dir) = open("lru", 10);
+        cache.put("17/1/1", &tile(b"aaaa")).unwrap();
+        cache.put("17/1/2", &tile(b"bbbb")).unwrap();
+        set_last_used(&cache, "17/1/1", 200);
+        set_last_used(&cache, "17/1/2", 100);
+        cache.put("17/1/3", &tile(b"cccc")).unwrap();
+        assert!(cache.get("17/1/2").is_none());
+        assert!(cache.get("17/1/1").is_some());
+        assert!(cache.get("17/1/3").is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn same_second_evicts_the_first_stored() {
+        let (cache, dir) = open("tie", 10);
+        let now = unix_now();
+        cache.put("17/1/1", &tile(b"aaaa")).unwrap();
+        cache.put("17/1/2", &tile(b"bbbb")).unwrap();
+        // The new tile is stored as used at `now` too.
+        set_last_used(&cache, "17/1/1", now);
+        set_last_used(&cache, "17/1/2", now);
+        cache.put("17/1/3", &tile(b"cccc")).unwrap();
+        assert!(cache.get("17/1/1").is_none());
+        assert!(cache.get("17/1/2").is_some());
+        assert!(cache.get("17/1/3").is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn an_oversize_tile_is_kept_alone() {
+        let (cache, dir) = open("oversize", 10);
+        cache.put("17/1/1", &tile(b"aaaa")).unwrap();
+        cache.put("17/1/2", &tile(&[b'b'; 12])).unwrap();
+        assert!(cache.get("17/1/1").is_none());
+        assert_eq!(cache.get("17/1/2").unwrap().bytes.len(), 12);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn upgrades_a_cache_from_before_migrations() {
+        let (cache, dir) = open("upgrade", 100);
+        drop(cache);
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("17/1")).unwrap();
+        std::fs::write(dir.join("17/1/1.png"), b"aaaa").unwrap();
+        let conn = Connection::open(dir.join("index.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE tiles (path TEXT PRIMARY KEY, etag TEXT, last_modified TEXT,
+                 expires_at INTEGER NOT NULL, size INTEGER NOT NULL, last_used INTEGER NOT NULL);
+             INSERT INTO tiles VALUES ('17/1/1', '\"old\"', NULL, 0, 4, 0);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let cache = TileDiskCache::open(&dir, 100).unwrap();
+        let got = cache.get("17/1/1").unwrap();
+        assert_eq!(got.bytes, b"aaaa");
+        assert_eq!(got.content_type, "image/png");
+        assert_eq!(got.validity.etag.as_deref(), Some("\"old\""));
+        assert!(!got.is_fresh());
+        drop(cache);
+        // Opened again, the migration is not rerun.
+        assert!(TileDiskCache::open(&dir, 100)
+            .unwrap()
+            .get("17/1/1")
+            .is_some());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn max_age_sets_the_expiry() {
+        let validity = TileValidity::from_headers(None, None, Some("public, max-age=100"));
+        assert!((validity.expires_at - unix_now() - 100).abs() <= 1);
+        let validity = TileValidity::from_headers(None, None, None);
+        assert!((validity.expires_at - unix_now() - DEFAULT_TTL_SECS).abs() <= 1);
+    }
+}
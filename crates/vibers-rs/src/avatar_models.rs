@@ -0,0 +1,81 @@
+//! Avatar model catalog (ADR-020): the glTF files avatars can wear, read from `avatars.toml` in the
+//! asset directory. Appearances name models by id; an id this client does not have is shown as the
+//! default model, so clients with different catalogs still see everyone.
+
+use anyhow::Context;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::Path;
+use vibe_core::appearance::DEFAULT_AVATAR_MODEL;
+
+/// Catalog file name, relative to the asset directory.
+pub const AVATAR_MODELS_FILE: &str = "avatars.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct AvatarModel {
+    pub id: String,
+    /// Asset path of the glTF file.
+    pub gltf: String,
+    /// Scene of the file to spawn.
+    #[serde(default)]
+    pub scene: usize,
+    /// Animation clip indices in the file.
+    pub idle_animation: usize,
+    pub run_animation: usize,
+    /// Transform scale that brings the model to avatar size; appearances scale relative to it.
+    pub scale: f32,
+}
+
+#[derive(Deserialize)]
+struct CatalogFile {
+    model: Vec<AvatarModel>,
+}
+
+/// The catalog; always holds [`DEFAULT_AVATAR_MODEL`].
+#[derive(Resource, Debug, Clone)]
+pub struct AvatarModels(Vec<AvatarModel>);
+
+impl Default for AvatarModels {
+    /// Bevy's sample fox only (models/animated/Fox.glb); clips 0 = Survey (idle), 2 = Run.
+    fn default() -> Self {
+        Self(vec![AvatarModel {
+            id: DEFAULT_AVATAR_MODEL.into(),
+            gltf: "models/animated/Fox.glb".into(),
+            scene: 0,
+            idle_animation: 0,
+            run_animation: 2,
+            scale: 0.02,
+        }])
+    }
+}
+
+impl AvatarModels {
+    /// Read the catalog; the built-in fox is added if the file does not define the default model.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("read avatar models {path:?}"))?;
+        let file: CatalogFile =
+            toml::from_str(&text).with_context(|| format!("parse avatar models {path:?}"))?;
+        let mut models = file.model;
+        if !models.iter().any(|m| m.id == DEFAULT_AVATAR_MODEL) {
+            models.extend(Self::default().0);
+        }
+        Ok(Self(models))
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        self.0.iter().any(|m| m.id == id)
+    }
+
+    /// The model with `id`, or the default one.
+    pub fn get(&self, id: &str) -> &AvatarModel {
+        let by_id = |id: &str| self.0.iter().find(|m| m.id == id);
+        by_id(id)
+            .or_else(|| by_id(DEFAULT_AVATAR_MODEL))
+            .unwrap_or(&self.0[0])
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.0.iter().map(|m| m.id.as_str())
+    }
+}
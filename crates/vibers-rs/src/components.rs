@@ -1,4 +1,5 @@
 use bevy::prelude::*;
+use vibe_core::{AvatarAnim, AvatarStateDto, InterpolationBuffer, PrimDto};
 
 #[derive(Component, Debug, Clone)]
 pub struct Region {
@@ -22,24 +23,37 @@ pub struct Prim {
     pub color: Color,
 }
 
+impl Prim {
+    /// Wire/storage form of this prim placed at `transform` (rotation as XYZ Euler radians).
+    pub fn to_dto(&self, transform: &Transform) -> PrimDto {
+        let (rx, ry, rz) = transform.rotation.to_euler(EulerRot::XYZ);
+        let color = self.color.to_srgba();
+        PrimDto {
+            id: self.id,
+            region_id: self.region_id,
+            name: self.name.clone(),
+            shape: self.shape.as_str().to_string(),
+            position: transform.translation,
+            rotation: Vec3::new(rx, ry, rz),
+            scale: transform.scale,
+            color: [color.red, color.green, color.blue],
+        }
+    }
+}
+
 #[derive(Component, Debug, Clone)]
 pub struct Avatar;
 
 /// Another client’s avatar (sim id from `WorldSnapshot`); same fox mesh as [`Avatar`].
-/// `net_*` is authoritative each tick; [`crate::systems::avatar::smooth_remote_avatars`] blends `Transform`.
-#[derive(Component, Debug, Clone, Copy)]
+/// [`crate::systems::avatar::smooth_remote_avatars`] plays `states` back a fixed delay behind the sim.
+#[derive(Component, Debug, Clone)]
 pub struct RemoteAvatar {
     pub sim_id: u64,
-    pub net_position: Vec3,
-    pub net_yaw: f32,
-}
-
-/// Horizontal speed (m/s) from visual motion, used for remote run vs idle animation.
-#[derive(Component, Debug, Clone, Copy, Default)]
-pub struct RemoteAvatarMotionHint {
-    pub last_translation: Vec3,
-    pub horizontal_speed: f32,
-    pub initialized: bool,
+    /// Newest replicated state; deltas are applied to it before it is buffered.
+    pub latest: AvatarStateDto,
+    pub states: InterpolationBuffer,
+    /// Animation of the state currently shown.
+    pub anim: AvatarAnim,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -62,6 +76,16 @@ impl PrimShape {
             _ => PrimShape::Box,
         }
     }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            PrimShape::Box => "box",
+            PrimShape::Sphere => "sphere",
+            PrimShape::Cylinder => "cylinder",
+            PrimShape::Cone => "cone",
+            PrimShape::Torus => "torus",
+        }
+    }
 }
 
 
@@ -1,3 +1,4 @@
+pub mod prims;
 pub mod schema;
 
 /// Helper function to calculate tile coordinates from lat/lng
@@ -0,0 +1,71 @@
+//! Writes to the local `prims` table (offline editing); the sim's own store is `vibers-sim::db`.
+
+use rusqlite::{params, Connection, Result};
+use vibe_core::PrimDto;
+
+/// Overwrite every stored field of an existing prim; returns whether the row existed.
+pub fn update_prim(conn: &Connection, prim: &PrimDto) -> Result<bool> {
+    let changed = conn.execute(
+        "UPDATE prims SET region_id = ?2, name = ?3, shape = ?4,
+             position_x = ?5, position_y = ?6, position_z = ?7,
+             rotation_x = ?8, rotation_y = ?9, rotation_z = ?10,
+             scale_x = ?11, scale_y = ?12, scale_z = ?13,
+             color_r = ?14, color_g = ?15, color_b = ?16,
+             updated_at = datetime('now')
+         WHERE id = ?1",
+        params![
+            prim.id,
+            prim.region_id,
+            prim.name,
+            prim.shape,
+            prim.position.x,
+            prim.position.y,
+            prim.position.z,
+            prim.rotation.x,
+            prim.rotation.y,
+            prim.rotation.z,
+            prim.scale.x,
+            prim.scale.y,
+            prim.scale.z,
+            prim.color[0],
+            prim.color[1],
+            prim.color[2],
+        ],
+    )?;
+    Ok(changed > 0)
+}
+
+/// Store a new prim (its `id` is ignored) and return the id SQLite assigned.
+pub fn insert_prim(conn: &Connection, prim: &PrimDto) -> Result<i64> {
+    conn.execute(
+        "INSERT INTO prims (region_id, name, shape,
+             position_x, position_y, position_z,
+             rotation_x, rotation_y, rotation_z,
+             scale_x, scale_y, scale_z,
+             color_r, color_g, color_b)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15)",
+        params![
+            prim.region_id,
+            prim.name,
+            prim.shape,
+            prim.position.x,
+            prim.position.y,
+            prim.position.z,
+            prim.rotation.x,
+            prim.rotation.y,
+            prim.rotation.z,
+            prim.scale.x,
+            prim.scale.y,
+            prim.scale.z,
+            prim.color[0],
+            prim.color[1],
+            prim.color[2],
+        ],
+    )?;
+    Ok(conn.last_insert_rowid())
+}
+
+/// Returns whether the prim existed.
+pub fn delete_prim(conn: &Connection, id: i64) -> Result<bool> {
+    Ok(conn.execute("DELETE FROM prims WHERE id = ?1", params![id])? > 0)
+}
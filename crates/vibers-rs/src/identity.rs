@@ -0,0 +1,44 @@
+//! Account keypair for `--connect` (ADR-009 login, `vibe_core::auth`): an Ed25519 key in PKCS#8,
+//! created on first use. Whoever holds the file is that account on every sim it has logged into.
+
+use anyhow::Context;
+use ring::rand::SystemRandom;
+use ring::signature::Ed25519KeyPair;
+use std::path::Path;
+
+/// Default location, next to the offline world database.
+pub const DEFAULT_IDENTITY_PATH: &str = "data/identity.pk8";
+
+pub fn load_or_create(path: &Path) -> anyhow::Result<Ed25519KeyPair> {
+    if path.exists() {
+        let pkcs8 = std::fs::read(path).with_context(|| format!("read identity {path:?}"))?;
+        return Ed25519KeyPair::from_pkcs8(&pkcs8)
+            .map_err(|e| anyhow::anyhow!("identity {path:?} is not an Ed25519 key: {e}"));
+    }
+    let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new())
+        .map_err(|_| anyhow::anyhow!("no system randomness for a new identity"))?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("create_dir_all {parent:?}"))?;
+    }
+    write_private(path, pkcs8.as_ref()).with_context(|| format!("write identity {path:?}"))?;
+    tracing::info!(?path, "created new identity");
+    Ed25519KeyPair::from_pkcs8(pkcs8.as_ref())
+        .map_err(|e| anyhow::anyhow!("generated identity rejected: {e}"))
+}
+
+#[cfg(unix)]
+fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::os::unix::fs::OpenOptionsExt;
+    std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(0o600)
+        .open(path)?
+        .write_all(bytes)
+}
+
+#[cfg(not(unix))]
+fn write_private(path: &Path, bytes: &[u8]) -> std::io::Result<()> {
+    std::fs::write(path, bytes)
+}
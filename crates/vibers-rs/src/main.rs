@@ -2,21 +2,31 @@ use bevy::asset::AssetPlugin;
 use bevy::pbr::light_consts::lux::AMBIENT_DAYLIGHT;
 use bevy::prelude::*;
 use bevy_atmosphere::prelude::*;
-use clap::Parser;
+use clap::{CommandFactory, Parser};
 use std::path::PathBuf;
+use vibe_core::appearance::{parse_tint, validate_appearance, DEFAULT_AVATAR_MODEL};
+use vibe_core::AvatarAppearance;
 
+mod avatar_models;
 mod components;
 mod db;
+mod identity;
 mod resources;
 mod systems;
+mod tile_disk_cache;
+mod tile_source;
 mod utils;
 
+use avatar_models::{AvatarModels, AVATAR_MODELS_FILE};
 use components::Avatar;
 use resources::{
-    AvatarState, CameraState, ConnectAddr, Database, GameState, LocalAvatarSimId, MouseState,
-    OsmTileUrlTemplate,
+    AvatarAppearances, AvatarProfiles, AvatarState, CameraState, ConnectAddr, Database,
+    DisplayName, EditHistory, GameState, IdentityPath, LocalAppearance, LocalAvatarSimId,
+    MouseState, OsmTileUrlTemplate,
 };
 use systems::*;
+use tile_disk_cache::TileDiskCache;
+use tile_source::HttpTiles;
 
 #[derive(Parser, Debug)]
 #[command(name = "vibers-rs")]
@@ -24,6 +34,34 @@ struct Cli {
     /// Connect to a `vibers-sim` instance (TCP, postcard messages).
     #[arg(long)]
     connect: Option<String>,
+    /// Account key used to log in with `--connect`; created on first use.
+    #[arg(long, default_value = identity::DEFAULT_IDENTITY_PATH)]
+    identity: PathBuf,
+    /// Display name shown above your avatar with `--connect`; the sim keeps it for the account.
+    #[arg(long)]
+    name: Option<String>,
+    /// Avatar model, by id from `assets/avatars.toml`.
+    #[arg(long, default_value = DEFAULT_AVATAR_MODEL)]
+    avatar_model: String,
+    /// Avatar tint as an RRGGBB hex color; white keeps the model's own colors.
+    #[arg(long, default_value = "ffffff", value_parser = parse_tint)]
+    avatar_tint: [f32; 3],
+    /// Avatar size relative to the model's (0.5 to 2).
+    #[arg(long, default_value_t = 1.0)]
+    avatar_scale: f32,
+    /// Map tile source, instead of the sim's: an `http(s)://` URL template with `{z}`, `{x}`,
+    /// `{y}`, `file://DIR` for a `{z}/{x}/{y}.png` tree or `mbtiles://FILE`.
+    #[arg(long)]
+    tile_url: Option<String>,
+    /// Directory of the on-disk map tile cache.
+    #[arg(long, default_value = tile_disk_cache::DEFAULT_TILE_CACHE_DIR)]
+    tile_cache_dir: PathBuf,
+    /// Tile cache size limit in MiB; the least recently used tiles are evicted beyond it.
+    #[arg(long, default_value_t = tile_disk_cache::DEFAULT_TILE_CACHE_MB)]
+    tile_cache_mb: u64,
+    /// Show only map tiles already in the tile cache; never download any.
+    #[arg(long)]
+    offline_tiles: bool,
 }
 
 fn main() {
@@ -41,6 +79,27 @@ fn main() {
         .join("../../assets")
         .display()
         .to_string();
+    let models_path = PathBuf::from(&asset_dir).join(AVATAR_MODELS_FILE);
+    let models = AvatarModels::load(&models_path).unwrap_or_else(|e| {
+        tracing::warn!("{e:#}; only the built-in fox is available");
+        AvatarModels::default()
+    });
+    let appearance = AvatarAppearance {
+        model: cli.avatar_model,
+        tint: cli.avatar_tint,
+        scale: cli.avatar_scale,
+    };
+    if let Err(e) = validate_appearance(&appearance) {
+        Cli::command().error(clap::error::ErrorKind::InvalidValue, e).exit();
+    }
+    if !models.contains(&appearance.model) {
+        let known = models.ids().collect::<Vec<_>>().join(", ");
+        let e = format!("unknown avatar model {:?} (known: {known})", appearance.model);
+        Cli::command().error(clap::error::ErrorKind::InvalidValue, e).exit();
+    }
+    let tile_disk_cache = TileDiskCache::open(&cli.tile_cache_dir, cli.tile_cache_mb << 20)
+        .map_err(|e| tracing::warn!("{e:#}; map tiles will not be cached on disk"))
+        .ok();
     app.add_plugins((
         DefaultPlugins
             .set(WindowPlugin {
@@ -63,11 +122,25 @@ fn main() {
     .init_resource::<LocalAvatarSimId>()
     .init_resource::<CameraState>()
     .init_resource::<MouseState>()
-    .init_resource::<systems::tile_loader::TileCache>()
+    .init_resource::<build::BuildMode>()
+    .init_resource::<chat::ChatLog>()
+    .init_resource::<EditHistory>()
+    .init_resource::<AvatarProfiles>()
+    .init_resource::<AvatarAppearances>()
+    .insert_resource(LocalAppearance(appearance))
+    .insert_resource(models)
+    .insert_resource(systems::tile_loader::TileCache::new(
+        HttpTiles::new(tile_disk_cache, cli.offline_tiles),
+        cli.tile_url,
+    ))
     .init_resource::<OsmTileUrlTemplate>();
 
     if let Some(addr) = cli.connect {
         app.insert_resource(ConnectAddr(addr));
+        app.insert_resource(IdentityPath(cli.identity));
+        if let Some(name) = cli.name {
+            app.insert_resource(DisplayName(name));
+        }
     }
 
     app.add_systems(
@@ -76,10 +149,13 @@ fn main() {
             database::init_database.run_if(no_connect_addr),
             network::spawn_network_thread.run_if(has_connect_addr),
             systems::free_camera::setup_camera,
+            overlay::setup_overlay_camera,
+            chat::setup_chat_overlay,
             spawn_avatar_entity,
             setup_sky,
         ),
     )
+    .add_systems(PreUpdate, chat::chat_keys.after(bevy::input::InputSystem))
     .add_systems(
         Update,
         (
@@ -101,6 +177,7 @@ fn main() {
     .add_systems(
         Update,
         (
+            systems::ground_lod::update_ground_lod.after(systems::free_camera::camera_controls),
             systems::tile_loader::load_region_tiles,
             rendering::update_region_materials,
         ),
@@ -112,18 +189,28 @@ fn main() {
             avatar::handle_avatar_movement.after(network::apply_network_snapshot),
             avatar::smooth_online_avatar_display
                 .after(network::apply_network_snapshot)
-                .after(avatar::handle_avatar_movement),
+                .after(network::send_network_intent),
             avatar::smooth_remote_avatars.after(avatar::smooth_online_avatar_display),
-            avatar::tick_remote_avatar_motion_hint.after(avatar::smooth_remote_avatars),
             network::send_network_intent.after(avatar::handle_avatar_movement),
-            network::send_observer_update
-                .after(network::apply_network_snapshot)
-                .after(avatar::handle_avatar_movement),
-            systems::free_camera::camera_controls.after(avatar::smooth_online_avatar_display),
+            hud::show_window_status.after(selection::highlight_selection),
+            chat::show_chat.after(network::apply_network_snapshot),
+            name_tags::update_name_tags
+                .after(avatar::smooth_remote_avatars)
+                .after(systems::free_camera::camera_controls),
+            build::build_keys.after(rendering::spawn_prims),
+            build::build_drag.after(build::build_keys),
+            selection::pick_prim
+                .after(rendering::spawn_prims)
+                .after(build::build_keys),
+            selection::highlight_selection.after(selection::pick_prim),
+            systems::free_camera::camera_controls
+                .after(avatar::smooth_online_avatar_display)
+                .after(build::build_drag),
             avatar::spawn_avatar,
             avatar::update_fox_animation.after(avatar::handle_avatar_movement),
-            avatar::update_remote_fox_animation.after(avatar::tick_remote_avatar_motion_hint),
+            avatar::update_remote_fox_animation.after(avatar::smooth_remote_avatars),
             systems::debug::debug_region_entities.after(rendering::spawn_regions),
+            systems::debug::debug_network_clock,
         ),
     );
 
@@ -145,7 +232,7 @@ fn has_database(db: Option<Res<Database>>) -> bool {
 fn spawn_avatar_entity(mut commands: Commands) {
     commands.spawn((
         Avatar,
-        Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)).with_scale(Vec3::splat(0.02)),
+        Transform::from_translation(Vec3::new(0.0, 0.0, 0.0)),
     ));
 }
 
@@ -1,8 +1,12 @@
 use bevy::prelude::*;
 use rusqlite::Connection;
+use std::collections::HashMap;
 use std::sync::{Arc, Mutex, MutexGuard};
 use tokio::sync::mpsc::UnboundedSender;
-use vibe_core::NetMessage;
+use vibe_core::{
+    AvatarAppearance, AvatarProfileDto, Capabilities, ClockSync, NetMessage, Predictor, PrimDto,
+    TickClock,
+};
 
 #[derive(Resource)]
 pub struct Database {
@@ -20,7 +24,7 @@ pub struct GameState {
 pub struct AvatarState {
     /// Authoritative sim position (from server when online).
     pub position: Vec3,
-    /// Visual follow target: smoothed toward `position` online so camera and mesh do not stutter at tick rate.
+    /// Visual follow target: predicted position online (or smoothed toward `position` without prediction).
     pub display_position: Vec3,
     /// Offline / local yaw for tank controls (radians).
     pub rotation: f32,
@@ -99,10 +103,61 @@ impl Default for OsmTileUrlTemplate {
     }
 }
 
+/// Smoothed RTT and sim clock offset from heartbeats; written by the network thread (ADR-008).
+#[derive(Resource, Clone, Default)]
+pub struct NetworkClock(pub Arc<Mutex<ClockSync>>);
+
+impl NetworkClock {
+    pub fn snapshot(&self) -> ClockSync {
+        self.0.lock().map(|c| *c).unwrap_or_default()
+    }
+}
+
+/// Estimated current sim tick, fed by every ticked message; `None` until the hello (ADR-011).
+#[derive(Resource, Default)]
+pub struct ServerTickClock(pub Option<TickClock>);
+
+/// Online local-avatar prediction (ADR-010). Disabled against sims that do not echo `input_seq`.
+#[derive(Resource, Default)]
+pub struct LocalPrediction {
+    pub enabled: bool,
+    pub predictor: Predictor,
+    /// Visual offset left by the last correction; decays so reconciliation never snaps the camera.
+    pub correction: Vec3,
+}
+
 /// When set, client connects to `vibers-sim` instead of loading local SQLite world.
 #[derive(Resource, Clone)]
 pub struct ConnectAddr(pub String);
 
+/// Account key file for logging in to the sim (see `identity`).
+#[derive(Resource, Clone)]
+pub struct IdentityPath(pub std::path::PathBuf);
+
+/// Name to give our account after logging in (ADR-019); without it the sim keeps the stored one.
+#[derive(Resource, Clone)]
+pub struct DisplayName(pub String);
+
+/// Who the sim's avatars are, by avatar id, from `AvatarProfiles` (ADR-019). Avatar ids are not
+/// reused, so entries are kept for the session.
+#[derive(Resource, Default)]
+pub struct AvatarProfiles(pub HashMap<u64, AvatarProfileDto>);
+
+impl AvatarProfiles {
+    pub fn name(&self, avatar_id: u64) -> Option<&str> {
+        self.0.get(&avatar_id).map(|p| p.display_name.as_str())
+    }
+}
+
+/// How our avatar looks (ADR-020), from the command line; sent to the sim after logging in.
+#[derive(Resource, Clone, Default)]
+pub struct LocalAppearance(pub AvatarAppearance);
+
+/// How the sim's avatars look, by avatar id, from `AvatarAppearances` (ADR-020); kept for the
+/// session like [`AvatarProfiles`]. Avatars not in it wear the default appearance.
+#[derive(Resource, Default)]
+pub struct AvatarAppearances(pub HashMap<u64, AvatarAppearance>);
+
 #[derive(Resource)]
 pub struct OnlineSession {
     pub intent_tx: UnboundedSender<NetMessage>,
@@ -112,8 +167,118 @@ pub struct OnlineSession {
 #[derive(Resource, Default)]
 pub struct NetworkSyncState {
     pub received_initial_world: bool,
+    /// Features the sim enabled for this session (`ServerHelloAck`); empty until then.
+    pub capabilities: Capabilities,
 }
 
+/// Online prim edits sent but not yet acknowledged, by request id; a refused edit is rolled back to
+/// its `before`, an applied one goes into the [`EditHistory`] (ADR-011 write path).
+#[derive(Resource)]
+pub struct PrimEditRequests {
+    next_request_id: u32,
+    pub pending: HashMap<u32, PendingEdit>,
+    /// Prim we created whose `PrimUpdated` has not arrived yet; it is selected when it does.
+    pub select_on_arrival: Option<i64>,
+}
+
+impl Default for PrimEditRequests {
+    fn default() -> Self {
+        Self {
+            next_request_id: 1,
+            pending: HashMap::new(),
+            select_on_arrival: None,
+        }
+    }
+}
+
+impl PrimEditRequests {
+    /// Fresh non-zero id (0 means "no request" on the wire).
+    pub fn next_request_id(&mut self) -> u32 {
+        let id = self.next_request_id;
+        self.next_request_id = id.checked_add(1).unwrap_or(1);
+        id
+    }
+}
+
+/// An online edit awaiting its ack.
+pub struct PendingEdit {
+    /// `before` is `None` for a create, whose `after.id` is only known from the ack.
+    pub op: PrimOp,
+    pub origin: EditOrigin,
+    pub step: u32,
+}
+
+/// A prim as it was before and after one of our edits (`None`: it did not exist).
+#[derive(Clone, Debug)]
+pub struct PrimOp {
+    pub before: Option<PrimDto>,
+    pub after: Option<PrimDto>,
+}
+
+/// Why an edit was made, which decides the stack its [`PrimOp`] goes to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EditOrigin {
+    /// A new change; it clears the redo stack.
+    Do,
+    Undo,
+    Redo,
+}
+
+/// Undo/redo over this user's own prim edits. A step is every edit submitted in one frame (an
+/// array duplicate undoes as one); undoing a step restores each operation's `before`, latest first,
+/// and those restores form the redo step.
+#[derive(Resource, Default)]
+pub struct EditHistory {
+    undo: Vec<(u32, Vec<PrimOp>)>,
+    redo: Vec<(u32, Vec<PrimOp>)>,
+}
+
+impl EditHistory {
+    /// Add an applied edit to the stack `origin` feeds.
+    pub fn record(&mut self, origin: EditOrigin, step: u32, op: PrimOp) {
+        let stack = match origin {
+            EditOrigin::Do => {
+                self.redo.clear();
+                &mut self.undo
+            }
+            EditOrigin::Undo => &mut self.redo,
+            EditOrigin::Redo => &mut self.undo,
+        };
+        match stack.last_mut() {
+            Some((last, ops)) if *last == step => ops.push(op),
+            _ => stack.push((step, vec![op])),
+        }
+    }
+
+    /// Latest step to revert for an undo (or redo).
+    pub fn take(&mut self, origin: EditOrigin) -> Option<Vec<PrimOp>> {
+        let stack = match origin {
+            EditOrigin::Undo => &mut self.undo,
+            EditOrigin::Redo => &mut self.redo,
+            EditOrigin::Do => return None,
+        };
+        stack.pop().map(|(_, ops)| ops)
+    }
+
+    /// A deleted prim was recreated under a new id: point the recorded operations at it.
+    pub fn remap(&mut self, from: i64, to: i64) {
+        let prims = self
+            .undo
+            .iter_mut()
+            .chain(&mut self.redo)
+            .flat_map(|(_, ops)| ops);
+        for p in prims.flat_map(|op| op.before.iter_mut().chain(&mut op.after)) {
+            if p.id == from {
+                p.id = to;
+            }
+        }
+    }
+}
+
+/// Latest `ServerError` from the sim, already phrased for the user (shown in the window title).
+#[derive(Resource, Default)]
+pub struct ServerNotice(pub Option<String>);
+
 /// Set from `ServerHelloAck.your_avatar_id` so we can pick the local row in `WorldSnapshot::avatars`.
 #[derive(Resource, Default, Clone, Copy)]
 pub struct LocalAvatarSimId(pub Option<u64>);
@@ -3,25 +3,19 @@ use bevy::ecs::query::Or;
 use bevy::gltf::GltfAssetLabel;
 use bevy::prelude::*;
 use bevy::scene::SceneInstanceReady;
-use crate::components::{Avatar, RemoteAvatar, RemoteAvatarMotionHint};
-use crate::resources::AvatarState;
-use vibe_core::wrap_angle_pi;
-
-// Official Bevy fox model (models/animated/Fox.glb)
-const FOX_GLB: &str = "models/animated/Fox.glb";
-// Bevy fox animations: 0=Survey (idle), 1=Walk, 2=Run
-const IDLE_ANIMATION_INDEX: usize = 0;
-const RUN_ANIMATION_INDEX: usize = 2;
+use crate::avatar_models::AvatarModels;
+use crate::components::{Avatar, RemoteAvatar};
+use crate::resources::{
+    AvatarAppearances, AvatarState, LocalAppearance, LocalPrediction, ServerTickClock,
+};
+use vibe_core::{step_position, wrap_angle_pi, AvatarAnim, AvatarAppearance, MoveInput};
 
 /// Online: blend visual toward authoritative sim position (see `smooth_online_avatar_display`).
 const ONLINE_DISPLAY_SMOOTHING: f32 = 14.0;
-/// Remote rotation should feel a bit snappier than position.
-const REMOTE_ROT_SMOOTHING: f32 = 18.0;
-/// Horizontal visual speed (m/s) above this plays run animation on remote foxes.
-const REMOTE_RUN_SPEED_THRESH: f32 = 0.12;
-
-const WALK_SPEED: f32 = 8.0;
-const FLY_SPEED: f32 = 40.0;
+/// Online with prediction: rate at which a reconciliation error fades out of the displayed position.
+const CORRECTION_SMOOTHING: f32 = 10.0;
+/// Larger corrections (teleports, long stalls) are shown at once instead of blended.
+const CORRECTION_SNAP_DISTANCE: f32 = 4.0;
 
 /// Horizontal forward from orbit [`CameraState::azimuth`] (into the screen / away from camera).
 #[inline]
@@ -71,14 +65,39 @@ pub(crate) fn fox_facing_yaw_from_camera(azimuth: f32) -> f32 {
     let f = camera_plane_forward(azimuth);
     wrap_angle_pi(f32::atan2(f.x, f.z))
 }
-const GRAVITY: f32 = -9.8;
-const AVATAR_HEIGHT: f32 = 0.8; // Fox model height (scaled)
-const GROUND_HEIGHT: f32 = 0.05; // Region tile top surface (cuboid half-extent y)
 
+/// One frame of WASD / Space / Shift input as a world-space [`MoveInput`] (camera-relative, fox faces
+/// into the view). Shared by offline movement and the online prediction in `send_network_intent`.
+pub(crate) fn sample_move_input(
+    keyboard_input: &ButtonInput<KeyCode>,
+    azimuth: f32,
+    flying: bool,
+) -> MoveInput {
+    let move_forward =
+        keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp);
+    let move_backward =
+        keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown);
+    let move_left =
+        keyboard_input.pressed(KeyCode::KeyA) || keyboard_input.pressed(KeyCode::ArrowLeft);
+    let move_right =
+        keyboard_input.pressed(KeyCode::KeyD) || keyboard_input.pressed(KeyCode::ArrowRight);
+    let wish = wish_dir_camera_relative(azimuth, move_forward, move_backward, move_left, move_right);
+    MoveInput {
+        move_x: wish.x,
+        move_z: wish.z,
+        display_yaw: fox_facing_yaw_from_camera(azimuth),
+        fly_up: keyboard_input.pressed(KeyCode::Space),
+        fly_down: keyboard_input.pressed(KeyCode::ShiftLeft)
+            || keyboard_input.pressed(KeyCode::ShiftRight),
+        flying,
+    }
+}
+
+/// Appearance an avatar's model was spawned with (ADR-020).
 #[derive(Component)]
-pub struct AvatarFoxLoaded;
+pub struct AvatarLook(AvatarAppearance);
 
-/// Component storing animation data for the fox, used when scene is ready
+/// Component storing animation data for the avatar's model, used when scene is ready
 #[derive(Component)]
 pub(crate) struct FoxAnimationToPlay {
     graph_handle: Handle<AnimationGraph>,
@@ -86,32 +105,61 @@ pub(crate) struct FoxAnimationToPlay {
     run_index: AnimationNodeIndex,
 }
 
+type AvatarRoot<'a> = (
+    Entity,
+    &'a mut Transform,
+    Option<&'a RemoteAvatar>,
+    Option<&'a AvatarLook>,
+);
+
+/// Give each avatar the model, clips, scale and tint of its appearance: ours from the command
+/// line, others' from the sim (the default until theirs arrives). When an appearance changes, the
+/// new scene replaces the old one.
 pub fn spawn_avatar(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     mut graphs: ResMut<Assets<AnimationGraph>>,
-    avatar_query: Query<Entity, (Without<AvatarFoxLoaded>, Or<(With<Avatar>, With<RemoteAvatar>)>)>,
+    models: Res<AvatarModels>,
+    local: Res<LocalAppearance>,
+    remote: Res<AvatarAppearances>,
+    mut avatar_query: Query<AvatarRoot, Or<(With<Avatar>, With<RemoteAvatar>)>>,
 ) {
-    for entity in avatar_query.iter() {
-        let (graph, indices) = AnimationGraph::from_clips([
-            asset_server.load(GltfAssetLabel::Animation(IDLE_ANIMATION_INDEX).from_asset(FOX_GLB)),
-            asset_server.load(GltfAssetLabel::Animation(RUN_ANIMATION_INDEX).from_asset(FOX_GLB)),
-        ]);
+    let default = AvatarAppearance::default();
+    for (entity, mut transform, remote_avatar, look) in avatar_query.iter_mut() {
+        let wanted = match remote_avatar {
+            Some(r) => remote.0.get(&r.sim_id).unwrap_or(&default),
+            None => &local.0,
+        };
+        if look.is_some_and(|l| &l.0 == wanted) {
+            continue;
+        }
+        let model = models.get(&wanted.model);
+        let clip = |index| {
+            asset_server.load(GltfAssetLabel::Animation(index).from_asset(model.gltf.clone()))
+        };
+        let (graph, indices) =
+            AnimationGraph::from_clips([clip(model.idle_animation), clip(model.run_animation)]);
         let graph_handle = graphs.add(graph);
 
         let mesh_scene = SceneRoot(
-            asset_server.load(GltfAssetLabel::Scene(0).from_asset(FOX_GLB)),
+            asset_server.load(GltfAssetLabel::Scene(model.scene).from_asset(model.gltf.clone())),
         );
+        transform.scale = Vec3::splat(model.scale * wanted.scale);
 
-        commands.entity(entity).insert((
-            AvatarFoxLoaded,
+        let mut avatar = commands.entity(entity);
+        avatar.insert((
+            AvatarLook(wanted.clone()),
             FoxAnimationToPlay {
                 graph_handle,
                 idle_index: indices[0],
                 run_index: indices[1],
             },
             mesh_scene,
-        )).observe(play_fox_animation_when_ready);
+        ));
+        // A replaced `SceneRoot` is respawned and triggers the same observer again.
+        if look.is_none() {
+            avatar.observe(play_fox_animation_when_ready);
+        }
     }
 }
 
@@ -119,10 +167,13 @@ fn play_fox_animation_when_ready(
     trigger: Trigger<SceneInstanceReady>,
     mut commands: Commands,
     children: Query<&Children>,
-    animations_to_play: Query<&FoxAnimationToPlay>,
+    animations_to_play: Query<(&FoxAnimationToPlay, &AvatarLook)>,
     mut players: Query<&mut AnimationPlayer>,
+    mesh_materials: Query<&MeshMaterial3d<StandardMaterial>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    if let Ok(animation_to_play) = animations_to_play.get(trigger.target()) {
+    if let Ok((animation_to_play, look)) = animations_to_play.get(trigger.target()) {
+        let tint = look.0.tint;
         for child in children.iter_descendants(trigger.target()) {
             if let Ok(mut player) = players.get_mut(child) {
                 // Start with idle; update_fox_animation will switch based on movement
@@ -131,10 +182,40 @@ fn play_fox_animation_when_ready(
                     .entity(child)
                     .insert(AnimationGraphHandle(animation_to_play.graph_handle.clone()));
             }
+            // Tinted avatars get their own copy of the model's materials.
+            if tint == [1.0; 3] {
+                continue;
+            }
+            let Some(tinted) = mesh_materials
+                .get(child)
+                .ok()
+                .and_then(|m| materials.get(&m.0))
+                .map(|m| tinted_material(m, tint))
+            else {
+                continue;
+            };
+            commands
+                .entity(child)
+                .insert(MeshMaterial3d(materials.add(tinted)));
         }
     }
 }
 
+/// `material` with its base color multiplied by `tint` (sRGB channels).
+fn tinted_material(material: &StandardMaterial, tint: [f32; 3]) -> StandardMaterial {
+    let base = material.base_color.to_linear();
+    let tint = Color::srgb(tint[0], tint[1], tint[2]).to_linear();
+    StandardMaterial {
+        base_color: Color::LinearRgba(LinearRgba::new(
+            base.red * tint.red,
+            base.green * tint.green,
+            base.blue * tint.blue,
+            base.alpha,
+        )),
+        ..material.clone()
+    }
+}
+
 /// Switch fox animation between idle and run based on movement
 pub fn update_fox_animation(
     avatar_state: Res<AvatarState>,
@@ -165,69 +246,53 @@ pub fn update_fox_animation(
     }
 }
 
-/// Interpolate remote entities toward `RemoteAvatar::net_*` so motion matches the local smoothed feel.
+/// Show each remote avatar's buffered state at the interpolation delay behind the estimated sim tick
+/// (extrapolated briefly when the buffer runs dry); before the first tick estimate, its newest state.
 pub fn smooth_remote_avatars(
     online: Option<Res<crate::resources::OnlineSession>>,
+    tick_clock: Option<Res<ServerTickClock>>,
     time: Res<Time>,
-    mut q: Query<(&RemoteAvatar, &mut Transform), (With<RemoteAvatar>, Without<Avatar>)>,
+    mut q: Query<(&mut RemoteAvatar, &mut Transform), Without<Avatar>>,
 ) {
     if online.is_none() {
         return;
     }
-    let dt = time.delta_secs();
-    let a_pos = 1.0 - (-ONLINE_DISPLAY_SMOOTHING * dt).exp();
-    let a_rot = 1.0 - (-REMOTE_ROT_SMOOTHING * dt).exp();
-    for (remote, mut tf) in q.iter_mut() {
-        tf.translation = tf.translation.lerp(remote.net_position, a_pos);
-        // Replicated world yaw from each client’s camera-facing convention.
-        let target_rot = Quat::from_rotation_y(wrap_angle_pi(remote.net_yaw));
-        tf.rotation = tf.rotation.slerp(target_rot, a_rot);
-    }
-}
-
-/// Derive horizontal speed from smoothed remote motion for animation blending.
-pub fn tick_remote_avatar_motion_hint(
-    online: Option<Res<crate::resources::OnlineSession>>,
-    time: Res<Time>,
-    mut q: Query<(&Transform, &mut RemoteAvatarMotionHint), With<RemoteAvatar>>,
-) {
-    if online.is_none() {
-        return;
-    }
-    let dt = time.delta_secs().max(1e-4);
-    for (tf, mut hint) in q.iter_mut() {
-        if !hint.initialized {
-            hint.last_translation = tf.translation;
-            hint.initialized = true;
-            hint.horizontal_speed = 0.0;
+    let clock = tick_clock.and_then(|c| c.0);
+    let render_tick = clock.and_then(|c| c.render_tick(time.elapsed_secs_f64()));
+    for (mut remote, mut tf) in q.iter_mut() {
+        let shown = match (clock, render_tick) {
+            (Some(c), Some(t)) => {
+                let s = remote.states.sample(t, c.tick_hz());
+                remote.states.discard_before(t);
+                s
+            }
+            _ => remote.states.latest().copied(),
+        };
+        let Some(s) = shown else {
             continue;
-        }
-        let dx = tf.translation.x - hint.last_translation.x;
-        let dz = tf.translation.z - hint.last_translation.z;
-        hint.horizontal_speed = (dx * dx + dz * dz).sqrt() / dt;
-        hint.last_translation = tf.translation;
+        };
+        tf.translation = s.position;
+        tf.rotation = Quat::from_rotation_y(wrap_angle_pi(s.yaw));
+        remote.anim = s.anim;
     }
 }
 
-/// Run / idle for other players’ foxes based on [`RemoteAvatarMotionHint::horizontal_speed`].
+/// Run / idle for other players’ foxes from the replicated [`AvatarAnim`] being shown.
 pub fn update_remote_fox_animation(
     online: Option<Res<crate::resources::OnlineSession>>,
     children: Query<&Children>,
     animations_to_play: Query<&FoxAnimationToPlay, With<RemoteAvatar>>,
-    hints: Query<&RemoteAvatarMotionHint, With<RemoteAvatar>>,
     mut players: Query<&mut AnimationPlayer>,
-    remote_roots: Query<Entity, With<RemoteAvatar>>,
+    remote_roots: Query<(Entity, &RemoteAvatar)>,
 ) {
     if online.is_none() {
         return;
     }
-    for avatar_entity in remote_roots.iter() {
+    for (avatar_entity, remote) in remote_roots.iter() {
         let Ok(animation_to_play) = animations_to_play.get(avatar_entity) else {
             continue;
         };
-        let is_walking = hints
-            .get(avatar_entity)
-            .is_ok_and(|h| h.horizontal_speed > REMOTE_RUN_SPEED_THRESH);
+        let is_walking = remote.anim == AvatarAnim::Run;
         for child in children.iter_descendants(avatar_entity) {
             if let Ok(mut player) = players.get_mut(child) {
                 if is_walking {
@@ -246,23 +311,36 @@ pub fn update_remote_fox_animation(
     }
 }
 
-/// When online, the server updates `AvatarState::position` at tick rate; smooth `display_position`
-/// and the avatar transform so the third-person camera and world do not jitter.
+/// When online, show the locally predicted position (ADR-010), fading out reconciliation errors.
+/// Against a sim without prediction support, smooth toward the tick-rate authoritative position.
 pub fn smooth_online_avatar_display(
     online: Option<Res<crate::resources::OnlineSession>>,
+    prediction: Option<ResMut<LocalPrediction>>,
     mut avatar_state: ResMut<AvatarState>,
     time: Res<Time>,
     mut avatar_query: Query<&mut Transform, With<Avatar>>,
 ) {
     let dt = time.delta_secs();
-    if online.is_some() {
-        let alpha = 1.0 - (-ONLINE_DISPLAY_SMOOTHING * dt).exp();
-        avatar_state.display_position = avatar_state.display_position.lerp(avatar_state.position, alpha);
-        if let Ok(mut tf) = avatar_query.single_mut() {
-            tf.translation = avatar_state.display_position;
-        }
-    } else {
+    if online.is_none() {
         avatar_state.display_position = avatar_state.position;
+        return;
+    }
+    match prediction.filter(|p| p.enabled) {
+        Some(mut p) => {
+            if p.correction.length_squared() > CORRECTION_SNAP_DISTANCE * CORRECTION_SNAP_DISTANCE {
+                p.correction = Vec3::ZERO;
+            }
+            p.correction *= (-CORRECTION_SMOOTHING * dt).exp();
+            avatar_state.display_position = p.predictor.predicted() + p.correction;
+        }
+        None => {
+            let alpha = 1.0 - (-ONLINE_DISPLAY_SMOOTHING * dt).exp();
+            avatar_state.display_position =
+                avatar_state.display_position.lerp(avatar_state.position, alpha);
+        }
+    }
+    if let Ok(mut tf) = avatar_query.single_mut() {
+        tf.translation = avatar_state.display_position;
     }
 }
 
@@ -288,84 +366,30 @@ pub fn handle_avatar_movement(
     };
     let delta_time = time.delta().as_secs_f32();
 
-    // Online: server owns translation; camera-relative WASD + fox faces into the orbit camera.
-    if online.is_some() {
-        if keyboard_input.just_pressed(KeyCode::KeyF) {
-            avatar_state.is_flying = !avatar_state.is_flying;
-        }
-        let move_left =
-            keyboard_input.pressed(KeyCode::KeyA) || keyboard_input.pressed(KeyCode::ArrowLeft);
-        let move_right =
-            keyboard_input.pressed(KeyCode::KeyD) || keyboard_input.pressed(KeyCode::ArrowRight);
-        let move_forward =
-            keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp);
-        let move_backward =
-            keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown);
-        avatar_state.is_walking =
-            move_forward || move_backward || move_left || move_right;
-        let az = camera_state.azimuth;
-        let face = fox_facing_yaw_from_camera(az);
-        let pi = std::f32::consts::PI;
-        avatar_state.online_tank_yaw = wrap_angle_pi(face - pi);
-        transform.rotation = Quat::from_rotation_y(face);
-        return;
-    }
-
-    // Always sync avatar state position with transform
-    // This ensures camera can follow the avatar correctly
-    avatar_state.position = transform.translation;
-    avatar_state.display_position = transform.translation;
-
     // Toggle fly mode with F key
     if keyboard_input.just_pressed(KeyCode::KeyF) {
         avatar_state.is_flying = !avatar_state.is_flying;
-        println!("Fly mode: {}", if avatar_state.is_flying { "ON" } else { "OFF" });
+        if online.is_none() {
+            println!("Fly mode: {}", if avatar_state.is_flying { "ON" } else { "OFF" });
+        }
     }
 
-    let move_forward = keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp);
-    let move_backward = keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown);
-    let move_left = keyboard_input.pressed(KeyCode::KeyA) || keyboard_input.pressed(KeyCode::ArrowLeft);
-    let move_right = keyboard_input.pressed(KeyCode::KeyD) || keyboard_input.pressed(KeyCode::ArrowRight);
-    let fly_up = keyboard_input.pressed(KeyCode::Space);
-    let fly_down = keyboard_input.pressed(KeyCode::ShiftLeft) || keyboard_input.pressed(KeyCode::ShiftRight);
+    let input = sample_move_input(&keyboard_input, camera_state.azimuth, avatar_state.is_flying);
+    avatar_state.is_walking = input.move_x != 0.0 || input.move_z != 0.0;
+    let face = input.display_yaw;
+    transform.rotation = Quat::from_rotation_y(face);
 
-    let az = camera_state.azimuth;
-    let wish = wish_dir_camera_relative(az, move_forward, move_backward, move_left, move_right);
-    if wish.length_squared() > 1e-8 {
-        let speed = if avatar_state.is_flying { FLY_SPEED } else { WALK_SPEED };
-        let delta = wish * speed * delta_time;
-        transform.translation.x += delta.x;
-        transform.translation.z += delta.z;
-        avatar_state.is_walking = true;
-    } else {
-        avatar_state.is_walking = false;
+    // Online: `send_network_intent` predicts translation with the same kinematics the sim runs.
+    if online.is_some() {
+        let pi = std::f32::consts::PI;
+        avatar_state.online_tank_yaw = wrap_angle_pi(face - pi);
+        return;
     }
 
-    // Handle vertical movement
-    if avatar_state.is_flying {
-        if fly_up {
-            transform.translation.y += FLY_SPEED * delta_time;
-        } else if fly_down {
-            let min_height = GROUND_HEIGHT + AVATAR_HEIGHT / 2.0;
-            if transform.translation.y > min_height + 0.1 {
-                transform.translation.y -= FLY_SPEED * delta_time;
-            }
-        }
-    } else {
-        // Walking mode: apply gravity
-        transform.translation.y += GRAVITY * delta_time;
-
-        // Enforce minimum height
-        let min_height = GROUND_HEIGHT + AVATAR_HEIGHT / 2.0;
-        if transform.translation.y < min_height {
-            transform.translation.y = min_height;
-        }
-    }
+    transform.translation = step_position(transform.translation, &input, delta_time);
 
-    let face = fox_facing_yaw_from_camera(az);
     let pi = std::f32::consts::PI;
     avatar_state.rotation = wrap_angle_pi(face - pi);
-    transform.rotation = Quat::from_rotation_y(face);
 
     // Update avatar state position to match transform (important for camera following)
     avatar_state.position = transform.translation;
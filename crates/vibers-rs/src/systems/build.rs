@@ -0,0 +1,698 @@
+//! Build mode (B): create prims on the ground under the cursor; move, rotate or scale the selected
+//! prim by dragging it or from the keyboard, optionally along one axis and snapped to a grid, or
+//! type exact values; duplicate (also as an array) and delete it; undo and redo. Every change is
+//! stored or sent through [`PrimWriter`].
+
+use crate::components::{Prim, PrimShape};
+use crate::resources::{EditOrigin, GameState};
+use crate::systems::prim_edit::{dto_transform, PrimWriter};
+use crate::systems::rendering::PrimMesh;
+use crate::systems::selection::{ray_prim, CursorView};
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use vibe_core::edit::{PRIM_HEIGHT_RANGE, PRIM_SCALE_RANGE};
+use vibe_core::{PrimDto, PrimEdit};
+
+/// Grid steps cycled with N (meters, and scale units); 0 turns snapping off.
+const GRID_STEPS: [f32; 5] = [0.0, 0.1, 0.25, 0.5, 1.0];
+/// Rotation increment while the grid is on.
+const ROTATE_SNAP_DEG: f32 = 15.0;
+/// Keyboard rotation step while the grid is off.
+const NUDGE_ROTATE_DEG: f32 = 5.0;
+/// Keyboard move/scale step while the grid is off.
+const NUDGE_STEP: f32 = 0.1;
+const ROTATE_PER_PX: f32 = 0.01;
+const SCALE_PER_PX: f32 = 0.01;
+/// Shapes cycled with V for new prims.
+const CREATE_SHAPES: [PrimShape; 5] = [
+    PrimShape::Box,
+    PrimShape::Sphere,
+    PrimShape::Cylinder,
+    PrimShape::Cone,
+    PrimShape::Torus,
+];
+/// Where a plain duplicate lands relative to the original.
+const DUPLICATE_OFFSET: Vec3 = Vec3::X;
+const MAX_ARRAY_COPIES: u32 = 64;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BuildTool {
+    #[default]
+    Move,
+    Rotate,
+    Scale,
+}
+
+impl BuildTool {
+    fn label(self) -> &'static str {
+        match self {
+            BuildTool::Move => "move",
+            BuildTool::Rotate => "rotate",
+            BuildTool::Scale => "scale",
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BuildAxis {
+    X,
+    Y,
+    Z,
+}
+
+impl BuildAxis {
+    fn index(self) -> usize {
+        match self {
+            BuildAxis::X => 0,
+            BuildAxis::Y => 1,
+            BuildAxis::Z => 2,
+        }
+    }
+
+    fn unit(self) -> Vec3 {
+        Vec3::AXES[self.index()]
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Prompt {
+    /// Absolute values for the current tool and axis.
+    Transform,
+    /// `copies,dx,dy,dz` for an array duplicate.
+    Array,
+}
+
+struct Typing {
+    prompt: Prompt,
+    text: String,
+}
+
+/// A mouse drag in progress; the prim is recomputed from `start` every frame so snapping is exact.
+struct Drag {
+    cursor_start: Vec2,
+    start: Transform,
+    /// Ground-plane offset from the grabbed point to the prim centre (unconstrained move).
+    grab: Option<Vec3>,
+}
+
+#[derive(Resource, Default)]
+pub struct BuildMode {
+    pub active: bool,
+    pub tool: BuildTool,
+    /// `None`: move on the ground plane, rotate about Y, scale uniformly.
+    pub axis: Option<BuildAxis>,
+    grid: usize,
+    /// Index into [`CREATE_SHAPES`].
+    shape: usize,
+    /// Typed values (Enter or M starts, Enter applies, Escape cancels).
+    typing: Option<Typing>,
+    drag: Option<Drag>,
+    /// Why the last change was not applied.
+    message: Option<String>,
+}
+
+impl BuildMode {
+    pub fn is_dragging(&self) -> bool {
+        self.drag.is_some()
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.typing.is_some()
+    }
+
+    fn grid_step(&self) -> f32 {
+        GRID_STEPS[self.grid]
+    }
+
+    /// One-line state for the status line.
+    pub fn status(&self) -> String {
+        let axis = self.axis.map_or(String::new(), |a| format!(" {a:?}"));
+        let step = self.grid_step();
+        let grid = if step > 0.0 {
+            format!("{step} m")
+        } else {
+            "off".into()
+        };
+        let shape = CREATE_SHAPES[self.shape].as_str();
+        let mut out = format!(
+            "build: {}{axis} · grid {grid} · new {shape}",
+            self.tool.label()
+        );
+        if let Some(typing) = &self.typing {
+            let hint = self.typing_hint(typing.prompt);
+            out.push_str(&format!(" · enter {hint}: {}_", typing.text));
+        }
+        if let Some(message) = &self.message {
+            out.push_str(" · ");
+            out.push_str(message);
+        }
+        out
+    }
+
+    fn typing_hint(&self, prompt: Prompt) -> &'static str {
+        if prompt == Prompt::Array {
+            return "copies,dx,dy,dz";
+        }
+        match (self.tool, self.axis) {
+            (BuildTool::Move, None) => "x,y,z",
+            (BuildTool::Rotate, None) => "yaw° or x°,y°,z°",
+            (BuildTool::Rotate, Some(_)) => "degrees",
+            (BuildTool::Scale, None) => "uniform or x,y,z",
+            _ => "value",
+        }
+    }
+
+    fn start_typing(&mut self, prompt: Prompt) {
+        self.typing = Some(Typing {
+            prompt,
+            text: String::new(),
+        });
+        self.message = None;
+    }
+
+    /// Add this frame's typed characters (digits, `.`, `-`, `,`) to the prompt.
+    fn type_keys(&mut self, keys: &[Key]) {
+        let Some(typing) = self.typing.as_mut() else {
+            return;
+        };
+        for key in keys {
+            match key {
+                Key::Character(c) => typing.text.extend(
+                    c.chars()
+                        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | '-' | ',')),
+                ),
+                Key::Backspace => {
+                    typing.text.pop();
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn leave(&mut self) {
+        self.active = false;
+        self.typing = None;
+        self.drag = None;
+        self.message = None;
+    }
+
+    /// `start` dragged from `drag.cursor_start` to `cursor`.
+    fn dragged(&self, drag: &Drag, cursor: Vec2, view: &CursorView) -> Transform {
+        let start = drag.start;
+        let mut tf = start;
+        let delta = cursor - drag.cursor_start;
+        let step = self.grid_step();
+        match (self.tool, self.axis) {
+            (BuildTool::Move, Some(axis)) => {
+                // Follow the cursor along the axis as drawn on screen.
+                let (Some(a), Some(b)) = (
+                    view.to_viewport(start.translation),
+                    view.to_viewport(start.translation + axis.unit()),
+                ) else {
+                    return tf;
+                };
+                let per_meter = b - a;
+                if per_meter.length_squared() < 1e-3 {
+                    return tf;
+                }
+                let i = axis.index();
+                let moved =
+                    start.translation[i] + delta.dot(per_meter) / per_meter.length_squared();
+                tf.translation[i] = snap(moved, step);
+            }
+            (BuildTool::Move, None) => {
+                let (Some(grab), Some(ray)) = (drag.grab, view.ray(cursor)) else {
+                    return tf;
+                };
+                let ground = InfinitePlane3d::new(Vec3::Y);
+                let Some(d) = ray.intersect_plane(start.translation, ground) else {
+                    return tf;
+                };
+                let at = ray.get_point(d) + grab;
+                tf.translation.x = snap(at.x, step);
+                tf.translation.z = snap(at.z, step);
+            }
+            (BuildTool::Rotate, axis) => {
+                let mut angle = delta.x * ROTATE_PER_PX;
+                if step > 0.0 {
+                    angle = snap(angle, ROTATE_SNAP_DEG.to_radians());
+                }
+                let axis = axis.unwrap_or(BuildAxis::Y).unit();
+                tf.rotation = Quat::from_axis_angle(axis, angle) * start.rotation;
+            }
+            (BuildTool::Scale, axis) => {
+                let factor = ((delta.x - delta.y) * SCALE_PER_PX).exp();
+                match axis {
+                    Some(axis) => {
+                        let i = axis.index();
+                        tf.scale[i] = snap(start.scale[i] * factor, step);
+                    }
+                    // Snap the factor rather than each axis so proportions survive.
+                    None => tf.scale = start.scale * snap(factor, step).max(step),
+                }
+            }
+        }
+        clamp_transform(&mut tf);
+        tf
+    }
+
+    /// One keyboard step in `sign` direction (PageUp / PageDown).
+    fn nudged(&self, tf: &Transform, sign: f32) -> Transform {
+        let mut out = *tf;
+        let step = self.grid_step();
+        let linear = if step > 0.0 { step } else { NUDGE_STEP };
+        match (self.tool, self.axis) {
+            (BuildTool::Move, axis) => {
+                let i = axis.unwrap_or(BuildAxis::Y).index();
+                out.translation[i] = snap(out.translation[i] + sign * linear, step);
+            }
+            (BuildTool::Rotate, axis) => {
+                let deg = if step > 0.0 {
+                    ROTATE_SNAP_DEG
+                } else {
+                    NUDGE_ROTATE_DEG
+                };
+                let axis = axis.unwrap_or(BuildAxis::Y).unit();
+                out.rotation = Quat::from_axis_angle(axis, sign * deg.to_radians()) * out.rotation;
+            }
+            (BuildTool::Scale, Some(axis)) => {
+                let i = axis.index();
+                out.scale[i] = snap(out.scale[i] + sign * linear, step);
+            }
+            (BuildTool::Scale, None) => out.scale += Vec3::splat(sign * linear),
+        }
+        clamp_transform(&mut out);
+        out
+    }
+
+    /// `tf` with the typed values applied: absolute position, rotation in degrees or scale.
+    fn typed(&self, tf: &Transform, text: &str) -> Result<Transform, String> {
+        let values = parse_values(text)?;
+        let mut out = *tf;
+        let (rx, ry, rz) = tf.rotation.to_euler(EulerRot::XYZ);
+        let mut euler = Vec3::new(rx, ry, rz);
+        match (self.tool, self.axis, values.as_slice()) {
+            (BuildTool::Move, _, &[x, y, z]) => out.translation = Vec3::new(x, y, z),
+            (BuildTool::Move, Some(axis), &[v]) => out.translation[axis.index()] = v,
+            (BuildTool::Rotate, _, &[x, y, z]) => euler = Vec3::new(x, y, z).map(f32::to_radians),
+            (BuildTool::Rotate, axis, &[v]) => {
+                euler[axis.unwrap_or(BuildAxis::Y).index()] = v.to_radians();
+            }
+            (BuildTool::Scale, _, &[x, y, z]) => out.scale = Vec3::new(x, y, z),
+            (BuildTool::Scale, Some(axis), &[v]) => out.scale[axis.index()] = v,
+            (BuildTool::Scale, None, &[v]) => out.scale = Vec3::splat(v),
+            (BuildTool::Move, None, &[_]) => {
+                return Err("pick an axis (X/Y/Z) for a single value".into());
+            }
+            _ => return Err("type one value or three, separated by commas".into()),
+        }
+        if self.tool == BuildTool::Rotate {
+            out.rotation = Quat::from_euler(EulerRot::XYZ, euler.x, euler.y, euler.z);
+        }
+        if !(out.translation.is_finite() && out.scale.is_finite()) {
+            return Err("values must be finite".into());
+        }
+        clamp_transform(&mut out);
+        Ok(out)
+    }
+}
+
+/// Build-mode keys: B toggles; C creates a prim under the cursor and V picks its shape; 1/2/3 pick
+/// move/rotate/scale, X/Y/Z toggle the axis, N cycles the grid, PageUp/PageDown step, Enter types
+/// values; I duplicates, M array-duplicates and Delete deletes the selection; Ctrl+Z undoes and
+/// Ctrl+Y or Ctrl+Shift+Z redoes. Escape cancels typing or a drag before it can clear the selection
+/// (`selection::pick_prim`).
+pub fn build_keys(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut key_events: EventReader<KeyboardInput>,
+    view: CursorView,
+    mut game_state: ResMut<GameState>,
+    mut build: ResMut<BuildMode>,
+    mut prims: Query<(&Prim, &mut Transform), With<PrimMesh>>,
+    mut writer: PrimWriter,
+) {
+    let was_typing = build.is_typing();
+    let typed: Vec<Key> = key_events
+        .read()
+        .filter(|e| e.state == ButtonState::Pressed)
+        .map(|e| e.logical_key.clone())
+        .collect();
+    if !was_typing && !build.is_dragging() && keys.just_pressed(KeyCode::KeyB) {
+        if build.active {
+            build.leave();
+        } else {
+            build.active = true;
+        }
+    }
+    if !build.active {
+        return;
+    }
+    let selected = game_state
+        .selected_prim_id
+        .and_then(|id| prims.iter_mut().find(|(p, _)| p.id == id));
+
+    if keys.just_pressed(KeyCode::Escape) {
+        let drag = build.drag.take();
+        if let (Some(drag), Some((_, mut tf))) = (&drag, selected) {
+            *tf = drag.start;
+        }
+        if build.typing.take().is_some() || drag.is_some() {
+            keys.clear_just_pressed(KeyCode::Escape);
+        }
+        return;
+    }
+    let enter = keys.just_pressed(KeyCode::Enter) || keys.just_pressed(KeyCode::NumpadEnter);
+    if was_typing {
+        build.type_keys(&typed);
+        if !enter {
+            return;
+        }
+        let Some(typing) = build.typing.take() else {
+            return;
+        };
+        let Some((prim, mut tf)) = selected else {
+            build.message = Some("select a prim first".into());
+            return;
+        };
+        match typing.prompt {
+            Prompt::Transform => match build.typed(&tf, &typing.text) {
+                Ok(next) => {
+                    let before = prim.to_dto(&tf);
+                    *tf = next;
+                    commit(&mut writer, &mut build, prim, &mut tf, &before);
+                }
+                Err(e) => build.message = Some(e),
+            },
+            Prompt::Array => {
+                let copied = parse_array(&typing.text).and_then(|(copies, offset)| {
+                    duplicate(&mut writer, &prim.to_dto(&tf), copies, offset)
+                });
+                report(&mut build, &mut game_state, copied);
+            }
+        }
+        return;
+    }
+    if build.is_dragging() {
+        return;
+    }
+    if keys.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        let shift = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+        let origin = match (
+            keys.just_pressed(KeyCode::KeyZ),
+            keys.just_pressed(KeyCode::KeyY),
+        ) {
+            (true, _) if !shift => EditOrigin::Undo,
+            (true, _) | (_, true) => EditOrigin::Redo,
+            _ => return,
+        };
+        let reverted = writer.revert(origin);
+        report(&mut build, &mut game_state, reverted);
+        return;
+    }
+
+    for (key, tool) in [
+        (KeyCode::Digit1, BuildTool::Move),
+        (KeyCode::Digit2, BuildTool::Rotate),
+        (KeyCode::Digit3, BuildTool::Scale),
+    ] {
+        if keys.just_pressed(key) {
+            build.tool = tool;
+        }
+    }
+    for (key, axis) in [
+        (KeyCode::KeyX, BuildAxis::X),
+        (KeyCode::KeyY, BuildAxis::Y),
+        (KeyCode::KeyZ, BuildAxis::Z),
+    ] {
+        if keys.just_pressed(key) {
+            build.axis = (build.axis != Some(axis)).then_some(axis);
+        }
+    }
+    if keys.just_pressed(KeyCode::KeyN) {
+        build.grid = (build.grid + 1) % GRID_STEPS.len();
+    }
+    if keys.just_pressed(KeyCode::KeyV) {
+        build.shape = (build.shape + 1) % CREATE_SHAPES.len();
+    }
+    if keys.just_pressed(KeyCode::KeyC) {
+        let shape = CREATE_SHAPES[build.shape];
+        let created = create_at_cursor(&mut writer, &view, shape, build.grid_step());
+        report(&mut build, &mut game_state, created);
+        return;
+    }
+
+    let Some((prim, mut tf)) = selected else {
+        return;
+    };
+    if enter {
+        build.start_typing(Prompt::Transform);
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyM) {
+        build.start_typing(Prompt::Array);
+        return;
+    }
+    if keys.just_pressed(KeyCode::KeyI) || keys.just_pressed(KeyCode::Insert) {
+        let copied = duplicate(&mut writer, &prim.to_dto(&tf), 1, DUPLICATE_OFFSET);
+        report(&mut build, &mut game_state, copied);
+        return;
+    }
+    if keys.just_pressed(KeyCode::Delete) {
+        let deleted = writer.submit(Some(&prim.to_dto(&tf)), PrimEdit::Delete { id: prim.id });
+        if deleted.is_ok() {
+            game_state.selected_prim_id = None;
+        }
+        report(&mut build, &mut game_state, deleted);
+        return;
+    }
+    let sign = match (
+        keys.just_pressed(KeyCode::PageUp),
+        keys.just_pressed(KeyCode::PageDown),
+    ) {
+        (true, false) => 1.0,
+        (false, true) => -1.0,
+        _ => return,
+    };
+    let before = prim.to_dto(&tf);
+    *tf = build.nudged(&tf, sign);
+    commit(&mut writer, &mut build, prim, &mut tf, &before);
+}
+
+/// Left-drag on the selected prim in build mode manipulates it with the current tool; the change
+/// is committed on release. The orbit camera ignores the drag (`free_camera::camera_controls`).
+pub fn build_drag(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    view: CursorView,
+    meshes: Res<Assets<Mesh>>,
+    game_state: Res<GameState>,
+    mut build: ResMut<BuildMode>,
+    mut prims: Query<DragTarget, With<PrimMesh>>,
+    mut writer: PrimWriter,
+) {
+    if !build.active || build.is_typing() {
+        return;
+    }
+    let Some(id) = game_state.selected_prim_id else {
+        return;
+    };
+    let Some((prim, mut tf, global, mesh, aabb)) = prims.iter_mut().find(|(p, ..)| p.id == id)
+    else {
+        build.drag = None;
+        return;
+    };
+    let Some(drag) = build.drag.take() else {
+        if !mouse_input.just_pressed(MouseButton::Left) {
+            return;
+        }
+        let Some(cursor) = view.cursor() else {
+            return;
+        };
+        let Some(ray) = view.ray(cursor) else {
+            return;
+        };
+        if meshes
+            .get(&mesh.0)
+            .and_then(|m| ray_prim(ray, m, global, aabb))
+            .is_none()
+        {
+            return;
+        }
+        let ground = InfinitePlane3d::new(Vec3::Y);
+        let grab = ray
+            .intersect_plane(tf.translation, ground)
+            .map(|d| tf.translation - ray.get_point(d));
+        build.drag = Some(Drag {
+            cursor_start: cursor,
+            start: *tf,
+            grab,
+        });
+        build.message = None;
+        return;
+    };
+    if mouse_input.pressed(MouseButton::Left) {
+        if let Some(cursor) = view.cursor() {
+            *tf = build.dragged(&drag, cursor, &view);
+        }
+        build.drag = Some(drag);
+        return;
+    }
+    let before = prim.to_dto(&drag.start);
+    commit(&mut writer, &mut build, prim, &mut tf, &before);
+}
+
+type DragTarget<'a> = (
+    &'a Prim,
+    &'a mut Transform,
+    &'a GlobalTransform,
+    &'a Mesh3d,
+    Option<&'a Aabb>,
+);
+
+/// Store or send the prim's new placement; on refusal put it back to `before`.
+fn commit(
+    writer: &mut PrimWriter,
+    build: &mut BuildMode,
+    prim: &Prim,
+    tf: &mut Transform,
+    before: &PrimDto,
+) {
+    let after = prim.to_dto(tf);
+    if after == *before {
+        return;
+    }
+    let edit = PrimEdit::Transform {
+        id: prim.id,
+        position: after.position,
+        rotation: after.rotation,
+        scale: after.scale,
+    };
+    match writer.submit(Some(before), edit) {
+        Ok(_) => build.message = None,
+        Err(e) => {
+            tracing::warn!(prim_id = prim.id, "prim edit not applied: {e}");
+            *tf = dto_transform(before);
+            build.message = Some(e);
+        }
+    }
+}
+
+/// Show the outcome of a create, duplicate, delete, undo or redo; a prim created offline becomes the
+/// selection (online, the sim's ack selects it).
+fn report(build: &mut BuildMode, game_state: &mut GameState, result: Result<Option<i64>, String>) {
+    match result {
+        Ok(created) => {
+            build.message = None;
+            if let Some(id) = created {
+                game_state.selected_prim_id = Some(id);
+            }
+        }
+        Err(e) => {
+            tracing::warn!("prim edit not applied: {e}");
+            build.message = Some(e);
+        }
+    }
+}
+
+/// A unit prim of `shape` standing on the region floor under the cursor, in the region it lands in.
+fn create_at_cursor(
+    writer: &mut PrimWriter,
+    view: &CursorView,
+    shape: PrimShape,
+    step: f32,
+) -> Result<Option<i64>, String> {
+    let ground = view
+        .cursor()
+        .and_then(|c| view.ray(c))
+        .and_then(|ray| writer.ground_hit(ray))
+        .ok_or("point at a region's ground to place a prim")?;
+    let position = Vec3::new(snap(ground.x, step), ground.y + 0.5, snap(ground.z, step));
+    let region_id = writer
+        .region_at(position)
+        .ok_or("that spot is outside every region")?;
+    let mut name = shape.as_str().to_string();
+    name[..1].make_ascii_uppercase();
+    let prim = PrimDto {
+        id: 0,
+        region_id,
+        name,
+        shape: shape.as_str().to_string(),
+        position,
+        rotation: Vec3::ZERO,
+        scale: Vec3::ONE,
+        color: [0.5, 0.5, 0.5],
+    };
+    writer.submit(None, PrimEdit::Create(prim))
+}
+
+/// `copies` copies of `original`, the k-th offset by k × `offset`, each assigned to the region it
+/// lands in. Nothing is created unless every copy has one.
+fn duplicate(
+    writer: &mut PrimWriter,
+    original: &PrimDto,
+    copies: u32,
+    offset: Vec3,
+) -> Result<Option<i64>, String> {
+    let placed = (1..=copies)
+        .map(|k| {
+            let position = original.position + offset * k as f32;
+            let region_id = writer
+                .region_at(position)
+                .ok_or_else(|| format!("copy {k} would land outside every region"))?;
+            Ok(PrimDto {
+                id: 0,
+                region_id,
+                position,
+                ..original.clone()
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    let mut last = None;
+    for copy in placed {
+        last = writer.submit(None, PrimEdit::Create(copy))?.or(last);
+    }
+    Ok(last)
+}
+
+fn parse_values(text: &str) -> Result<Vec<f32>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+        .map(str::parse::<f32>)
+        .collect::<Result<Vec<f32>, _>>()
+        .map_err(|_| format!("{text:?} is not a number list"))
+}
+
+/// `copies,dx,dy,dz`.
+fn parse_array(text: &str) -> Result<(u32, Vec3), String> {
+    let &[copies, x, y, z] = parse_values(text)?.as_slice() else {
+        return Err("type copies,dx,dy,dz".into());
+    };
+    if copies.fract() != 0.0 || !(1.0..=MAX_ARRAY_COPIES as f32).contains(&copies) {
+        return Err(format!(
+            "copies must be a whole number in 1..={MAX_ARRAY_COPIES}"
+        ));
+    }
+    let offset = Vec3::new(x, y, z);
+    if !offset.is_finite() {
+        return Err("offset must be finite".into());
+    }
+    Ok((copies as u32, offset))
+}
+
+fn snap(value: f32, step: f32) -> f32 {
+    if step > 0.0 {
+        (value / step).round() * step
+    } else {
+        value
+    }
+}
+
+/// Keep scale and height inside what the sim accepts (`vibe_core::edit`).
+fn clamp_transform(tf: &mut Transform) {
+    let (lo, hi) = (*PRIM_SCALE_RANGE.start(), *PRIM_SCALE_RANGE.end());
+    tf.scale = tf.scale.clamp(Vec3::splat(lo), Vec3::splat(hi));
+    let (lo, hi) = (*PRIM_HEIGHT_RANGE.start(), *PRIM_HEIGHT_RANGE.end());
+    tf.translation.y = tf.translation.y.clamp(lo, hi);
+}
@@ -0,0 +1,297 @@
+//! Chat log overlay and input line (ADR-018). The log is text on the [`overlay`](super::overlay);
+//! while the input line is open it takes the whole keyboard.
+
+use crate::resources::{AvatarProfiles, NetworkSyncState, OnlineSession, PrimEditRequests};
+use crate::systems::build::BuildMode;
+use crate::systems::overlay::OVERLAY_LAYER;
+use bevy::ecs::system::SystemParam;
+use bevy::input::keyboard::{Key, KeyboardInput};
+use bevy::input::ButtonState;
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy::sprite::Anchor;
+use bevy::window::PrimaryWindow;
+use std::collections::VecDeque;
+use vibe_core::chat::validate_chat;
+use vibe_core::{Capabilities, ChatScope, NetMessage};
+
+/// Lines kept in the log.
+const MAX_LOG_LINES: usize = 50;
+/// Lines shown at once.
+const SHOWN_LINES: usize = 8;
+/// Seconds a line stays up while the input line is closed.
+const LINE_SECS: f64 = 20.0;
+/// Our latest chat request ids, remembered so their refusals go into the log.
+const RECENT_REQUESTS: usize = 16;
+/// Distance from the window's bottom-left corner, in logical pixels.
+const MARGIN: f32 = 12.0;
+
+/// Received chat lines, our unsent line and the requests that may still be refused.
+#[derive(Resource, Default)]
+pub struct ChatLog {
+    /// `(seconds since startup, text)`, oldest first.
+    lines: VecDeque<(f64, String)>,
+    /// Text being typed; `None` while the input line is closed.
+    input: Option<String>,
+    sent: VecDeque<u32>,
+}
+
+impl ChatLog {
+    pub fn push(&mut self, now: f64, line: String) {
+        if self.lines.len() == MAX_LOG_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back((now, line));
+    }
+
+    /// Whether `request_id` is one of our recent chat lines.
+    pub fn was_sent(&self, request_id: u32) -> bool {
+        self.sent.contains(&request_id)
+    }
+
+    fn remember(&mut self, request_id: u32) {
+        if self.sent.len() == RECENT_REQUESTS {
+            self.sent.pop_front();
+        }
+        self.sent.push_back(request_id);
+    }
+
+    /// What the overlay shows: the latest lines (only recent ones while not typing), then the
+    /// input line.
+    fn text(&self, now: f64) -> String {
+        let shown: Vec<&str> = self
+            .lines
+            .iter()
+            .rev()
+            .take(SHOWN_LINES)
+            .filter(|(at, _)| self.input.is_some() || now - at < LINE_SECS)
+            .map(|(_, line)| line.as_str())
+            .collect();
+        let mut text = shown.into_iter().rev().collect::<Vec<_>>().join("\n");
+        if let Some(input) = &self.input {
+            if !text.is_empty() {
+                text.push('\n');
+            }
+            text.push_str("> ");
+            text.push_str(input);
+            text.push('_');
+        }
+        text
+    }
+}
+
+/// Log line for a received `ChatMessage`; `own_id` is our avatar. Private lines carry the
+/// sender's avatar id, which `/w` needs to answer.
+pub fn describe_chat(
+    from: u64,
+    own_id: Option<u64>,
+    profiles: &AvatarProfiles,
+    scope: ChatScope,
+    text: &str,
+) -> String {
+    let name = |id: u64| {
+        profiles
+            .name(id)
+            .map_or_else(|| format!("avatar {id}"), str::to_string)
+    };
+    let who = if own_id == Some(from) {
+        "you".to_string()
+    } else {
+        name(from)
+    };
+    match scope {
+        ChatScope::Local => format!("{who}: {text}"),
+        ChatScope::Region => format!("[region] {who}: {text}"),
+        ChatScope::Direct { avatar_id } if own_id == Some(from) => {
+            format!("[to {}] {who}: {text}", name(avatar_id))
+        }
+        ChatScope::Direct { .. } => format!("[private #{from}] {who}: {text}"),
+    }
+}
+
+/// Marks the overlay's text entity.
+#[derive(Component)]
+pub struct ChatOverlay;
+
+pub fn setup_chat_overlay(mut commands: Commands) {
+    commands.spawn((
+        ChatOverlay,
+        Text2d::default(),
+        TextFont {
+            font_size: 16.0,
+            ..default()
+        },
+        TextLayout::new_with_justify(JustifyText::Left),
+        Anchor::BottomLeft,
+        RenderLayers::layer(OVERLAY_LAYER),
+    ));
+}
+
+/// T opens the input line, Enter sends it and Escape closes it. While it is open every key
+/// goes to the line and the key state is cleared, so movement, camera and build keys stay idle.
+/// Runs right after Bevy's input systems, before anything reads the keys.
+pub fn chat_keys(
+    mut keys: ResMut<ButtonInput<KeyCode>>,
+    mut key_events: EventReader<KeyboardInput>,
+    mut chat: ResMut<ChatLog>,
+    build: Res<BuildMode>,
+    time: Res<Time>,
+    mut sender: ChatSender,
+) {
+    let pressed: Vec<KeyboardInput> = key_events
+        .read()
+        .filter(|e| e.state == ButtonState::Pressed)
+        .cloned()
+        .collect();
+    let Some(mut input) = chat.input.take() else {
+        if keys.just_pressed(KeyCode::KeyT) && !build.is_typing() {
+            chat.input = Some(String::new());
+            keys.reset_all();
+        }
+        return;
+    };
+    keys.reset_all();
+    for e in pressed {
+        match &e.logical_key {
+            Key::Enter => {
+                if input.trim().is_empty() {
+                    return;
+                }
+                match sender.send(&input) {
+                    Ok(request_id) => chat.remember(request_id),
+                    Err(e) => chat.push(time.elapsed_secs_f64(), format!("(not sent: {e})")),
+                }
+                return;
+            }
+            Key::Escape => return,
+            Key::Backspace => {
+                input.pop();
+            }
+            _ => {
+                if let Some(text) = &e.text {
+                    input.extend(text.chars().filter(|c| !c.is_control()));
+                }
+            }
+        }
+    }
+    chat.input = Some(input);
+}
+
+#[derive(SystemParam)]
+pub struct ChatSender<'w> {
+    online: Option<Res<'w, OnlineSession>>,
+    sync: Option<Res<'w, NetworkSyncState>>,
+    /// Request ids are shared with prim edits, so a refusal is never mistaken for the other.
+    requests: Option<ResMut<'w, PrimEditRequests>>,
+}
+
+impl ChatSender<'_> {
+    /// Send one typed line; returns its request id. `Err` is user-facing.
+    fn send(&mut self, line: &str) -> Result<u32, String> {
+        let line = parse_line(line)?;
+        let (Some(online), Some(requests)) = (&self.online, self.requests.as_mut()) else {
+            return Err("chat needs a sim (--connect)".into());
+        };
+        let capabilities = self
+            .sync
+            .as_ref()
+            .map_or(Capabilities::empty(), |s| s.capabilities);
+        let (needed, missing) = match line {
+            ChatLine::Say { .. } => (Capabilities::CHAT, "this sim has no chat"),
+            ChatLine::Rollback { .. } => (Capabilities::REGION_ADMIN, "this sim has no rollback"),
+        };
+        if !capabilities.contains(needed) {
+            return Err(missing.into());
+        }
+        let request_id = requests.next_request_id();
+        let message = match line {
+            ChatLine::Say { scope, text } => NetMessage::SendChat {
+                request_id,
+                scope,
+                text,
+            },
+            ChatLine::Rollback { region_id, to } => NetMessage::RollbackRegion {
+                request_id,
+                region_id,
+                to,
+            },
+        };
+        online
+            .intent_tx
+            .send(message)
+            .map_err(|_| "not connected".to_string())?;
+        Ok(request_id)
+    }
+}
+
+/// What a typed line asks for.
+enum ChatLine {
+    Say { scope: ChatScope, text: String },
+    /// Region owners' rollback (ADR-017).
+    Rollback { region_id: i64, to: String },
+}
+
+/// `/r text` says to the region, `/w ID text` (or `/tell`) to one avatar; anything else is local.
+/// `/rollback REGION_ID TIME` puts a region back as it was at `TIME` (UTC).
+fn parse_line(line: &str) -> Result<ChatLine, String> {
+    let line = line.trim_start();
+    let say = |scope, text| {
+        let text = validate_chat(text)?.to_string();
+        Ok(ChatLine::Say { scope, text })
+    };
+    let Some(command) = line.strip_prefix('/') else {
+        return say(ChatScope::Local, line);
+    };
+    let (name, rest) = command.split_once(' ').unwrap_or((command, ""));
+    match name {
+        "r" | "region" => say(ChatScope::Region, rest),
+        "w" | "tell" => {
+            let (to, text) = rest.trim_start().split_once(' ').unwrap_or((rest, ""));
+            let avatar_id = to
+                .parse()
+                .map_err(|_| format!("usage: /{name} AVATAR_ID message"))?;
+            say(ChatScope::Direct { avatar_id }, text)
+        }
+        "rollback" => {
+            let (region, to) = rest.trim_start().split_once(' ').unwrap_or((rest, ""));
+            let usage = || "usage: /rollback REGION_ID TIME (UTC, e.g. 2026-10-17T12:00)";
+            let region_id = region.parse().map_err(|_| usage())?;
+            let to = to.trim();
+            if to.is_empty() {
+                return Err(usage().into());
+            }
+            Ok(ChatLine::Rollback {
+                region_id,
+                to: to.to_string(),
+            })
+        }
+        _ => Err(format!("unknown command /{name} (try /r or /w)")),
+    }
+}
+
+/// Keep the overlay text current and pinned to the window's bottom-left corner.
+pub fn show_chat(
+    chat: Res<ChatLog>,
+    time: Res<Time>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut overlay: Query<(&mut Text2d, &mut Transform), With<ChatOverlay>>,
+) {
+    let Ok(window) = windows.single() else {
+        return;
+    };
+    let text = chat.text(time.elapsed_secs_f64());
+    let corner = Vec3::new(
+        MARGIN - window.width() / 2.0,
+        MARGIN - window.height() / 2.0,
+        0.0,
+    );
+    for (mut shown, mut tf) in &mut overlay {
+        // Only write on change: a changed `Text2d` is laid out again.
+        if shown.0 != text {
+            shown.0.clone_from(&text);
+        }
+        if tf.translation != corner {
+            tf.translation = corner;
+        }
+    }
+}
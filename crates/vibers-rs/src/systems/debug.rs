@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 use crate::components::Region;
+use crate::resources::NetworkClock;
 use crate::systems::rendering::RegionMesh;
 
 pub fn debug_region_entities(
@@ -17,3 +18,21 @@ pub fn debug_region_entities(
         tracing::trace!("region entity {:?} id={} name={}", entity, region.id, region.name);
     }
 }
+
+/// Heartbeat estimates (ADR-008), logged once per new sample.
+pub fn debug_network_clock(clock: Option<Res<NetworkClock>>, mut last_samples: Local<u64>) {
+    let Some(clock) = clock else {
+        return;
+    };
+    let c = clock.snapshot();
+    if c.samples == *last_samples {
+        return;
+    }
+    *last_samples = c.samples;
+    tracing::debug!(
+        rtt_ms = c.rtt.map(|r| r * 1e3),
+        clock_offset_ms = c.offset * 1e3,
+        samples = c.samples,
+        "network clock"
+    );
+}
@@ -5,6 +5,7 @@ use bevy_atmosphere::prelude::*;
 use bevy_atmosphere::skybox::{self, AtmosphereSkyBoxMaterial};
 
 use crate::resources::{AvatarState, CameraState, CameraMode};
+use crate::systems::build::BuildMode;
 use crate::systems::rendering::RegionMesh;
 
 #[derive(Component)]
@@ -79,6 +80,7 @@ pub fn camera_controls(
     avatar_state: Res<AvatarState>,
     time: Res<Time>,
     region_mesh_query: Query<&GlobalTransform, With<RegionMesh>>,
+    build: Res<BuildMode>,
 ) {
     if camera_query.is_empty() {
         return;
@@ -100,8 +102,10 @@ pub fn camera_controls(
                 camera_state.distance = camera_state.distance.max(2.0).min(100.0);
             }
 
-            // Handle mouse drag for rotation
-            if mouse_input.pressed(MouseButton::Left) {
+            // Handle mouse drag for rotation (unless the drag is moving a prim in build mode)
+            if build.is_dragging() {
+                cursor_moved_events.clear();
+            } else if mouse_input.pressed(MouseButton::Left) {
                 for event in cursor_moved_events.read() {
                     if let Some(last_pos) = camera_state.pan_offset {
                         let delta = event.position - last_pos;
@@ -0,0 +1,112 @@
+//! Region ground level of detail (ADR-004). A region's ground is the root of a quadtree: its tile
+//! (zoom 17) covers all of it, and near the camera a node is split into four patches textured with
+//! the tiles one zoom level down, to [`MAX_GROUND_ZOOM_LEVEL`]. A patch is drawn just above its
+//! parent and only once its texture has loaded, so detail streams in without holes; patches the
+//! camera has moved away from are despawned and their textures released.
+
+use crate::systems::free_camera::FreeCamera;
+use crate::systems::rendering::RegionMesh;
+use crate::systems::tile_loader::{RegionTile, TileCache, TileKey};
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use std::collections::{HashMap, HashSet};
+use vibe_core::world::MAX_GROUND_ZOOM_LEVEL;
+
+/// A node is split once the camera is closer to it than this many times its width...
+const SPLIT_DISTANCE: f32 = 1.5;
+/// ...and merged again beyond this many times, so it does not flicker at the boundary.
+const MERGE_DISTANCE: f32 = 2.0;
+/// Height of each level of patches above the one below, against z-fighting (metres).
+const PATCH_LIFT: f32 = 0.01;
+
+/// One quadtree node below the region, as a child entity of the region.
+#[derive(Component)]
+pub struct GroundPatch {
+    pub key: TileKey,
+}
+
+/// Split and merge every region's ground quadtree for the current camera position.
+pub fn update_ground_lod(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut tile_cache: ResMut<TileCache>,
+    cameras: Query<&GlobalTransform, With<FreeCamera>>,
+    regions: Query<(Entity, &RegionTile, &GlobalTransform, &Aabb), With<RegionMesh>>,
+    patches: Query<(Entity, &GroundPatch, &ChildOf)>,
+) {
+    let Ok(camera) = cameras.single() else {
+        return;
+    };
+    let eye = camera.translation();
+    let existing: HashMap<(Entity, TileKey), Entity> = patches
+        .iter()
+        .map(|(entity, patch, child_of)| ((child_of.parent(), patch.key.clone()), entity))
+        .collect();
+
+    // (region, key, centre relative to the region, width, height above the region's origin)
+    let mut wanted = Vec::new();
+    for (region, tile, transform, aabb) in &regions {
+        let top = aabb.max().y;
+        let mut nodes = vec![(tile.tile_key.clone(), Vec2::ZERO, aabb.half_extents.x * 2.0)];
+        while let Some((key, offset, width)) = nodes.pop() {
+            if key.z >= MAX_GROUND_ZOOM_LEVEL {
+                continue;
+            }
+            let center = transform.translation() + Vec3::new(offset.x, top, offset.y);
+            let is_split = existing.contains_key(&(region, key.children()[0].clone()));
+            let factor = if is_split {
+                MERGE_DISTANCE
+            } else {
+                SPLIT_DISTANCE
+            };
+            if distance_to_square(eye, center, width) >= factor * width {
+                continue;
+            }
+            let half = width / 2.0;
+            let height = top + PATCH_LIFT * (key.z + 1 - tile.tile_key.z) as f32;
+            for (i, child) in key.children().into_iter().enumerate() {
+                // children() runs west to east, then north (-Z) to south (+Z), like the tile UVs.
+                let quadrant = Vec2::new((i % 2) as f32 - 0.5, (i / 2) as f32 - 0.5);
+                let child_offset = offset + quadrant * half;
+                wanted.push((region, child.clone(), child_offset, half, height));
+                nodes.push((child, child_offset, half));
+            }
+        }
+    }
+
+    let wanted_keys: HashSet<(Entity, TileKey)> = wanted
+        .iter()
+        .map(|(region, key, ..)| (*region, key.clone()))
+        .collect();
+    for ((region, key), entity) in &existing {
+        if !wanted_keys.contains(&(*region, key.clone())) {
+            commands.entity(*entity).despawn();
+            tile_cache.release(key);
+        }
+    }
+    for (region, key, offset, width, height) in wanted {
+        if existing.contains_key(&(region, key.clone())) {
+            continue;
+        }
+        commands.entity(region).with_child((
+            GroundPatch { key: key.clone() },
+            RegionTile { tile_key: key },
+            Mesh3d(meshes.add(Plane3d::default().mesh().size(width, width))),
+            MeshMaterial3d::<StandardMaterial>::default(),
+            Transform::from_xyz(offset.x, height, offset.y),
+            // Shown by `rendering::update_region_materials` once the texture is in.
+            Visibility::Hidden,
+        ));
+    }
+}
+
+/// Distance from `eye` to the nearest point of the horizontal square of `width` around `center`.
+fn distance_to_square(eye: Vec3, center: Vec3, width: f32) -> f32 {
+    let half = width / 2.0;
+    let nearest = Vec3::new(
+        eye.x.clamp(center.x - half, center.x + half),
+        center.y,
+        eye.z.clamp(center.z - half, center.z + half),
+    );
+    eye.distance(nearest)
+}
@@ -0,0 +1,62 @@
+//! Window-title status line (the workspace builds Bevy without `bevy_ui`): the selected prim's
+//! readout, build mode state and the latest server notice.
+
+use crate::components::Prim;
+use crate::resources::{GameState, ServerNotice};
+use crate::systems::build::BuildMode;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+
+pub fn show_window_status(
+    game_state: Res<GameState>,
+    notice: Option<Res<ServerNotice>>,
+    build: Res<BuildMode>,
+    prims: Query<(&Prim, &Transform)>,
+    mut windows: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    let mut title = String::from("Vibers RS");
+    let selected = game_state
+        .selected_prim_id
+        .and_then(|id| prims.iter().find(|(p, _)| p.id == id));
+    if let Some((prim, tf)) = selected {
+        title.push_str(" — ");
+        title.push_str(&prim_readout(prim, tf));
+    }
+    if build.active {
+        title.push_str(" — ");
+        title.push_str(&build.status());
+    }
+    if let Some(text) = notice.as_ref().and_then(|n| n.0.as_deref()) {
+        title.push_str(" — ");
+        title.push_str(text);
+    }
+    for mut w in &mut windows {
+        // Only write on change: touching `Window` every frame makes winit re-apply it.
+        if w.title != title {
+            w.title.clone_from(&title);
+        }
+    }
+}
+
+/// Id, name, shape, region and transform (rotation as XYZ Euler degrees, as stored).
+fn prim_readout(prim: &Prim, tf: &Transform) -> String {
+    let (rx, ry, rz) = tf.rotation.to_euler(EulerRot::XYZ);
+    let (p, s) = (tf.translation, tf.scale);
+    format!(
+        "prim #{} \"{}\" {:?} in region {} · pos ({:.2}, {:.2}, {:.2}) \
+         · rot ({:.1}°, {:.1}°, {:.1}°) · scale ({:.2}, {:.2}, {:.2})",
+        prim.id,
+        prim.name,
+        prim.shape,
+        prim.region_id,
+        p.x,
+        p.y,
+        p.z,
+        rx.to_degrees(),
+        ry.to_degrees(),
+        rz.to_degrees(),
+        s.x,
+        s.y,
+        s.z,
+    )
+}
@@ -1,8 +1,16 @@
 pub mod avatar;
+pub mod build;
 pub mod camera;
+pub mod chat;
 pub mod database;
 pub mod debug;
 pub mod free_camera;
+pub mod ground_lod;
+pub mod hud;
+pub mod name_tags;
 pub mod network;
+pub mod overlay;
+pub mod prim_edit;
 pub mod rendering;
+pub mod selection;
 pub mod tile_loader;
\ No newline at end of file
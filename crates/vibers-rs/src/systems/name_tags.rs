@@ -0,0 +1,95 @@
+//! Name tags above other players' avatars (ADR-019). A tag is overlay text placed where the point
+//! above the avatar's head projects, so it always faces the camera; it fades out with distance.
+
+use crate::components::RemoteAvatar;
+use crate::resources::{AvatarAppearances, AvatarProfiles};
+use crate::systems::free_camera::FreeCamera;
+use crate::systems::overlay::{viewport_to_overlay, OVERLAY_LAYER};
+use bevy::prelude::*;
+use bevy::render::view::RenderLayers;
+use bevy::sprite::Anchor;
+use bevy::window::PrimaryWindow;
+
+/// Height of the tag above the origin of an avatar at appearance scale 1 (metres).
+const TAG_HEIGHT: f32 = 1.8;
+/// Tags are opaque up to this distance from the camera (metres)...
+const FADE_START: f32 = 15.0;
+/// ...and gone from this one.
+const FADE_END: f32 = 45.0;
+
+/// The overlay text naming one remote avatar.
+#[derive(Component)]
+pub struct NameTag {
+    pub avatar_id: u64,
+}
+
+/// Spawn, place and fade a tag for every remote avatar whose profile has arrived; despawn tags of
+/// avatars that left.
+pub fn update_name_tags(
+    mut commands: Commands,
+    profiles: Option<Res<AvatarProfiles>>,
+    appearances: Res<AvatarAppearances>,
+    remotes: Query<(&RemoteAvatar, &GlobalTransform)>,
+    cameras: Query<(&Camera, &GlobalTransform), With<FreeCamera>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut tags: Query<(
+        Entity,
+        &NameTag,
+        &mut Text2d,
+        &mut Transform,
+        &mut TextColor,
+        &mut Visibility,
+    )>,
+) {
+    let (Some(profiles), Ok((camera, camera_tf)), Ok(window)) =
+        (profiles, cameras.single(), windows.single())
+    else {
+        return;
+    };
+    for (entity, tag, mut text, mut tf, mut color, mut visibility) in &mut tags {
+        let Some((_, avatar_tf)) = remotes.iter().find(|(r, _)| r.sim_id == tag.avatar_id) else {
+            commands.entity(entity).despawn();
+            continue;
+        };
+        let name = profiles.name(tag.avatar_id).unwrap_or_default();
+        if text.0 != name {
+            text.0 = name.to_string();
+        }
+        let scale = appearances.0.get(&tag.avatar_id).map_or(1.0, |a| a.scale);
+        let head = avatar_tf.translation() + Vec3::Y * TAG_HEIGHT * scale;
+        let distance = head.distance(camera_tf.translation());
+        let alpha = 1.0 - ((distance - FADE_START) / (FADE_END - FADE_START)).clamp(0.0, 1.0);
+        // Behind the camera there is no projection.
+        let Some(at) = camera
+            .world_to_viewport(camera_tf, head)
+            .ok()
+            .filter(|_| alpha > 0.0)
+        else {
+            visibility.set_if_neq(Visibility::Hidden);
+            continue;
+        };
+        visibility.set_if_neq(Visibility::Inherited);
+        tf.translation = viewport_to_overlay(at, window.size()).extend(0.0);
+        color.0 = Color::WHITE.with_alpha(alpha);
+    }
+    for (remote, _) in &remotes {
+        let tagged = tags
+            .iter()
+            .any(|(_, tag, ..)| tag.avatar_id == remote.sim_id);
+        if !tagged && profiles.name(remote.sim_id).is_some() {
+            commands.spawn((
+                NameTag {
+                    avatar_id: remote.sim_id,
+                },
+                Text2d::default(),
+                TextFont {
+                    font_size: 14.0,
+                    ..default()
+                },
+                Anchor::BottomCenter,
+                Visibility::Hidden,
+                RenderLayers::layer(OVERLAY_LAYER),
+            ));
+        }
+    }
+}
@@ -1,34 +1,81 @@
 //! TCP client for `--connect` (ADR-008, ADR-009).
 
-use crate::components::{Avatar, Prim, PrimShape, Region, RemoteAvatar, RemoteAvatarMotionHint};
+use crate::components::{Avatar, Prim, Region, RemoteAvatar};
+use crate::identity;
 use crate::resources::{
-    AvatarState, CameraState, ConnectAddr, GameState, LocalAvatarSimId, NetworkMailbox,
-    NetworkSyncState, OnlineSession, OsmTileUrlTemplate,
+    AvatarAppearances, AvatarProfiles, AvatarState, CameraState, ConnectAddr, DisplayName,
+    EditHistory, EditOrigin, GameState, IdentityPath, LocalAppearance, LocalAvatarSimId,
+    LocalPrediction, NetworkClock, NetworkMailbox, NetworkSyncState, OnlineSession,
+    OsmTileUrlTemplate, PendingEdit, PrimEditRequests, ServerNotice, ServerTickClock,
 };
-use crate::systems::avatar::{fox_facing_yaw_from_camera, wish_dir_camera_relative};
+use crate::systems::avatar::{fox_facing_yaw_from_camera, sample_move_input};
+use crate::systems::chat::{describe_chat, ChatLog};
+use crate::systems::prim_edit::prim_bundle;
 use bevy::prelude::*;
 use bytes::Bytes;
 use futures_util::{SinkExt, StreamExt};
-use std::collections::HashSet;
+use ring::signature::KeyPair;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
 use std::sync::{mpsc, Arc, Mutex};
+use std::time::Duration;
 use tokio::net::TcpStream;
 use tokio::sync::mpsc::UnboundedReceiver;
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use vibe_core::auth::challenge_payload;
+use vibe_core::kinematics::PREDICTION_PROTOCOL_VERSION;
+use vibe_core::profile::validate_display_name;
 use vibe_core::{
-    decode_app_frame, encode_app_frame, snap_yaw_continuation, wrap_angle_pi, AvatarStateDto,
-    NetMessage, PrimDto, RegionDto, PROTOCOL_VERSION,
+    decode_app_frame, decode_app_frame_versioned, encode_app_frame, encode_app_frame_as,
+    message_tick, now_micros, snap_yaw_continuation, wrap_angle_pi, AvatarAppearance,
+    AvatarDeltaDto, AvatarSample, AvatarStateDto, Capabilities, ClockSync, ErrorCode,
+    InterpolationBuffer, MoveInput, NetMessage, RegionDto, TickClock, MIN_PROTOCOL_VERSION,
+    PROTOCOL_VERSION,
 };
 
 const MAX_FRAME: usize = 32 * 1024 * 1024;
+/// Optional features this client can handle (ADR-009 hello).
+const CLIENT_CAPABILITIES: Capabilities =
+    Capabilities::REGION_STREAMING
+        .union(Capabilities::HEARTBEAT)
+        .union(Capabilities::REGION_ADMIN)
+        .union(Capabilities::PRIM_EDITING)
+        .union(Capabilities::CHAT)
+        .union(Capabilities::DISPLAY_NAMES)
+        .union(Capabilities::APPEARANCE);
+/// Heartbeat period when the sim supports it; keeps the session alive and feeds [`NetworkClock`].
+const PING_INTERVAL: Duration = Duration::from_secs(1);
+/// With heartbeats on, a sim silent this long is treated as gone (half-open connection).
+const SIM_SILENCE_TIMEOUT: Duration = Duration::from_secs(10);
 
-pub fn spawn_network_thread(mut commands: Commands, addr: Res<ConnectAddr>) {
+/// How we present ourselves once logged in: display name (ADR-019) and appearance (ADR-020).
+struct Presence {
+    display_name: Option<String>,
+    appearance: AvatarAppearance,
+}
+
+pub fn spawn_network_thread(
+    mut commands: Commands,
+    addr: Res<ConnectAddr>,
+    identity: Res<IdentityPath>,
+    display_name: Option<Res<DisplayName>>,
+    appearance: Res<LocalAppearance>,
+) {
     let tile_template = Arc::new(Mutex::new(String::new()));
     let tile_for_thread = tile_template.clone();
     commands.insert_resource(OsmTileUrlTemplate(tile_template));
+    let clock = Arc::new(Mutex::new(ClockSync::default()));
+    let clock_for_thread = clock.clone();
+    commands.insert_resource(NetworkClock(clock));
 
     let (out_tx, out_rx) = mpsc::channel::<NetMessage>();
     let (intent_tx, intent_rx) = tokio::sync::mpsc::unbounded_channel();
     let connect_to = addr.0.clone();
+    let identity_path = identity.0.clone();
+    let presence = Presence {
+        display_name: display_name.map(|n| n.0.clone()),
+        appearance: appearance.0.clone(),
+    };
     std::thread::spawn(move || {
         let rt = match tokio::runtime::Builder::new_multi_thread()
             .enable_all()
@@ -40,9 +87,15 @@ pub fn spawn_network_thread(mut commands: Commands, addr: Res<ConnectAddr>) {
                 return;
             }
         };
-        if let Err(e) =
-            rt.block_on(client_loop(connect_to, out_tx, intent_rx, tile_for_thread))
-        {
+        if let Err(e) = rt.block_on(client_loop(
+            connect_to,
+            identity_path,
+            presence,
+            out_tx,
+            intent_rx,
+            tile_for_thread,
+            clock_for_thread,
+        )) {
             eprintln!("network client ended: {e:#}");
         }
     });
@@ -50,15 +103,24 @@ pub fn spawn_network_thread(mut commands: Commands, addr: Res<ConnectAddr>) {
         rx: Mutex::new(out_rx),
     });
     commands.insert_resource(OnlineSession { intent_tx });
+    commands.insert_resource(LocalPrediction::default());
+    commands.insert_resource(ServerTickClock::default());
     commands.insert_resource(NetworkSyncState::default());
+    commands.insert_resource(ServerNotice::default());
+    commands.insert_resource(PrimEditRequests::default());
 }
 
 async fn client_loop(
     addr: String,
+    identity_path: PathBuf,
+    presence: Presence,
     out_tx: mpsc::Sender<NetMessage>,
     mut intent_rx: UnboundedReceiver<NetMessage>,
     tile_template: Arc<Mutex<String>>,
+    clock: Arc<Mutex<ClockSync>>,
 ) -> anyhow::Result<()> {
+    let key = identity::load_or_create(&identity_path)?;
+    let public_key = key.public_key().as_ref().to_vec();
     let stream = TcpStream::connect(&addr).await?;
     tracing::info!("connected to {addr}");
     let mut framed = Framed::new(
@@ -70,45 +132,120 @@ async fn client_loop(
     );
 
     let hello = encode_app_frame(&NetMessage::ClientHello {
-        protocol_version: PROTOCOL_VERSION,
+        min_protocol_version: MIN_PROTOCOL_VERSION,
+        max_protocol_version: PROTOCOL_VERSION,
+        capabilities: CLIENT_CAPABILITIES,
         client_token: format!("vibers-rs-{}", uuid::Uuid::new_v4()),
+        public_key: public_key.clone(),
     })?;
     framed.send(Bytes::from(hello)).await?;
 
-    let ack_bytes = framed
+    let first = framed
         .next()
         .await
         .transpose()?
         .ok_or_else(|| anyhow::anyhow!("closed before ServerHelloAck"))?;
-    let ack_msg = decode_app_frame(&ack_bytes)?;
-    match &ack_msg {
+    let (first_version, mut ack_msg) = decode_app_frame_versioned(&first)?;
+    // Login (v10+): sign the sim's nonce with the identity key; older sims ack straight away.
+    if let NetMessage::AuthChallenge { nonce } = &ack_msg {
+        let signature = key.sign(&challenge_payload(nonce, &public_key));
+        let response = NetMessage::AuthResponse {
+            signature: signature.as_ref().to_vec(),
+        };
+        framed
+            .send(Bytes::from(encode_app_frame_as(&response, first_version)?))
+            .await?;
+        let ack_bytes = framed
+            .next()
+            .await
+            .transpose()?
+            .ok_or_else(|| anyhow::anyhow!("closed before ServerHelloAck"))?;
+        ack_msg = decode_app_frame(&ack_bytes)?;
+    }
+    let (wire_version, heartbeat, names, looks) = match &ack_msg {
         NetMessage::ServerHelloAck {
             tick_hz,
             your_avatar_id,
             osm_tile_url_template,
+            protocol_version,
+            capabilities,
+            user_id,
             ..
         } => {
             if let Ok(mut g) = tile_template.lock() {
                 *g = osm_tile_url_template.clone();
             }
-            tracing::info!(tick_hz, your_avatar_id, "handshake ok");
+            tracing::info!(
+                tick_hz,
+                user_id,
+                your_avatar_id,
+                protocol_version,
+                capabilities = capabilities.0,
+                "handshake ok"
+            );
+            (
+                *protocol_version,
+                capabilities.contains(Capabilities::HEARTBEAT),
+                capabilities.contains(Capabilities::DISPLAY_NAMES),
+                capabilities.contains(Capabilities::APPEARANCE),
+            )
         }
-        NetMessage::ServerError { message, .. } => {
-            anyhow::bail!("server error: {message}");
+        NetMessage::ServerError { code, message, .. } => {
+            let reason = describe_server_error(*code, message);
+            let _ = out_tx.send(ack_msg);
+            anyhow::bail!("handshake rejected: {reason}");
         }
         other => anyhow::bail!("unexpected first server message: {other:?}"),
-    }
+    };
     if out_tx.send(ack_msg).is_err() {
         return Ok(());
     }
+    // The sim stores the name for the account; without `--name` the stored (or default) one stays.
+    match presence.display_name.as_deref().map(validate_display_name) {
+        Some(Ok(name)) if names => {
+            let set = NetMessage::SetDisplayName {
+                request_id: 0,
+                name: name.to_string(),
+            };
+            framed
+                .send(Bytes::from(encode_app_frame_as(&set, wire_version)?))
+                .await?;
+        }
+        Some(Ok(_)) => tracing::warn!("this sim has no display names; --name ignored"),
+        Some(Err(e)) => tracing::warn!("--name ignored: {e}"),
+        None => {}
+    }
+    // Validated at startup (`main`); without the capability others see the default fox.
+    if looks {
+        let set = NetMessage::SetAppearance {
+            request_id: 0,
+            appearance: presence.appearance,
+        };
+        framed
+            .send(Bytes::from(encode_app_frame_as(&set, wire_version)?))
+            .await?;
+    }
 
+    let mut acked_tick = 0u64;
+    let mut ping = tokio::time::interval(PING_INTERVAL);
+    ping.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut last_rx = tokio::time::Instant::now();
     loop {
         tokio::select! {
             biased;
+            _ = ping.tick(), if heartbeat => {
+                if last_rx.elapsed() > SIM_SILENCE_TIMEOUT {
+                    let reason = format!("no response from sim for {SIM_SILENCE_TIMEOUT:?}");
+                    let _ = out_tx.send(NetMessage::error(ErrorCode::IdleTimeout, 0, reason.clone()));
+                    anyhow::bail!(reason);
+                }
+                let b = encode_app_frame_as(&NetMessage::Ping { sent_at_us: now_micros() }, wire_version)?;
+                framed.send(Bytes::from(b)).await?;
+            }
             msg = intent_rx.recv() => {
                 match msg {
                     Some(m) => {
-                        let b = encode_app_frame(&m)?;
+                        let b = encode_app_frame_as(&m, wire_version)?;
                         framed.send(Bytes::from(b)).await?;
                     }
                     None => break,
@@ -119,7 +256,31 @@ async fn client_loop(
                     None => break,
                     Some(Err(e)) => return Err(e.into()),
                     Some(Ok(bytes)) => {
+                        last_rx = tokio::time::Instant::now();
                         let m = decode_app_frame(&bytes)?;
+                        match m {
+                            NetMessage::Pong { ping_sent_at_us, pong_sent_at_us } => {
+                                if let Ok(mut c) = clock.lock() {
+                                    c.observe(ping_sent_at_us, pong_sent_at_us, now_micros());
+                                }
+                                continue;
+                            }
+                            NetMessage::Ping { sent_at_us } => {
+                                let pong = NetMessage::Pong {
+                                    ping_sent_at_us: sent_at_us,
+                                    pong_sent_at_us: now_micros(),
+                                };
+                                framed.send(Bytes::from(encode_app_frame_as(&pong, wire_version)?)).await?;
+                                continue;
+                            }
+                            _ => {}
+                        }
+                        // Messages are applied in order, so receipt is as good as applied for the sim's delta baseline.
+                        if let Some(tick) = message_tick(&m).filter(|t| *t > acked_tick) {
+                            acked_tick = tick;
+                            let b = encode_app_frame_as(&NetMessage::SnapshotAck { tick }, wire_version)?;
+                            framed.send(Bytes::from(b)).await?;
+                        }
                         if out_tx.send(m).is_err() {
                             break;
                         }
@@ -140,24 +301,63 @@ pub fn apply_network_snapshot(
     mut avatar_state: ResMut<AvatarState>,
     mut local_sim_id: ResMut<LocalAvatarSimId>,
     camera_state: Res<CameraState>,
-    region_entities: Query<Entity, With<Region>>,
+    region_entities: Query<(Entity, &Region)>,
     prim_entities: Query<(Entity, &Prim)>,
     mut avatar_tf: Query<&mut Transform, (With<Avatar>, Without<RemoteAvatar>)>,
     mut remote_avatars: Query<(Entity, &mut RemoteAvatar), Without<Avatar>>,
+    mut prediction: Option<ResMut<LocalPrediction>>,
+    mut tick_clock: Option<ResMut<ServerTickClock>>,
+    // Grouped to stay within Bevy's 16 system parameters.
+    (mut edit_requests, mut edit_history): (
+        Option<ResMut<PrimEditRequests>>,
+        ResMut<EditHistory>,
+    ),
+    (mut notice, mut chat, mut profiles, mut appearances): (
+        Option<ResMut<ServerNotice>>,
+        ResMut<ChatLog>,
+        ResMut<AvatarProfiles>,
+        ResMut<AvatarAppearances>,
+    ),
+    time: Res<Time>,
 ) {
     let Some(mb) = mailbox else {
         return;
     };
+    // Remotes first seen this frame; spawned after the mailbox drains so later ticks can still patch them.
+    let mut pending_remotes: HashMap<u64, RemoteAvatar> = HashMap::new();
+    let mut spawned = FrameSpawns::default();
     while let Ok(msg) = mb.lock_rx().try_recv() {
+        if let (Some(tick), Some(clock)) = (
+            message_tick(&msg),
+            tick_clock.as_mut().and_then(|c| c.0.as_mut()),
+        ) {
+            clock.observe(tick, time.elapsed_secs_f64());
+        }
         match msg {
-            NetMessage::ServerHelloAck { your_avatar_id, .. } => {
+            NetMessage::ServerHelloAck {
+                your_avatar_id,
+                protocol_version,
+                tick_hz,
+                capabilities,
+                ..
+            } => {
                 local_sim_id.0 = Some(your_avatar_id);
+                if let Some(s) = sync.as_mut() {
+                    s.capabilities = capabilities;
+                }
+                if let Some(c) = tick_clock.as_mut() {
+                    c.0 = Some(TickClock::new(tick_hz));
+                }
+                if let Some(p) = prediction.as_mut() {
+                    p.enabled = protocol_version >= PREDICTION_PROTOCOL_VERSION;
+                }
             }
             NetMessage::WorldSnapshot {
                 regions,
                 prims,
                 avatars,
                 tick,
+                input_seq,
             } => {
                 tracing::debug!(tick, "world snapshot");
                 let repeat_tick = sync
@@ -165,9 +365,13 @@ pub fn apply_network_snapshot(
                     .is_some_and(|s| s.received_initial_world);
 
                 if repeat_tick {
-                    apply_local_avatar_pose(&avatars, local_sim_id.0, &mut avatar_state);
+                    if let Some(a) = local_avatar_dto(&avatars, local_sim_id.0) {
+                        apply_local_avatar_pose(a, &mut avatar_state);
+                        reconcile_prediction(prediction.as_deref_mut(), a.position, input_seq);
+                    }
                     sync_remote_avatar_entities(
                         &mut commands,
+                        tick,
                         &avatars,
                         local_sim_id.0,
                         &mut remote_avatars,
@@ -175,7 +379,7 @@ pub fn apply_network_snapshot(
                     continue;
                 }
 
-                let region_es: Vec<Entity> = region_entities.iter().collect();
+                let region_es: Vec<Entity> = region_entities.iter().map(|(e, _)| e).collect();
                 for e in region_es {
                     commands.entity(e).despawn();
                 }
@@ -196,7 +400,7 @@ pub fn apply_network_snapshot(
                 game_state.regions_loaded = true;
 
                 for p in prims {
-                    commands.spawn(prim_bundle_from_dto(p));
+                    commands.spawn(prim_bundle(p));
                 }
                 game_state.prims_loaded = true;
 
@@ -207,8 +411,13 @@ pub fn apply_network_snapshot(
                     &mut avatar_tf,
                     camera_state.azimuth,
                 );
+                if let Some(p) = prediction.as_mut() {
+                    p.predictor.reset(avatar_state.position, input_seq);
+                    p.correction = Vec3::ZERO;
+                }
                 sync_remote_avatar_entities(
                     &mut commands,
+                    tick,
                     &avatars,
                     local_sim_id.0,
                     &mut remote_avatars,
@@ -218,33 +427,300 @@ pub fn apply_network_snapshot(
                     s.received_initial_world = true;
                 }
             }
+            NetMessage::AvatarsAdded { tick, avatars } => {
+                for a in avatars {
+                    if Some(a.id) == local_sim_id.0 {
+                        apply_local_avatar_pose(&a, &mut avatar_state);
+                        // No echo here: keep the acked sequence, re-base the pending inputs.
+                        reconcile_prediction(prediction.as_deref_mut(), a.position, 0);
+                    } else if let Some(r) = pending_remotes.get_mut(&a.id) {
+                        set_remote_avatar(r, tick, a);
+                    } else if let Some((_, mut r)) =
+                        remote_avatars.iter_mut().find(|(_, r)| r.sim_id == a.id)
+                    {
+                        set_remote_avatar(&mut r, tick, a);
+                    } else {
+                        pending_remotes.insert(a.id, new_remote_avatar(tick, a));
+                    }
+                }
+            }
+            NetMessage::AvatarsUpdated {
+                tick,
+                updates,
+                input_seq,
+            } => {
+                for d in updates {
+                    if Some(d.id) == local_sim_id.0 {
+                        apply_local_avatar_delta(&d, &mut avatar_state);
+                    } else if let Some(r) = pending_remotes.get_mut(&d.id) {
+                        patch_remote_avatar(r, tick, &d);
+                    } else if let Some((_, mut r)) =
+                        remote_avatars.iter_mut().find(|(_, r)| r.sim_id == d.id)
+                    {
+                        patch_remote_avatar(&mut r, tick, &d);
+                    }
+                }
+                // Unchanged fields are as last received, so `position` is authoritative at this tick.
+                reconcile_prediction(prediction.as_deref_mut(), avatar_state.position, input_seq);
+            }
+            NetMessage::AvatarsRemoved { ids, .. } => {
+                for id in ids {
+                    pending_remotes.remove(&id);
+                    for (e, r) in remote_avatars.iter() {
+                        if r.sim_id == id {
+                            commands.entity(e).despawn();
+                        }
+                    }
+                }
+            }
+            NetMessage::RegionEntered { region, prims } => {
+                tracing::debug!(region_id = region.id, prims = prims.len(), "region entered");
+                // Re-entry replaces whatever is held for the region (ADR-012: events are idempotent).
+                despawn_region(
+                    &mut commands,
+                    region.id,
+                    &region_entities,
+                    &prim_entities,
+                    &mut spawned,
+                );
+                let region_id = region.id;
+                let e = commands.spawn(region_from_dto(region)).id();
+                spawned.regions.insert(region_id, e);
+                for p in prims {
+                    let id = p.id;
+                    let e = commands.spawn(prim_bundle(p)).id();
+                    spawned.prims.insert(id, (region_id, e));
+                }
+            }
+            NetMessage::RegionLeft { region_id } => {
+                tracing::debug!(region_id, "region left");
+                despawn_region(
+                    &mut commands,
+                    region_id,
+                    &region_entities,
+                    &prim_entities,
+                    &mut spawned,
+                );
+            }
+            NetMessage::ServerError {
+                request_id,
+                code,
+                message,
+            } => {
+                let reason = describe_server_error(code, &message);
+                tracing::warn!(code, request_id, "{reason}");
+                if chat.was_sent(request_id) {
+                    chat.push(time.elapsed_secs_f64(), format!("(not sent: {reason})"));
+                    continue;
+                }
+                if let Some(n) = notice.as_mut() {
+                    n.0 = Some(reason);
+                }
+                // A refused edit was already shown locally; put the prim back as the sim has it.
+                let refused = edit_requests
+                    .as_mut()
+                    .and_then(|r| r.pending.remove(&request_id))
+                    .and_then(|p| p.op.before);
+                if let Some(before) = refused {
+                    let (id, region_id) = (before.id, before.region_id);
+                    despawn_prim(&mut commands, id, &prim_entities, &mut spawned);
+                    let e = commands.spawn(prim_bundle(before)).id();
+                    spawned.prims.insert(id, (region_id, e));
+                }
+            }
             NetMessage::PrimRemoved { id } => {
-                for (e, p) in prim_entities.iter() {
-                    if p.id == id {
-                        commands.entity(e).despawn();
-                        break;
+                despawn_prim(&mut commands, id, &prim_entities, &mut spawned);
+            }
+            // Replaced rather than patched: meshes and materials are built once per prim entity.
+            NetMessage::PrimUpdated { prim } => {
+                let (id, region_id) = (prim.id, prim.region_id);
+                if let Some(r) = edit_requests.as_mut() {
+                    if r.select_on_arrival == Some(id) {
+                        r.select_on_arrival = None;
+                        game_state.selected_prim_id = Some(id);
+                    }
+                }
+                despawn_prim(&mut commands, id, &prim_entities, &mut spawned);
+                let e = commands.spawn(prim_bundle(prim)).id();
+                spawned.prims.insert(id, (region_id, e));
+            }
+            NetMessage::ChatMessage {
+                from_avatar_id,
+                scope,
+                text,
+                ..
+            } => {
+                let line = describe_chat(from_avatar_id, local_sim_id.0, &profiles, scope, &text);
+                chat.push(time.elapsed_secs_f64(), line);
+            }
+            // Kept for the whole session: avatar ids are never reused.
+            NetMessage::AvatarProfiles { profiles: received } => {
+                for p in received {
+                    profiles.0.insert(p.id, p);
+                }
+            }
+            NetMessage::AvatarAppearances {
+                appearances: received,
+            } => {
+                for a in received {
+                    appearances.0.insert(a.id, a.appearance);
+                }
+            }
+            NetMessage::PrimEditAck { request_id, id } => {
+                tracing::debug!(request_id, prim_id = id, "prim edit applied");
+                let Some(PendingEdit {
+                    mut op,
+                    origin,
+                    step,
+                }) = edit_requests
+                    .as_mut()
+                    .and_then(|r| r.pending.remove(&request_id))
+                else {
+                    continue;
+                };
+                let created = op.before.is_none();
+                if let (true, Some(after)) = (created, op.after.as_mut()) {
+                    // An undone delete comes back under a new id.
+                    if origin != EditOrigin::Do {
+                        edit_history.remap(after.id, id);
+                    }
+                    after.id = id;
+                }
+                edit_history.record(origin, step, op);
+                // Our own create: select the new prim, as offline creates are, once it is here.
+                if let (true, Some(r)) = (created, edit_requests.as_mut()) {
+                    let arrived = spawned.prims.contains_key(&id)
+                        || prim_entities.iter().any(|(_, p)| p.id == id);
+                    if arrived {
+                        game_state.selected_prim_id = Some(id);
+                    } else {
+                        r.select_on_arrival = Some(id);
                     }
                 }
             }
             _ => {}
         }
     }
+    for r in pending_remotes.into_values() {
+        spawn_remote_avatar(&mut commands, r);
+    }
+}
+
+/// User-facing text for a `ServerError`; unknown codes come from a newer sim.
+fn describe_server_error(code: u32, message: &str) -> String {
+    match ErrorCode::from_wire(code) {
+        Some(c) if c.ends_session() => format!("disconnected: {} ({message})", c.describe()),
+        Some(c) => format!("{} ({message})", c.describe()),
+        None => format!("server error {code} ({message})"),
+    }
+}
+
+/// Region and prim entities spawned while draining the mailbox. Queries do not see them until the
+/// commands apply, so later messages in the same frame find them here.
+#[derive(Default)]
+struct FrameSpawns {
+    regions: HashMap<i64, Entity>,
+    /// Prim id → (region id, entity).
+    prims: HashMap<i64, (i64, Entity)>,
+}
+
+/// Despawn one prim, including an entity spawned for it earlier this frame.
+fn despawn_prim(
+    commands: &mut Commands,
+    id: i64,
+    prim_entities: &Query<(Entity, &Prim)>,
+    spawned: &mut FrameSpawns,
+) {
+    if let Some((_, e)) = spawned.prims.remove(&id) {
+        commands.entity(e).despawn();
+    }
+    for (e, p) in prim_entities.iter() {
+        if p.id == id {
+            commands.entity(e).despawn();
+        }
+    }
+}
+
+/// Despawn a region and its prims, including entities spawned earlier this frame.
+fn despawn_region(
+    commands: &mut Commands,
+    region_id: i64,
+    region_entities: &Query<(Entity, &Region)>,
+    prim_entities: &Query<(Entity, &Prim)>,
+    spawned: &mut FrameSpawns,
+) {
+    if let Some(e) = spawned.regions.remove(&region_id) {
+        commands.entity(e).despawn();
+    }
+    spawned.prims.retain(|_, &mut (region, e)| {
+        if region == region_id {
+            commands.entity(e).despawn();
+        }
+        region != region_id
+    });
+    for (e, r) in region_entities.iter() {
+        if r.id == region_id {
+            commands.entity(e).despawn();
+        }
+    }
+    for (e, p) in prim_entities.iter() {
+        if p.region_id == region_id {
+            commands.entity(e).despawn();
+        }
+    }
 }
 
 /// Updates authoritative position and replicated yaw. Does not touch [`AvatarState::online_tank_yaw`]
 /// (snapshot reconciliation there would cancel A/D before the server applies the next intent).
-fn apply_local_avatar_pose(
-    avatars: &[AvatarStateDto],
-    local_id: Option<u64>,
-    avatar_state: &mut AvatarState,
-) {
-    let Some(a) = local_avatar_dto(avatars, local_id) else {
-        return;
-    };
+fn apply_local_avatar_pose(a: &AvatarStateDto, avatar_state: &mut AvatarState) {
     avatar_state.position = a.position;
     avatar_state.sim_facing_yaw = snap_yaw_continuation(avatar_state.sim_facing_yaw, a.yaw);
 }
 
+/// Same as [`apply_local_avatar_pose`] for the fields present in an ADR-011 delta.
+fn apply_local_avatar_delta(d: &AvatarDeltaDto, avatar_state: &mut AvatarState) {
+    if let Some(p) = d.position {
+        avatar_state.position = p;
+    }
+    if let Some(y) = d.yaw {
+        avatar_state.sim_facing_yaw = snap_yaw_continuation(avatar_state.sim_facing_yaw, y);
+    }
+}
+
+/// ADR-010: re-base the prediction on the sim's state after `input_seq`; the visual jump this
+/// would cause is moved into [`LocalPrediction::correction`] and faded out.
+fn reconcile_prediction(prediction: Option<&mut LocalPrediction>, position: Vec3, input_seq: u32) {
+    let Some(p) = prediction.filter(|p| p.enabled) else {
+        return;
+    };
+    let before = p.predictor.predicted();
+    p.predictor.reconcile(position, input_seq);
+    p.correction += before - p.predictor.predicted();
+}
+
+fn new_remote_avatar(tick: u64, a: AvatarStateDto) -> RemoteAvatar {
+    let mut r = RemoteAvatar {
+        sim_id: a.id,
+        anim: a.anim,
+        latest: a,
+        states: InterpolationBuffer::default(),
+    };
+    r.states.push(tick, AvatarSample::from(&r.latest));
+    r
+}
+
+/// Full state at `tick` (join snapshot or re-add), buffered for interpolation.
+fn set_remote_avatar(r: &mut RemoteAvatar, tick: u64, a: AvatarStateDto) {
+    r.latest = a;
+    r.states.push(tick, AvatarSample::from(&r.latest));
+}
+
+/// ADR-011 delta at `tick`: fields not present are unchanged since the previous state.
+fn patch_remote_avatar(r: &mut RemoteAvatar, tick: u64, d: &AvatarDeltaDto) {
+    r.latest.apply_delta(d);
+    r.states.push(tick, AvatarSample::from(&r.latest));
+}
+
 fn apply_local_avatar_pose_full(
     avatars: &[AvatarStateDto],
     local_id: Option<u64>,
@@ -277,6 +753,7 @@ fn local_avatar_dto<'a>(avatars: &'a [AvatarStateDto], local_id: Option<u64>) ->
 
 fn sync_remote_avatar_entities(
     commands: &mut Commands,
+    tick: u64,
     avatars: &[AvatarStateDto],
     local_id: Option<u64>,
     remote_query: &mut Query<(Entity, &mut RemoteAvatar), Without<Avatar>>,
@@ -303,29 +780,23 @@ fn sync_remote_avatar_entities(
         let mut found = false;
         for (_, mut r) in remote_query.iter_mut() {
             if r.sim_id == a.id {
-                r.net_position = a.position;
-                r.net_yaw = snap_yaw_continuation(r.net_yaw, a.yaw);
+                set_remote_avatar(&mut r, tick, a.clone());
                 found = true;
                 break;
             }
         }
         if !found {
-            let y = snap_yaw_continuation(0.0, a.yaw);
-            commands.spawn((
-                RemoteAvatar {
-                    sim_id: a.id,
-                    net_position: a.position,
-                    net_yaw: y,
-                },
-                RemoteAvatarMotionHint::default(),
-                Transform::from_translation(a.position)
-                    .with_rotation(Quat::from_rotation_y(wrap_angle_pi(y)))
-                    .with_scale(Vec3::splat(0.02)),
-            ));
+            spawn_remote_avatar(commands, new_remote_avatar(tick, a.clone()));
         }
     }
 }
 
+fn spawn_remote_avatar(commands: &mut Commands, r: RemoteAvatar) {
+    let transform = Transform::from_translation(r.latest.position)
+        .with_rotation(Quat::from_rotation_y(wrap_angle_pi(r.latest.yaw)));
+    commands.spawn((r, transform));
+}
+
 fn region_from_dto(r: RegionDto) -> Region {
     Region {
         id: r.id,
@@ -339,76 +810,54 @@ fn region_from_dto(r: RegionDto) -> Region {
     }
 }
 
-fn prim_bundle_from_dto(p: PrimDto) -> (Prim, Transform) {
-    (
-        Prim {
-            id: p.id,
-            region_id: p.region_id,
-            name: p.name,
-            shape: PrimShape::from_str(&p.shape),
-            color: Color::srgb(p.color[0], p.color[1], p.color[2]),
-        },
-        Transform::from_translation(p.position)
-            .with_rotation(Quat::from_euler(
-                EulerRot::XYZ,
-                p.rotation.x,
-                p.rotation.y,
-                p.rotation.z,
-            ))
-            .with_scale(p.scale),
-    )
-}
-
+/// Sample this frame's input, advance the local prediction and send each finished command (ADR-010).
+/// Against a pre-prediction sim, send the raw input every frame as before.
 pub fn send_network_intent(
     online: Option<Res<OnlineSession>>,
+    prediction: Option<ResMut<LocalPrediction>>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     camera_state: Res<crate::resources::CameraState>,
+    avatar_state: Res<AvatarState>,
+    time: Res<Time>,
+    mut last_yaw: Local<f32>,
 ) {
     let Some(sess) = online else {
         return;
     };
-    if camera_state.mode == crate::resources::CameraMode::Free {
+    let free_camera = camera_state.mode == crate::resources::CameraMode::Free;
+    // Free camera: the avatar stands still (but keeps falling), facing where it last looked.
+    let input = if free_camera {
+        MoveInput {
+            display_yaw: *last_yaw,
+            flying: avatar_state.is_flying,
+            ..default()
+        }
+    } else {
+        sample_move_input(&keyboard_input, camera_state.azimuth, avatar_state.is_flying)
+    };
+    *last_yaw = input.display_yaw;
+
+    let Some(mut p) = prediction.filter(|p| p.enabled) else {
+        if !free_camera {
+            let _ = sess.intent_tx.send(intent_message(0, 0.0, &input));
+        }
         return;
+    };
+    if let Some(cmd) = p.predictor.push(input, time.delta_secs()) {
+        let _ = sess.intent_tx.send(intent_message(cmd.seq, cmd.dt, &cmd.input));
     }
-
-    let move_forward =
-        keyboard_input.pressed(KeyCode::KeyW) || keyboard_input.pressed(KeyCode::ArrowUp);
-    let move_backward =
-        keyboard_input.pressed(KeyCode::KeyS) || keyboard_input.pressed(KeyCode::ArrowDown);
-    let move_left =
-        keyboard_input.pressed(KeyCode::KeyA) || keyboard_input.pressed(KeyCode::ArrowLeft);
-    let move_right =
-        keyboard_input.pressed(KeyCode::KeyD) || keyboard_input.pressed(KeyCode::ArrowRight);
-    let fly_up = keyboard_input.pressed(KeyCode::Space);
-    let fly_down = keyboard_input.pressed(KeyCode::ShiftLeft)
-        || keyboard_input.pressed(KeyCode::ShiftRight);
-
-    let az = camera_state.azimuth;
-    let v = wish_dir_camera_relative(az, move_forward, move_backward, move_left, move_right);
-    let display_yaw = fox_facing_yaw_from_camera(az);
-
-    let _ = sess.intent_tx.send(NetMessage::ClientIntent {
-        request_id: 0,
-        move_x: v.x,
-        move_z: v.z,
-        display_yaw,
-        fly_up,
-        fly_down,
-    });
 }
 
-pub fn send_observer_update(
-    online: Option<Res<OnlineSession>>,
-    avatar_state: Res<AvatarState>,
-    camera_state: Res<crate::resources::CameraState>,
-) {
-    let Some(sess) = online else {
-        return;
-    };
-    if camera_state.mode == crate::resources::CameraMode::Free {
-        return;
+fn intent_message(input_seq: u32, dt: f32, input: &MoveInput) -> NetMessage {
+    NetMessage::ClientIntent {
+        request_id: 0,
+        input_seq,
+        dt,
+        move_x: input.move_x,
+        move_z: input.move_z,
+        display_yaw: input.display_yaw,
+        fly_up: input.fly_up,
+        fly_down: input.fly_down,
+        flying: input.flying,
     }
-    let _ = sess.intent_tx.send(NetMessage::ObserverUpdate {
-        position: avatar_state.position,
-    });
 }
@@ -0,0 +1,27 @@
+//! 2D overlay drawn over the world by its own camera (the workspace builds Bevy without
+//! `bevy_ui`): the chat log (ADR-018) and name tags (ADR-019). Overlay entities use `Text2d` in
+//! [`OVERLAY_LAYER`], in logical pixels with the origin at the window centre and y up.
+
+use bevy::prelude::*;
+use bevy::render::camera::ClearColorConfig;
+use bevy::render::view::RenderLayers;
+
+/// Keeps the overlay out of the world camera and the world out of the overlay camera.
+pub const OVERLAY_LAYER: usize = 1;
+
+pub fn setup_overlay_camera(mut commands: Commands) {
+    commands.spawn((
+        Camera2d,
+        Camera {
+            order: 1,
+            clear_color: ClearColorConfig::None,
+            ..default()
+        },
+        RenderLayers::layer(OVERLAY_LAYER),
+    ));
+}
+
+/// Overlay position of a viewport point (origin top-left, y down) in a window of `size`.
+pub fn viewport_to_overlay(point: Vec2, size: Vec2) -> Vec2 {
+    Vec2::new(point.x - size.x / 2.0, size.y / 2.0 - point.y)
+}
@@ -0,0 +1,227 @@
+//! Where the client's prim edits go (ADR-011 write path): the local `prims` table offline, an
+//! `EditPrim` request to the sim online. Offline the prim entity is respawned to match what was
+//! stored; online the sim's `PrimUpdated` does that, and a refusal rolls the prim back (see
+//! `network::apply_network_snapshot`). Applied edits go into the [`EditHistory`] for undo/redo
+//! (ADR-017).
+
+use crate::components::{Prim, PrimShape, Region};
+use crate::db::prims::{delete_prim, insert_prim, update_prim};
+use crate::resources::{
+    Database, EditHistory, EditOrigin, NetworkSyncState, OnlineSession, PendingEdit,
+    PrimEditRequests, PrimOp,
+};
+use crate::systems::rendering::RegionMesh;
+use bevy::diagnostic::FrameCount;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use vibe_core::edit::{edits_between, validate_prim};
+use vibe_core::world::REGION_SIZE_METERS;
+use vibe_core::{Capabilities, NetMessage, PrimDto, PrimEdit};
+
+#[derive(SystemParam)]
+pub struct PrimWriter<'w, 's> {
+    commands: Commands<'w, 's>,
+    db: Option<Res<'w, Database>>,
+    online: Option<Res<'w, OnlineSession>>,
+    sync: Option<Res<'w, NetworkSyncState>>,
+    requests: Option<ResMut<'w, PrimEditRequests>>,
+    history: ResMut<'w, EditHistory>,
+    frame: Res<'w, FrameCount>,
+    regions: Query<'w, 's, (&'static Region, &'static GlobalTransform), With<RegionMesh>>,
+    prims: Query<'w, 's, (Entity, &'static Prim)>,
+}
+
+impl PrimWriter<'_, '_> {
+    /// Region whose square contains `point` (by x/z), the one with the nearest centre if several do.
+    pub fn region_at(&self, point: Vec3) -> Option<i64> {
+        let half = REGION_SIZE_METERS as f32 / 2.0;
+        self.regions
+            .iter()
+            .map(|(r, tf)| (r.id, (point - tf.translation()).xz().abs()))
+            .filter(|(_, offset)| offset.max_element() <= half)
+            .min_by(|(_, a), (_, b)| a.length_squared().total_cmp(&b.length_squared()))
+            .map(|(id, _)| id)
+    }
+
+    /// Nearest point where `ray` meets a region's floor.
+    pub fn ground_hit(&self, ray: Ray3d) -> Option<Vec3> {
+        let half = REGION_SIZE_METERS as f32 / 2.0;
+        self.regions
+            .iter()
+            .filter_map(|(_, tf)| {
+                let origin = tf.translation();
+                let d = ray.intersect_plane(origin, InfinitePlane3d::new(Vec3::Y))?;
+                let hit = ray.get_point(d);
+                ((hit - origin).xz().abs().max_element() <= half).then_some((d, hit))
+            })
+            .min_by(|(a, _), (b, _)| a.total_cmp(b))
+            .map(|(_, hit)| hit)
+    }
+
+    /// Store or send `edit`; `before` is the prim as it is now (`None` for a create). Online,
+    /// deletes disappear at once and the rest arrives from the sim. Returns the id of a prim created
+    /// offline. `Err` is user-facing and means nothing was written.
+    pub fn submit(
+        &mut self,
+        before: Option<&PrimDto>,
+        edit: PrimEdit,
+    ) -> Result<Option<i64>, String> {
+        self.apply(before, edit, EditOrigin::Do)
+    }
+
+    /// Undo this user's latest edit step, or redo the latest undone one, by restoring the `before`
+    /// of each of its operations. Only what an operation changed is restored, so later edits by
+    /// others to other fields survive. A step that fails part-way is dropped. Returns the id of a
+    /// prim recreated offline.
+    pub fn revert(&mut self, origin: EditOrigin) -> Result<Option<i64>, String> {
+        let Some(ops) = self.history.take(origin) else {
+            let what = if origin == EditOrigin::Redo {
+                "redo"
+            } else {
+                "undo"
+            };
+            return Err(format!("nothing to {what}"));
+        };
+        let mut recreated = None;
+        for op in ops.iter().rev() {
+            let mut current = op.after.clone();
+            for edit in edits_between(op.after.as_ref(), op.before.as_ref()) {
+                let next = current.as_ref().and_then(|c| edit.applied_to(c));
+                recreated = self.apply(current.as_ref(), edit, origin)?.or(recreated);
+                current = next;
+            }
+        }
+        Ok(recreated)
+    }
+
+    fn apply(
+        &mut self,
+        before: Option<&PrimDto>,
+        edit: PrimEdit,
+        origin: EditOrigin,
+    ) -> Result<Option<i64>, String> {
+        let step = self.frame.0;
+        let after = match (&edit, before) {
+            (PrimEdit::Create(new), _) => Some(new.clone()),
+            (edit, Some(before)) => edit.applied_to(before),
+            (_, None) => return Err("no prim to edit".into()),
+        };
+        if let Some(online) = &self.online {
+            let capabilities = self
+                .sync
+                .as_ref()
+                .map_or(Capabilities::empty(), |s| s.capabilities);
+            if !capabilities.contains(Capabilities::PRIM_EDITING) {
+                return Err("this sim does not accept prim edits".into());
+            }
+            // Offline regions have no sim origin (legacy layout), so only online edits are checked.
+            if let Some(after) = &after {
+                let sim_origin = self
+                    .regions
+                    .iter()
+                    .find(|(r, _)| r.id == after.region_id)
+                    .and_then(|(r, _)| r.sim_origin);
+                if let Some(sim_origin) = sim_origin {
+                    validate_prim(after, sim_origin)?;
+                }
+            }
+            let Some(requests) = self.requests.as_mut() else {
+                return Err("not connected".into());
+            };
+            let request_id = requests.next_request_id();
+            let deleted = match edit {
+                PrimEdit::Delete { id } => Some(id),
+                _ => None,
+            };
+            online
+                .intent_tx
+                .send(NetMessage::EditPrim { request_id, edit })
+                .map_err(|_| "not connected".to_string())?;
+            let op = PrimOp {
+                before: before.cloned(),
+                after,
+            };
+            let pending = PendingEdit { op, origin, step };
+            requests.pending.insert(request_id, pending);
+            if let Some(id) = deleted {
+                self.despawn(id);
+            }
+            return Ok(None);
+        }
+
+        let Some(db) = &self.db else {
+            return Err("no world database".into());
+        };
+        let conn = db
+            .conn
+            .lock()
+            .map_err(|_| "world database is unavailable".to_string())?;
+        let (stored, created) = match (edit, after) {
+            (PrimEdit::Create(mut new), _) => {
+                let old_id = new.id;
+                new.id = insert_prim(&conn, &new).map_err(|e| format!("saving new prim: {e}"))?;
+                drop(conn);
+                if origin != EditOrigin::Do {
+                    self.history.remap(old_id, new.id);
+                }
+                let id = new.id;
+                self.commands.spawn(prim_bundle(new.clone()));
+                (Some(new), Some(id))
+            }
+            (PrimEdit::Delete { id }, _) => {
+                delete_prim(&conn, id).map_err(|e| format!("deleting prim {id}: {e}"))?;
+                drop(conn);
+                self.despawn(id);
+                (None, None)
+            }
+            (_, Some(after)) => match update_prim(&conn, &after) {
+                Ok(true) => {
+                    drop(conn);
+                    self.despawn(after.id);
+                    self.commands.spawn(prim_bundle(after.clone()));
+                    (Some(after), None)
+                }
+                Ok(false) => return Err(format!("prim {} is not in the world database", after.id)),
+                Err(e) => return Err(format!("saving prim {}: {e}", after.id)),
+            },
+            (_, None) => return Ok(None),
+        };
+        let op = PrimOp {
+            before: before.cloned(),
+            after: stored,
+        };
+        self.history.record(origin, step, op);
+        Ok(created)
+    }
+
+    fn despawn(&mut self, id: i64) {
+        for (e, p) in &self.prims {
+            if p.id == id {
+                self.commands.entity(e).despawn();
+            }
+        }
+    }
+}
+
+/// Entity components for a stored prim; `rendering::spawn_prims` adds its mesh.
+pub fn prim_bundle(p: PrimDto) -> (Prim, Transform) {
+    let transform = dto_transform(&p);
+    (
+        Prim {
+            id: p.id,
+            region_id: p.region_id,
+            name: p.name,
+            shape: PrimShape::from_str(&p.shape),
+            color: Color::srgb(p.color[0], p.color[1], p.color[2]),
+        },
+        transform,
+    )
+}
+
+/// Placement of a stored prim; rotation is XYZ Euler radians, as `Prim::to_dto` writes it.
+pub fn dto_transform(prim: &PrimDto) -> Transform {
+    let r = prim.rotation;
+    Transform::from_translation(prim.position)
+        .with_rotation(Quat::from_euler(EulerRot::XYZ, r.x, r.y, r.z))
+        .with_scale(prim.scale)
+}
@@ -1,6 +1,7 @@
 use bevy::prelude::*;
 use bevy::math::primitives::{Cuboid, Cylinder, Sphere, Torus};
 use crate::components::{Region, Prim, PrimShape};
+use crate::systems::ground_lod::GroundPatch;
 use crate::systems::tile_loader::{RegionTile, TileKey};
 use vibe_core::world::REGION_SIZE_METERS;
 
@@ -86,10 +87,7 @@ pub fn spawn_regions(
             transform,
             Visibility::Visible,
             RegionMesh,
-            RegionTile {
-                tile_key,
-                lod_level: 1, // Start with medium quality
-            },
+            RegionTile { tile_key },
         ));
     }
 
@@ -98,13 +96,13 @@ pub fn spawn_regions(
     }
 }
 
-/// Update region materials when tile textures are loaded
+/// Update region and ground patch materials when tile textures are loaded; patches show from then on
 pub fn update_region_materials(
     mut materials: ResMut<Assets<StandardMaterial>>,
-    mut region_query: Query<(&mut MeshMaterial3d<StandardMaterial>, &crate::systems::tile_loader::RegionTileTexture), (With<RegionMesh>, Changed<crate::systems::tile_loader::RegionTileTexture>)>,
+    mut region_query: Query<(&mut MeshMaterial3d<StandardMaterial>, &crate::systems::tile_loader::RegionTileTexture, &mut Visibility, Has<GroundPatch>), (Or<(With<RegionMesh>, With<GroundPatch>)>, Changed<crate::systems::tile_loader::RegionTileTexture>)>,
     images: Res<Assets<Image>>,
 ) {
-    for (mut material, tile_texture) in region_query.iter_mut() {
+    for (mut material, tile_texture, mut visibility, is_patch) in region_query.iter_mut() {
         if images.get(&tile_texture.handle).is_some() {
             // Create new material with tile texture
             let new_material = materials.add(StandardMaterial {
@@ -112,6 +110,9 @@ pub fn update_region_materials(
                 ..default()
             });
             *material = MeshMaterial3d(new_material);
+            if is_patch {
+                *visibility = Visibility::Inherited;
+            }
         }
     }
 }
@@ -124,7 +125,8 @@ pub fn spawn_prims(
 ) {
     for (entity, prim, transform) in prim_query.iter() {
         let mesh_handle = match prim.shape {
-            PrimShape::Box => meshes.add(Cuboid::new(transform.scale.x / 2.0, transform.scale.y / 2.0, transform.scale.z / 2.0)),
+            // Unit meshes: the transform's scale is the prim's size, so edits need no new mesh.
+            PrimShape::Box => meshes.add(Cuboid::from_length(1.0)),
             PrimShape::Sphere => meshes.add(Sphere::default()),
             PrimShape::Cylinder => meshes.add(Cylinder::default()),
             PrimShape::Cone => meshes.add(Cylinder::default()), // Use cylinder as cone substitute
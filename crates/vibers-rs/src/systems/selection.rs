@@ -0,0 +1,205 @@
+//! Prim selection: a left click (press and release without dragging the camera) casts a ray from
+//! the cursor against the triangles of every [`PrimMesh`] and selects the nearest prim. The
+//! workspace builds Bevy without `bevy_picking`, so the ray cast is done here.
+
+use crate::components::Prim;
+use crate::resources::GameState;
+use crate::systems::free_camera::FreeCamera;
+use crate::systems::rendering::PrimMesh;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::primitives::Aabb;
+use bevy::window::PrimaryWindow;
+
+/// Cursor travel (px) between press and release beyond which the click was a camera drag.
+const CLICK_SLOP: f32 = 4.0;
+/// Emissive tint of the selected prim's material.
+const HIGHLIGHT: LinearRgba = LinearRgba::rgb(0.45, 0.35, 0.05);
+
+/// Marks the prim entity whose material currently carries the selection highlight.
+#[derive(Component)]
+pub struct Highlighted;
+
+/// The cursor and the camera looking through it.
+#[derive(SystemParam)]
+pub struct CursorView<'w, 's> {
+    windows: Query<'w, 's, &'static Window, With<PrimaryWindow>>,
+    cameras: Query<'w, 's, (&'static Camera, &'static GlobalTransform), With<FreeCamera>>,
+}
+
+impl CursorView<'_, '_> {
+    pub fn cursor(&self) -> Option<Vec2> {
+        self.windows.single().ok().and_then(Window::cursor_position)
+    }
+
+    /// World-space ray through viewport point `at`.
+    pub fn ray(&self, at: Vec2) -> Option<Ray3d> {
+        let (camera, tf) = self.cameras.single().ok()?;
+        camera.viewport_to_world(tf, at).ok()
+    }
+
+    /// Viewport point where `world` is drawn.
+    pub fn to_viewport(&self, world: Vec3) -> Option<Vec2> {
+        let (camera, tf) = self.cameras.single().ok()?;
+        camera.world_to_viewport(tf, world).ok()
+    }
+}
+
+type HighlightTarget<'a> = (
+    Entity,
+    &'a Prim,
+    &'a MeshMaterial3d<StandardMaterial>,
+    Has<Highlighted>,
+);
+
+pub fn pick_prim(
+    mouse_input: Res<ButtonInput<MouseButton>>,
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    view: CursorView,
+    prims: Query<(&Prim, &Mesh3d, &GlobalTransform, Option<&Aabb>), With<PrimMesh>>,
+    meshes: Res<Assets<Mesh>>,
+    mut game_state: ResMut<GameState>,
+    mut pressed_at: Local<Option<Vec2>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        game_state.selected_prim_id = None;
+    }
+    let cursor = view.cursor();
+    if mouse_input.just_pressed(MouseButton::Left) {
+        *pressed_at = cursor;
+    }
+    if !mouse_input.just_released(MouseButton::Left) {
+        return;
+    }
+    let (Some(start), Some(cursor)) = (pressed_at.take(), cursor) else {
+        return;
+    };
+    if start.distance(cursor) > CLICK_SLOP {
+        return;
+    }
+    let Some(ray) = view.ray(cursor) else {
+        return;
+    };
+
+    let mut nearest: Option<(f32, i64)> = None;
+    for (prim, mesh, tf, aabb) in &prims {
+        let Some(t) = meshes.get(&mesh.0).and_then(|m| ray_prim(ray, m, tf, aabb)) else {
+            continue;
+        };
+        if nearest.is_none_or(|(best, _)| t < best) {
+            nearest = Some((t, prim.id));
+        }
+    }
+    game_state.selected_prim_id = nearest.map(|(_, id)| id);
+    if let Some(id) = game_state.selected_prim_id {
+        tracing::debug!(prim_id = id, "selected prim");
+    }
+}
+
+/// Keep the emissive highlight on the selected prim only. Prims are respawned when they change
+/// (ADR-011 `PrimUpdated`), so the highlight follows the id rather than an entity. A selection
+/// whose prim is gone is cleared.
+pub fn highlight_selection(
+    mut commands: Commands,
+    mut game_state: ResMut<GameState>,
+    prims: Query<HighlightTarget, With<PrimMesh>>,
+    all_prims: Query<&Prim>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let selected = game_state.selected_prim_id;
+    if let Some(id) = selected {
+        if !all_prims.iter().any(|p| p.id == id) {
+            game_state.selected_prim_id = None;
+        }
+    }
+    for (entity, prim, material, highlighted) in &prims {
+        let want = selected == Some(prim.id);
+        if want == highlighted {
+            continue;
+        }
+        let Some(m) = materials.get_mut(&material.0) else {
+            continue;
+        };
+        if want {
+            m.emissive = HIGHLIGHT;
+            commands.entity(entity).insert(Highlighted);
+        } else {
+            m.emissive = LinearRgba::BLACK;
+            commands.entity(entity).remove::<Highlighted>();
+        }
+    }
+}
+
+/// Distance along `ray` to the nearest triangle of a prim's mesh placed at `tf`, if it is hit.
+pub(crate) fn ray_prim(
+    ray: Ray3d,
+    mesh: &Mesh,
+    tf: &GlobalTransform,
+    aabb: Option<&Aabb>,
+) -> Option<f32> {
+    // Rays map to local space affinely, so the hit distance along the ray is unchanged.
+    let to_local = tf.affine().inverse();
+    let origin = to_local.transform_point3(ray.origin);
+    let dir = to_local.transform_vector3(*ray.direction);
+    if let Some(aabb) = aabb {
+        let (min, max) = (Vec3::from(aabb.min()), Vec3::from(aabb.max()));
+        ray_box(origin, dir, min, max)?;
+    }
+    ray_mesh(origin, dir, mesh)
+}
+
+/// Entry distance of the ray into an axis-aligned box (slab test), if it hits in front.
+fn ray_box(origin: Vec3, dir: Vec3, min: Vec3, max: Vec3) -> Option<f32> {
+    let inv = dir.recip();
+    let t1 = (min - origin) * inv;
+    let t2 = (max - origin) * inv;
+    let near = t1.min(t2).max_element();
+    let far = t1.max(t2).min_element();
+    (far >= near.max(0.0)).then_some(near.max(0.0))
+}
+
+/// Nearest hit of the ray with the mesh's triangles (either face), in units of `dir`.
+fn ray_mesh(origin: Vec3, dir: Vec3, mesh: &Mesh) -> Option<f32> {
+    let Some(VertexAttributeValues::Float32x3(positions)) =
+        mesh.attribute(Mesh::ATTRIBUTE_POSITION)
+    else {
+        return None;
+    };
+    let vertex = |i: usize| positions.get(i).map(|p| Vec3::from(*p));
+    let indices: Vec<usize> = match mesh.indices() {
+        Some(Indices::U16(i)) => i.iter().map(|&i| i as usize).collect(),
+        Some(Indices::U32(i)) => i.iter().map(|&i| i as usize).collect(),
+        None => (0..positions.len()).collect(),
+    };
+    indices
+        .chunks_exact(3)
+        .filter_map(|tri| {
+            let (a, b, c) = (vertex(tri[0])?, vertex(tri[1])?, vertex(tri[2])?);
+            ray_triangle(origin, dir, a, b, c)
+        })
+        .min_by(f32::total_cmp)
+}
+
+/// Möller–Trumbore ray/triangle intersection, two-sided.
+fn ray_triangle(origin: Vec3, dir: Vec3, a: Vec3, b: Vec3, c: Vec3) -> Option<f32> {
+    let (e1, e2) = (b - a, c - a);
+    let p = dir.cross(e2);
+    let det = e1.dot(p);
+    if det.abs() < f32::EPSILON {
+        return None;
+    }
+    let inv_det = det.recip();
+    let s = origin - a;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+    let q = s.cross(e1);
+    let v = dir.dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+    let t = e2.dot(q) * inv_det;
+    (t > 0.0).then_some(t)
+}
@@ -1,142 +1,220 @@
+//! Region ground textures from map tiles (ADR-004). Tiles are downloaded on the [`blocking`]
+//! thread pool, since the HTTP client blocks and Bevy's [`IoTaskPool`] also serves the asset
+//! server, and decoded on Bevy's [`AsyncComputeTaskPool`]. The finished tasks are polled back into
+//! the [`TileCache`] every frame, so a slow tile server never stalls a frame. A tile that
+//! keeps failing is retried with backoff and then given up on. Tiles come from the [`TileSource`]
+//! the URL template names.
+
 use crate::resources::OsmTileUrlTemplate;
+use crate::tile_source::{HttpTiles, TileSource};
 use bevy::prelude::*;
 use bevy::render::render_asset::RenderAssetUsages;
+use bevy::tasks::futures::check_ready;
+use bevy::tasks::{AsyncComputeTaskPool, IoTaskPool, Task};
 use bevy_image::{Image, ImageSampler};
 use std::collections::HashMap;
-use std::io::Read;
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
 pub use vibe_core::TileKey;
 
+/// Tiles downloading or decoding at once; more would only queue up at the tile server.
+const MAX_CONCURRENT_FETCHES: usize = 4;
+/// Attempts per tile before it is marked [`TileState::Failed`].
+const MAX_ATTEMPTS: u32 = 4;
+/// Wait before the first retry (seconds); doubled for each further one.
+const RETRY_BASE_DELAY: f64 = 2.0;
+
+/// Where one tile is in its loading. `attempt` counts from 1.
+pub enum TileState {
+    Downloading {
+        task: Task<Result<Vec<u8>, String>>,
+        attempt: u32,
+    },
+    Decoding {
+        task: Task<Result<Image, String>>,
+        attempt: u32,
+    },
+    /// `attempt` failed; the next one may start at `retry_at` (seconds since startup).
+    Waiting {
+        attempt: u32,
+        retry_at: f64,
+    },
+    Loaded(Handle<Image>),
+    /// Every attempt failed; not requested again this session.
+    Failed,
+}
+
 /// Resource for managing OSM tile loading and caching
 #[derive(Resource)]
 pub struct TileCache {
-    /// Cache of loaded tile handles
-    pub handles: Arc<Mutex<HashMap<TileKey, Handle<Image>>>>,
-    /// Cache of loading states (tiles currently being loaded)
-    pub loading: Arc<Mutex<HashMap<TileKey, bool>>>,
+    pub tiles: HashMap<TileKey, TileState>,
+    http: HttpTiles,
+    /// `--tile-url`: used instead of [`OsmTileUrlTemplate`].
+    tile_url: Option<String>,
+    /// The template in use and its source; `None` if it could not be opened.
+    source: Option<(String, Option<Arc<TileSource>>)>,
 }
 
-impl Default for TileCache {
-    fn default() -> Self {
+impl TileCache {
+    pub fn new(http: HttpTiles, tile_url: Option<String>) -> Self {
         Self {
-            handles: Arc::new(Mutex::new(HashMap::new())),
-            loading: Arc::new(Mutex::new(HashMap::new())),
+            tiles: HashMap::new(),
+            http,
+            tile_url,
+            source: None,
         }
     }
+
+    /// The source for `template`, opened when the template first shows up or changes.
+    fn source(&mut self, template: &str) -> Option<Arc<TileSource>> {
+        let template = self.tile_url.as_deref().unwrap_or(template);
+        match &self.source {
+            Some((current, source)) if current == template => source.clone(),
+            _ => {
+                let source = TileSource::open(template, &self.http)
+                    .map(Arc::new)
+                    .map_err(|e| tracing::warn!("{e:#}; map tiles are off"))
+                    .ok();
+                // Tiles still loading or given up on are asked of the new source instead.
+                self.tiles.retain(|_, s| matches!(s, TileState::Loaded(_)));
+                self.source = Some((template.to_owned(), source.clone()));
+                source
+            }
+        }
+    }
+
+    fn in_flight(&self) -> usize {
+        self.tiles
+            .values()
+            .filter(|s| {
+                matches!(
+                    s,
+                    TileState::Downloading { .. } | TileState::Decoding { .. }
+                )
+            })
+            .count()
+    }
+
+    /// Start attempt `attempt` at `key` in the background.
+    fn fetch(&mut self, key: &TileKey, source: Arc<TileSource>, attempt: u32) {
+        let key_owned = key.clone();
+        // Only the wait runs on the IO pool; dropping the task drops the download if it has not
+        // started yet.
+        let download = blocking::unblock(move || source.fetch(&key_owned));
+        let task = IoTaskPool::get().spawn(download);
+        self.tiles
+            .insert(key.clone(), TileState::Downloading { task, attempt });
+    }
+
+    /// Forget `key`: its texture is freed once no material uses it, and a fetch still queued is
+    /// dropped. A download already under way runs to the end, but its bytes are thrown away.
+    pub fn release(&mut self, key: &TileKey) {
+        self.tiles.remove(key);
+    }
+
+    /// Record that `attempt` at `key` failed: wait and retry, or give up after [`MAX_ATTEMPTS`]
+    /// (at once if the source will not change).
+    fn fail(&mut self, key: &TileKey, attempt: u32, error: &str, now: f64) {
+        let retry = matches!(&self.source, Some((_, Some(s))) if s.is_worth_retrying());
+        let state = if attempt >= MAX_ATTEMPTS || !retry {
+            tracing::warn!(
+                "tile {}: {error}; giving up after {attempt} attempt(s)",
+                key.to_path()
+            );
+            TileState::Failed
+        } else {
+            let delay = RETRY_BASE_DELAY * f64::from(1u32 << (attempt - 1));
+            tracing::warn!("tile {}: {error}; retrying in {delay} s", key.to_path());
+            TileState::Waiting {
+                attempt,
+                retry_at: now + delay,
+            }
+        };
+        self.tiles.insert(key.clone(), state);
+    }
 }
 
-/// Component to mark regions that need tile textures
+/// Ground textured with one tile: a region, or a patch of one (`ground_lod`)
 #[derive(Component)]
 pub struct RegionTile {
     pub tile_key: TileKey,
-    pub lod_level: u32, // 0 = high-res (2x2), 1 = medium-res (1x1), 2 = low-res (1x1)
 }
 
-/// Build tile URL from template (`{z}`, `{x}`, `{y}`) or default OSM (ADR-004 / ADR-014).
-#[must_use]
-pub fn format_osm_tile_url(template: &str, key: &TileKey) -> String {
-    if template.is_empty() {
-        return format!(
-            "https://tile.openstreetmap.org/{}/{}/{}.png",
-            key.z, key.x, key.y
-        );
-    }
-    template
-        .replace("{z}", &key.z.to_string())
-        .replace("{x}", &key.x.to_string())
-        .replace("{y}", &key.y.to_string())
+/// Decode a downloaded tile into a texture. Runs on the async compute task pool.
+fn decode_tile(bytes: &[u8]) -> Result<Image, String> {
+    let rgba = image::load_from_memory(bytes)
+        .map_err(|e| format!("decoding: {e}"))?
+        .to_rgba8();
+    let size = bevy::render::render_resource::Extent3d {
+        width: rgba.width(),
+        height: rgba.height(),
+        depth_or_array_layers: 1,
+    };
+    let mut bevy_image = Image::new(
+        size,
+        bevy::render::render_resource::TextureDimension::D2,
+        rgba.into_raw(),
+        bevy::render::render_resource::TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    bevy_image.sampler = ImageSampler::linear();
+    Ok(bevy_image)
 }
 
-/// Load a single OSM tile image
-pub fn load_tile_image(key: &TileKey, template: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    let url = format_osm_tile_url(template, key);
-    let response = ureq::get(&url).call()?;
-    let mut bytes = Vec::new();
-    response.into_reader().read_to_end(&mut bytes)?;
-    Ok(bytes)
-}
-
-/// System to load OSM tiles for regions
+/// System to load OSM tiles for regions: advance finished downloads and decodes, then texture
+/// regions whose tile is loaded and start (or retry) the tiles still missing.
 pub fn load_region_tiles(
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
-    tile_cache: Res<TileCache>,
+    mut tile_cache: ResMut<TileCache>,
     tile_url: Res<OsmTileUrlTemplate>,
+    time: Res<Time>,
     region_query: Query<(Entity, &RegionTile), Without<RegionTileTexture>>,
 ) {
-    let template = tile_url
-        .0
-        .lock()
-        .map(|g| g.clone())
-        .unwrap_or_default();
-    for (entity, region_tile) in region_query.iter() {
-        let tile_key = region_tile.tile_key.clone();
+    let now = time.elapsed_secs_f64();
+    let mut failed = Vec::new();
+    for (key, state) in tile_cache.tiles.iter_mut() {
+        match state {
+            TileState::Downloading { task, attempt } => match check_ready(task) {
+                Some(Ok(bytes)) => {
+                    let task =
+                        AsyncComputeTaskPool::get().spawn(async move { decode_tile(&bytes) });
+                    let attempt = *attempt;
+                    *state = TileState::Decoding { task, attempt };
+                }
+                Some(Err(e)) => failed.push((key.clone(), *attempt, e)),
+                None => {}
+            },
+            TileState::Decoding { task, attempt } => match check_ready(task) {
+                Some(Ok(image)) => *state = TileState::Loaded(images.add(image)),
+                Some(Err(e)) => failed.push((key.clone(), *attempt, e)),
+                None => {}
+            },
+            _ => {}
+        }
+    }
+    for (key, attempt, error) in failed {
+        tile_cache.fail(&key, attempt, &error, now);
+    }
 
-        {
-            let handles = tile_cache.handles.lock().unwrap();
-            if let Some(handle) = handles.get(&tile_key) {
+    let template = tile_url.0.lock().map(|g| g.clone()).unwrap_or_default();
+    let Some(source) = tile_cache.source(&template) else {
+        return;
+    };
+    for (entity, region_tile) in region_query.iter() {
+        let tile_key = &region_tile.tile_key;
+        let attempt = match tile_cache.tiles.get(tile_key) {
+            Some(TileState::Loaded(handle)) => {
                 commands.entity(entity).insert(RegionTileTexture {
                     handle: handle.clone(),
                 });
                 continue;
             }
-        }
-
-        {
-            let mut loading = tile_cache.loading.lock().unwrap();
-            if loading.contains_key(&tile_key) {
-                continue;
-            }
-            loading.insert(tile_key.clone(), true);
-        }
-
-        match load_tile_image(&tile_key, &template) {
-            Ok(bytes) => match image::load_from_memory(&bytes) {
-                Ok(img) => {
-                    let rgba = img.to_rgba8();
-                    let size = bevy::render::render_resource::Extent3d {
-                        width: rgba.width(),
-                        height: rgba.height(),
-                        depth_or_array_layers: 1,
-                    };
-
-                    let mut bevy_image = Image::new(
-                        size,
-                        bevy::render::render_resource::TextureDimension::D2,
-                        rgba.into_raw(),
-                        bevy::render::render_resource::TextureFormat::Rgba8UnormSrgb,
-                        RenderAssetUsages::default(),
-                    );
-
-                    bevy_image.sampler = ImageSampler::linear();
-
-                    let handle = images.add(bevy_image);
-
-                    {
-                        let mut handles = tile_cache.handles.lock().unwrap();
-                        handles.insert(tile_key.clone(), handle.clone());
-                    }
-
-                    commands.entity(entity).insert(RegionTileTexture {
-                        handle: handle.clone(),
-                    });
-
-                    {
-                        let mut loading = tile_cache.loading.lock().unwrap();
-                        loading.remove(&tile_key);
-                    }
-                }
-                Err(e) => {
-                    eprintln!("Failed to decode tile image: {}", e);
-                    let mut loading = tile_cache.loading.lock().unwrap();
-                    loading.remove(&tile_key);
-                }
-            },
-            Err(e) => {
-                eprintln!("Failed to load tile {}: {}", tile_key.to_path(), e);
-                let mut loading = tile_cache.loading.lock().unwrap();
-                loading.remove(&tile_key);
-            }
+            None => 1,
+            Some(TileState::Waiting { attempt, retry_at }) if *retry_at <= now => attempt + 1,
+            Some(_) => continue,
+        };
+        if tile_cache.in_flight() < MAX_CONCURRENT_FETCHES {
+            tile_cache.fetch(tile_key, source.clone(), attempt);
         }
     }
 }
@@ -0,0 +1,23 @@
+//! The client's map tile cache (ADR-004), so a client start does not download every region tile
+//! again. The cache itself is [`vibe_tile_cache`]; here are its defaults and the entry path,
+//! `<template hash>/{z}/{x}/{y}`. Past its expiry a tile is revalidated with a conditional request.
+
+use ring::digest::{digest, SHA256};
+use vibe_core::TileKey;
+
+pub use vibe_tile_cache::{CachedTile, TileDiskCache, TileValidity};
+
+/// Default cache directory, next to the offline world database.
+pub const DEFAULT_TILE_CACHE_DIR: &str = "data/tile-cache";
+/// Default size limit in MiB.
+pub const DEFAULT_TILE_CACHE_MB: u64 = 512;
+
+/// `<template hash>/{z}/{x}/{y}`: tiles of different sources never share an entry.
+pub fn entry_path(template: &str, key: &TileKey) -> String {
+    let hash = digest(&SHA256, template.as_bytes());
+    let prefix: String = hash.as_ref()[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect();
+    format!("{prefix}/{}", key.to_path())
+}
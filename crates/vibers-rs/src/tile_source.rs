@@ -0,0 +1,220 @@
+//! Where map tiles come from (ADR-004), chosen by the scheme of the tile URL template:
+//! - `http://…` / `https://…` with `{z}`, `{x}`, `{y}` (empty means openstreetmap.org), downloaded
+//!   through the [`TileDiskCache`]
+//! - `file://DIR`: a local `DIR/{z}/{x}/{y}.png` tree; a path with `{z}`, `{x}`, `{y}` sets the
+//!   layout instead
+//! - `mbtiles://FILE`: an MBTiles SQLite file of raster tiles
+//!
+//! Local sources are read directly: they bypass the disk cache and work with `--offline-tiles`.
+//! Paths are resolved on the client, also when the template comes from the sim.
+
+use crate::tile_disk_cache::{entry_path, CachedTile, TileDiskCache, TileValidity};
+use anyhow::Context;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use vibe_core::TileKey;
+
+/// Used when neither the handshake nor `--tile-url` names a template.
+const DEFAULT_OSM_TILE_URL_TEMPLATE: &str = "https://tile.openstreetmap.org/{z}/{x}/{y}.png";
+/// Per-request limit: downloads block an IO thread, so a stalled server must not hold it.
+const FETCH_TIMEOUT: Duration = Duration::from_secs(15);
+
+/// Settings shared by every HTTP source.
+#[derive(Clone)]
+pub struct HttpTiles {
+    agent: ureq::Agent,
+    disk: Option<Arc<TileDiskCache>>,
+    offline: bool,
+}
+
+impl HttpTiles {
+    /// `offline` serves tiles from `disk` only and never touches the network.
+    pub fn new(disk: Option<TileDiskCache>, offline: bool) -> Self {
+        Self {
+            agent: ureq::AgentBuilder::new().timeout(FETCH_TIMEOUT).build(),
+            disk: disk.map(Arc::new),
+            offline,
+        }
+    }
+
+    /// One tile image, from disk if fresh, else downloaded (conditionally, when a stale copy is on
+    /// disk). A stale copy is also used when the server cannot be reached.
+    fn fetch(&self, template: &str, key: &TileKey) -> Result<Vec<u8>, String> {
+        let path = entry_path(template, key);
+        let cached = self.disk.as_ref().and_then(|d| d.get(&path));
+        match cached {
+            Some(c) if c.is_fresh() || self.offline => return Ok(c.bytes),
+            None if self.offline => return Err("not in the tile cache (offline)".into()),
+            _ => {}
+        }
+        let url = format_osm_tile_url(template, key);
+        let mut request = self.agent.get(&url);
+        if let Some(c) = &cached {
+            if let Some(etag) = &c.validity.etag {
+                request = request.set("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &c.validity.last_modified {
+                request = request.set("If-Modified-Since", last_modified);
+            }
+        }
+        let response = match (request.call(), cached) {
+            (Ok(response), Some(c)) if response.status() == 304 => {
+                let mut validity = validity_of(&response);
+                validity.etag = validity.etag.or(c.validity.etag);
+                validity.last_modified = validity.last_modified.or(c.validity.last_modified);
+                self.store(|d| d.refresh(&path, &validity));
+                return Ok(c.bytes);
+            }
+            (Ok(response), _) => response,
+            (Err(e), Some(c)) => {
+                tracing::debug!("tile {}: {e}; using the stale cached copy", key.to_path());
+                return Ok(c.bytes);
+            }
+            (Err(e), None) => return Err(e.to_string()),
+        };
+        let validity = validity_of(&response);
+        // Error pages sent with 200 must not be cached as tiles.
+        let content_type = response.content_type().to_owned();
+        let is_image = content_type.starts_with("image/");
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("reading {url}: {e}"))?;
+        let tile = CachedTile {
+            bytes,
+            content_type,
+            validity,
+        };
+        if is_image {
+            self.store(|d| d.put(&path, &tile));
+        }
+        Ok(tile.bytes)
+    }
+
+    /// Write to the disk cache, if any; a failed write only costs a later download.
+    fn store(&self, write: impl FnOnce(&TileDiskCache) -> anyhow::Result<()>) {
+        if let Some(disk) = &self.disk {
+            if let Err(e) = write(disk) {
+                tracing::warn!("tile cache: {e:#}");
+            }
+        }
+    }
+}
+
+pub enum TileSource {
+    Http {
+        template: String,
+        http: HttpTiles,
+    },
+    /// Path template with `{z}`, `{x}`, `{y}`.
+    Directory {
+        template: String,
+    },
+    MbTiles {
+        path: String,
+        conn: Mutex<Connection>,
+    },
+}
+
+impl TileSource {
+    /// The source `template` names; see the module docs.
+    pub fn open(template: &str, http: &HttpTiles) -> anyhow::Result<Self> {
+        if let Some(dir) = template.strip_prefix("file://") {
+            let template = if dir.contains("{z}") {
+                dir.to_owned()
+            } else {
+                format!("{}/{{z}}/{{x}}/{{y}}.png", dir.trim_end_matches('/'))
+            };
+            return Ok(Self::Directory { template });
+        }
+        if let Some(path) = template.strip_prefix("mbtiles://") {
+            return open_mbtiles(Path::new(path)).map(|conn| Self::MbTiles {
+                path: path.to_owned(),
+                conn: Mutex::new(conn),
+            });
+        }
+        if template.is_empty() {
+            return Ok(Self::Http {
+                template: DEFAULT_OSM_TILE_URL_TEMPLATE.into(),
+                http: http.clone(),
+            });
+        }
+        if !(template.starts_with("http://") || template.starts_with("https://")) {
+            anyhow::bail!("tile URL {template:?} is not http(s)://, file:// or mbtiles://");
+        }
+        Ok(Self::Http {
+            template: template.to_owned(),
+            http: http.clone(),
+        })
+    }
+
+    /// One tile's image file. Blocking; runs on the IO task pool.
+    pub fn fetch(&self, key: &TileKey) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Http { template, http } => http.fetch(template, key),
+            Self::Directory { template } => {
+                let path = format_osm_tile_url(template, key);
+                std::fs::read(&path).map_err(|e| format!("{path}: {e}"))
+            }
+            Self::MbTiles { path, conn } => {
+                let conn = conn.lock().map_err(|_| "MBTiles connection poisoned")?;
+                // MBTiles rows count from the south (TMS), slippy-map rows from the north.
+                let row = (1i64 << key.z) - 1 - key.y;
+                conn.query_row(
+                    "SELECT tile_data FROM tiles
+                     WHERE zoom_level = ?1 AND tile_column = ?2 AND tile_row = ?3",
+                    params![key.z, key.x, row],
+                    |r| r.get(0),
+                )
+                .optional()
+                .map_err(|e| format!("{path}: {e}"))?
+                .ok_or_else(|| format!("not in {path}"))
+            }
+        }
+    }
+
+    /// Whether a failed tile may succeed later. Local sources and offline HTTP will not change
+    /// while we run, so their failures are final.
+    pub fn is_worth_retrying(&self) -> bool {
+        matches!(self, Self::Http { http, .. } if !http.offline)
+    }
+}
+
+/// Build tile URL from template (`{z}`, `{x}`, `{y}`) (ADR-004 / ADR-014).
+#[must_use]
+pub fn format_osm_tile_url(template: &str, key: &TileKey) -> String {
+    template
+        .replace("{z}", &key.z.to_string())
+        .replace("{x}", &key.x.to_string())
+        .replace("{y}", &key.y.to_string())
+}
+
+/// Open an MBTiles file read-only and check that it holds raster tiles.
+fn open_mbtiles(path: &Path) -> anyhow::Result<Connection> {
+    let conn = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+        .with_context(|| format!("open MBTiles {path:?}"))?;
+    let format: Option<String> = conn
+        .query_row(
+            "SELECT value FROM metadata WHERE name = 'format'",
+            [],
+            |r| r.get(0),
+        )
+        .optional()
+        .with_context(|| format!("{path:?} is not an MBTiles file"))?;
+    if format.as_deref() == Some("pbf") {
+        anyhow::bail!("{path:?} holds vector tiles; only raster MBTiles are supported");
+    }
+    Ok(conn)
+}
+
+fn validity_of(response: &ureq::Response) -> TileValidity {
+    TileValidity::from_headers(
+        response.header("ETag"),
+        response.header("Last-Modified"),
+        response.header("Cache-Control"),
+    )
+}
@@ -0,0 +1,8 @@
+-- Accounts (ADR-009 login): one row per Ed25519 public key; sessions are bound to `id`.
+
+CREATE TABLE IF NOT EXISTS accounts (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    public_key BLOB NOT NULL UNIQUE,
+    created_at TEXT NOT NULL DEFAULT (datetime('now')),
+    last_login_at TEXT
+);
@@ -0,0 +1,11 @@
+-- Region roles (ADR-016): accounts without a row are visitors of that region.
+
+CREATE TABLE IF NOT EXISTS region_roles (
+    region_id INTEGER NOT NULL,
+    user_id INTEGER NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('manager', 'owner')),
+    granted_at TEXT NOT NULL DEFAULT (datetime('now')),
+    PRIMARY KEY (region_id, user_id),
+    FOREIGN KEY (region_id) REFERENCES regions(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES accounts(id) ON DELETE CASCADE
+);
@@ -0,0 +1,18 @@
+-- Prim edit history (ADR-017): one row per applied prim mutation, never updated. States are JSON
+-- objects of the prim's columns; `before_state` is NULL for a create, `after_state` for a delete.
+-- `user_id` NULL means the sim itself (e.g. `--rollback`).
+
+CREATE TABLE IF NOT EXISTS prim_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    prim_id INTEGER NOT NULL,
+    region_id INTEGER NOT NULL,
+    user_id INTEGER,
+    op TEXT NOT NULL CHECK (op IN ('create', 'update', 'delete')),
+    before_state TEXT,
+    after_state TEXT,
+    at TEXT NOT NULL DEFAULT (strftime('%Y-%m-%dT%H:%M:%fZ', 'now')),
+    FOREIGN KEY (region_id) REFERENCES regions(id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES accounts(id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_prim_history_region_at ON prim_history(region_id, at);
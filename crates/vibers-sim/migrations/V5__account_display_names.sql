@@ -0,0 +1,3 @@
+-- Display names (ADR-019): chosen by the client after login; NULL until an account sets one.
+
+ALTER TABLE accounts ADD COLUMN display_name TEXT;
@@ -0,0 +1,21 @@
+//! Login challenge (ADR-009, `vibe_core::auth`): fresh nonces and Ed25519 signature checks.
+
+use ring::rand::{SecureRandom, SystemRandom};
+use ring::signature::{UnparsedPublicKey, ED25519};
+use vibe_core::auth::{challenge_payload, NONCE_LEN, PUBLIC_KEY_LEN};
+
+pub fn new_nonce() -> anyhow::Result<Vec<u8>> {
+    let mut nonce = vec![0; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow::anyhow!("no system randomness for login nonce"))?;
+    Ok(nonce)
+}
+
+/// Whether `signature` proves ownership of `public_key` for this `nonce`.
+pub fn verify(public_key: &[u8], nonce: &[u8], signature: &[u8]) -> bool {
+    public_key.len() == PUBLIC_KEY_LEN
+        && UnparsedPublicKey::new(&ED25519, public_key)
+            .verify(&challenge_payload(nonce, public_key), signature)
+            .is_ok()
+}
@@ -1,6 +1,10 @@
 //! CLI overrides (ADR-014).
 
 use clap::Parser;
+use std::str::FromStr;
+use vibe_core::RegionRole;
+
+use crate::permissions::parse_role;
 
 #[derive(Parser, Debug)]
 #[command(name = "vibers-sim", about = "Headless vibers simulation server")]
@@ -15,7 +19,89 @@ pub struct SimCli {
     pub aoi_radius: Option<f32>,
     #[arg(
         long,
-        help = "OSM tile URL template with {z}/{x}/{y} (sent to clients at handshake)"
+        help = "Tile source sent to clients at handshake: URL template with {z}/{x}/{y}, \
+                file://DIR or mbtiles://FILE (paths on the client)"
     )]
     pub osm_tile_url_template: Option<String>,
+    #[arg(long, help = "Seconds without any client frame before the session is dropped")]
+    pub idle_timeout_secs: Option<f32>,
+    #[arg(long, help = "Seconds a client may lag behind on updates before it is dropped")]
+    pub slow_consumer_timeout_secs: Option<f32>,
+    #[arg(long, help = "Register unknown public keys at login (true/false)")]
+    pub open_registration: Option<bool>,
+    #[arg(
+        long,
+        help = "Serve map tiles to clients over HTTP on this address, e.g. 0.0.0.0:4748 (ADR-021)"
+    )]
+    pub tile_proxy_listen: Option<String>,
+    #[arg(long, help = "Tile proxy cache directory")]
+    pub tile_cache_dir: Option<String>,
+    #[arg(long, help = "Tile proxy cache size limit (MiB)")]
+    pub tile_cache_mb: Option<u64>,
+    #[arg(long, help = "Most tile proxy requests per second to the upstream server")]
+    pub tile_upstream_rps: Option<f32>,
+    #[arg(
+        long = "grant",
+        value_name = "REGION:USER:ROLE",
+        help = "Store a region role at startup (role: owner, manager or visitor); repeatable"
+    )]
+    pub grants: Vec<RoleGrant>,
+    #[arg(
+        long = "rollback",
+        value_name = "REGION:TIME",
+        help = "Undo a region's prim edits made after TIME (UTC, e.g. 2026-10-17T12:00) at startup"
+    )]
+    pub rollbacks: Vec<Rollback>,
+}
+
+/// `--grant`: bootstraps the first owner of a region, who can then grant roles in-world (ADR-016).
+#[derive(Debug, Clone, Copy)]
+pub struct RoleGrant {
+    pub region_id: i64,
+    pub user_id: i64,
+    pub role: RegionRole,
+}
+
+impl FromStr for RoleGrant {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(':');
+        let (Some(region), Some(user), Some(role), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(format!("expected REGION:USER:ROLE, got {s:?}"));
+        };
+        Ok(Self {
+            region_id: region
+                .parse()
+                .map_err(|_| format!("bad region id {region:?}"))?,
+            user_id: user.parse().map_err(|_| format!("bad user id {user:?}"))?,
+            role: parse_role(role).ok_or_else(|| format!("unknown role {role:?}"))?,
+        })
+    }
+}
+
+/// `--rollback`: undo a region's prim history after `time` before the world loads (ADR-017).
+#[derive(Debug, Clone)]
+pub struct Rollback {
+    pub region_id: i64,
+    /// Any SQLite date/time string; checked when it is applied.
+    pub time: String,
+}
+
+impl FromStr for Rollback {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some((region, time)) = s.split_once(':') else {
+            return Err(format!("expected REGION:TIME, got {s:?}"));
+        };
+        Ok(Self {
+            region_id: region
+                .parse()
+                .map_err(|_| format!("bad region id {region:?}"))?,
+            time: time.to_string(),
+        })
+    }
 }
@@ -1,6 +1,7 @@
 use figment::providers::{Env, Format, Serialized, Toml};
 use figment::Figment;
 use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
 use std::path::Path;
 
 /// Server configuration (ADR-014).
@@ -17,6 +18,28 @@ pub struct SimConfig {
     /// Placeholders `{z}`, `{x}`, `{y}` for client tile fetch (ADR-004 / ADR-014).
     #[serde(default = "default_osm_tile_url_template")]
     pub osm_tile_url_template: String,
+    /// Drop a session (and its avatar) after this long without any inbound frame (ADR-008).
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: f32,
+    /// Evict a session whose outbound queue has stayed behind the tick for this long (ADR-008).
+    #[serde(default = "default_slow_consumer_timeout_secs")]
+    pub slow_consumer_timeout_secs: f32,
+    /// Create an account for any unknown public key at login; otherwise only known keys get in.
+    #[serde(default = "default_open_registration")]
+    pub open_registration: bool,
+    /// Serve map tiles over HTTP on this address, fetched from `osm_tile_url_template` and cached,
+    /// and name it in the handshake instead (ADR-021). Empty: clients fetch tiles themselves.
+    #[serde(default)]
+    pub tile_proxy_listen: String,
+    /// Tile proxy cache directory.
+    #[serde(default = "default_tile_cache_dir")]
+    pub tile_cache_dir: String,
+    /// Tile proxy cache size limit (MiB); least recently used tiles are evicted beyond it.
+    #[serde(default = "default_tile_cache_mb")]
+    pub tile_cache_mb: u64,
+    /// Most requests per second the tile proxy sends upstream.
+    #[serde(default = "default_tile_upstream_rps")]
+    pub tile_upstream_rps: f32,
 }
 
 fn default_listen() -> String {
@@ -39,6 +62,30 @@ fn default_osm_tile_url_template() -> String {
     "https://tile.openstreetmap.org/{z}/{x}/{y}.png".into()
 }
 
+fn default_idle_timeout_secs() -> f32 {
+    15.0
+}
+
+fn default_slow_consumer_timeout_secs() -> f32 {
+    5.0
+}
+
+fn default_open_registration() -> bool {
+    true
+}
+
+fn default_tile_cache_dir() -> String {
+    "data/tile-proxy".into()
+}
+
+fn default_tile_cache_mb() -> u64 {
+    1024
+}
+
+fn default_tile_upstream_rps() -> f32 {
+    2.0
+}
+
 impl Default for SimConfig {
     fn default() -> Self {
         Self {
@@ -47,6 +94,13 @@ impl Default for SimConfig {
             tick_hz: default_tick_hz(),
             aoi_radius: default_aoi(),
             osm_tile_url_template: default_osm_tile_url_template(),
+            idle_timeout_secs: default_idle_timeout_secs(),
+            slow_consumer_timeout_secs: default_slow_consumer_timeout_secs(),
+            open_registration: default_open_registration(),
+            tile_proxy_listen: String::new(),
+            tile_cache_dir: default_tile_cache_dir(),
+            tile_cache_mb: default_tile_cache_mb(),
+            tile_upstream_rps: default_tile_upstream_rps(),
         }
     }
 }
@@ -79,5 +133,44 @@ impl SimConfig {
         if let Some(ref v) = cli.osm_tile_url_template {
             self.osm_tile_url_template.clone_from(v);
         }
+        if let Some(v) = cli.idle_timeout_secs {
+            self.idle_timeout_secs = v;
+        }
+        if let Some(v) = cli.slow_consumer_timeout_secs {
+            self.slow_consumer_timeout_secs = v;
+        }
+        if let Some(v) = cli.open_registration {
+            self.open_registration = v;
+        }
+        if let Some(ref v) = cli.tile_proxy_listen {
+            self.tile_proxy_listen.clone_from(v);
+        }
+        if let Some(ref v) = cli.tile_cache_dir {
+            self.tile_cache_dir.clone_from(v);
+        }
+        if let Some(v) = cli.tile_cache_mb {
+            self.tile_cache_mb = v;
+        }
+        if let Some(v) = cli.tile_upstream_rps {
+            self.tile_upstream_rps = v;
+        }
+    }
+
+    /// Tile template for the handshake of a session that reached us at `local`: the tile proxy on
+    /// that address if it runs, else `osm_tile_url_template`.
+    pub fn handshake_tile_template(&self, local: Option<IpAddr>) -> String {
+        let port = self
+            .tile_proxy_listen
+            .rsplit_once(':')
+            .map(|(_, port)| port);
+        match (local, port) {
+            (Some(IpAddr::V4(ip)), Some(port)) => {
+                format!("http://{ip}:{port}/{{z}}/{{x}}/{{y}}.png")
+            }
+            (Some(IpAddr::V6(ip)), Some(port)) => {
+                format!("http://[{ip}]:{port}/{{z}}/{{x}}/{{y}}.png")
+            }
+            _ => self.osm_tile_url_template.clone(),
+        }
     }
 }
@@ -1,8 +1,13 @@
 use anyhow::Context;
 use glam::Vec3;
-use rusqlite::Connection;
+use rusqlite::{Connection, OptionalExtension};
+use std::future::Future;
+use tokio::sync::oneshot;
 use vibe_core::world::{lat_lng_to_tile, REGION_ZOOM_LEVEL};
-use vibe_core::{PrimDto, RegionDto};
+use vibe_core::{PrimDto, RegionDto, RegionRole};
+
+use crate::permissions::{parse_role, role_name};
+use crate::state::PrimChange;
 
 mod embedded {
     use refinery::embed_migrations;
@@ -64,25 +69,470 @@ pub fn load_world(conn: &Connection) -> anyhow::Result<(Vec<RegionDto>, Vec<Prim
         })?
         .collect::<Result<Vec<_>, _>>()?;
 
-    let mut stmt = conn.prepare(
-        "SELECT id, region_id, name, shape, position_x, position_y, position_z,
-                rotation_x, rotation_y, rotation_z, scale_x, scale_y, scale_z,
-                color_r, color_g, color_b FROM prims ORDER BY id",
-    )?;
+    let mut stmt = conn.prepare(&format!("SELECT {PRIM_COLUMNS} FROM prims ORDER BY id"))?;
     let prims = stmt
-        .query_map([], |row| {
-            Ok(PrimDto {
-                id: row.get(0)?,
-                region_id: row.get(1)?,
+        .query_map([], prim_from_row)?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok((regions, prims))
+}
+
+/// Columns [`prim_from_row`] reads, in order.
+const PRIM_COLUMNS: &str = "id, region_id, name, shape, position_x, position_y, position_z,
+    rotation_x, rotation_y, rotation_z, scale_x, scale_y, scale_z, color_r, color_g, color_b";
+
+fn prim_from_row(row: &rusqlite::Row) -> rusqlite::Result<PrimDto> {
+    Ok(PrimDto {
+        id: row.get(0)?,
+        region_id: row.get(1)?,
+        name: row.get(2)?,
+        shape: row.get(3)?,
+        position: Vec3::new(row.get(4)?, row.get(5)?, row.get(6)?),
+        rotation: Vec3::new(row.get(7)?, row.get(8)?, row.get(9)?),
+        scale: Vec3::new(row.get(10)?, row.get(11)?, row.get(12)?),
+        color: [row.get(13)?, row.get(14)?, row.get(15)?],
+    })
+}
+
+/// The stored state of each prim in `ids`, as the changes that bring the world to it.
+pub fn current_prims(conn: &Connection, ids: &[i64]) -> anyhow::Result<Vec<PrimChange>> {
+    let mut stmt = conn.prepare(&format!("SELECT {PRIM_COLUMNS} FROM prims WHERE id = ?1"))?;
+    let mut changes = Vec::with_capacity(ids.len());
+    for &id in ids {
+        let prim = stmt.query_row([id], prim_from_row).optional()?;
+        changes.push(prim.map_or(PrimChange::Remove(id), PrimChange::Upsert));
+    }
+    Ok(changes)
+}
+
+/// Id for the next prim the sim creates: past every id `prims` has ever handed out, so a deleted
+/// prim's id is never reused.
+pub fn next_prim_id(conn: &Connection) -> anyhow::Result<i64> {
+    let last: i64 = conn.query_row(
+        "SELECT MAX(COALESCE((SELECT MAX(id) FROM prims), 0),
+                    COALESCE((SELECT seq FROM sqlite_sequence WHERE name = 'prims'), 0))",
+        [],
+        |row| row.get(0),
+    )?;
+    Ok(last + 1)
+}
+
+/// A `prims` row as the JSON object `prim_history` keeps (ADR-017).
+const PRIM_STATE_JSON: &str = "json_object('region_id', region_id, 'name', name, 'shape', shape,
+    'position', json_array(position_x, position_y, position_z),
+    'rotation', json_array(rotation_x, rotation_y, rotation_z),
+    'scale', json_array(scale_x, scale_y, scale_z),
+    'color', json_array(color_r, color_g, color_b))";
+
+/// Write one prim edit the sim has applied (ADR-011 write path) and record it in `prim_history`
+/// with its author (ADR-017). `user_id` `None` is the sim itself.
+pub fn store_prim_change(
+    conn: &mut Connection,
+    change: &PrimChange,
+    user_id: Option<i64>,
+) -> anyhow::Result<()> {
+    let tx = conn.transaction()?;
+    write_prim_change(&tx, change, user_id)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// `prims` row and history entry for one change. `false`: the row was already like that, and
+/// nothing is recorded.
+fn write_prim_change(
+    conn: &Connection,
+    change: &PrimChange,
+    user_id: Option<i64>,
+) -> anyhow::Result<bool> {
+    let id = change.prim_id();
+    let before = prim_state(conn, id)?;
+    match change {
+        PrimChange::Upsert(p) => upsert_prim(conn, p)?,
+        PrimChange::Remove(id) => {
+            conn.execute("DELETE FROM prims WHERE id = ?1", [id])?;
+        }
+    }
+    let after = prim_state(conn, id)?;
+    let (region_id, op) = match (&before, &after) {
+        (None, Some((region_id, _))) => (*region_id, "create"),
+        (Some((region_id, _)), None) => (*region_id, "delete"),
+        (Some(_), Some((region_id, _))) if before != after => (*region_id, "update"),
+        _ => return Ok(false),
+    };
+    conn.execute(
+        "INSERT INTO prim_history (prim_id, region_id, user_id, op, before_state, after_state)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        rusqlite::params![
+            id,
+            region_id,
+            user_id,
+            op,
+            before.map(|(_, json)| json),
+            after.map(|(_, json)| json),
+        ],
+    )?;
+    Ok(true)
+}
+
+/// `(region_id, state JSON)` of a stored prim.
+fn prim_state(conn: &Connection, id: i64) -> anyhow::Result<Option<(i64, String)>> {
+    let sql = format!("SELECT region_id, {PRIM_STATE_JSON} FROM prims WHERE id = ?1");
+    Ok(conn
+        .query_row(&sql, [id], |row| Ok((row.get(0)?, row.get(1)?)))
+        .optional()?)
+}
+
+fn upsert_prim(conn: &Connection, p: &PrimDto) -> anyhow::Result<()> {
+    conn.execute(
+        "INSERT INTO prims (id, region_id, name, shape, position_x, position_y, position_z,
+            rotation_x, rotation_y, rotation_z, scale_x, scale_y, scale_z,
+            color_r, color_g, color_b)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16)
+         ON CONFLICT (id) DO UPDATE SET
+            name = excluded.name, shape = excluded.shape,
+            position_x = excluded.position_x, position_y = excluded.position_y,
+            position_z = excluded.position_z, rotation_x = excluded.rotation_x,
+            rotation_y = excluded.rotation_y, rotation_z = excluded.rotation_z,
+            scale_x = excluded.scale_x, scale_y = excluded.scale_y,
+            scale_z = excluded.scale_z, color_r = excluded.color_r,
+            color_g = excluded.color_g, color_b = excluded.color_b,
+            updated_at = datetime('now')",
+        rusqlite::params![
+            p.id,
+            p.region_id,
+            p.name,
+            p.shape,
+            p.position.x,
+            p.position.y,
+            p.position.z,
+            p.rotation.x,
+            p.rotation.y,
+            p.rotation.z,
+            p.scale.x,
+            p.scale.y,
+            p.scale.z,
+            p.color[0],
+            p.color[1],
+            p.color[2],
+        ],
+    )?;
+    Ok(())
+}
+
+/// `to` (any SQLite date/time string, UTC) in the format of `prim_history.at`; `None` if SQLite
+/// cannot read it.
+pub fn history_time(conn: &Connection, to: &str) -> anyhow::Result<Option<String>> {
+    Ok(
+        conn.query_row("SELECT strftime('%Y-%m-%dT%H:%M:%fZ', ?1)", [to], |row| {
+            row.get(0)
+        })?,
+    )
+}
+
+/// Rollback (ADR-017): put every prim of the region back to how it was at `to` (any SQLite time
+/// string, UTC) by undoing the history recorded after it. The restores are recorded too, with
+/// `user_id` as their author (`None`: the sim itself), so a rollback can itself be rolled back.
+/// Returns the changes made, for the world to apply.
+pub fn rollback_region(
+    conn: &mut Connection,
+    region_id: i64,
+    to: &str,
+    user_id: Option<i64>,
+) -> anyhow::Result<Vec<PrimChange>> {
+    let tx = conn.transaction()?;
+    let at = history_time(&tx, to)?
+        .with_context(|| format!("{to:?} is not a date/time SQLite understands"))?;
+    // Each prim's state before its first change after `at` is its state at `at`.
+    let targets = {
+        let mut stmt = tx.prepare(
+            "SELECT prim_id,
+                    json_extract(before_state, '$.region_id'), json_extract(before_state, '$.name'),
+                    json_extract(before_state, '$.shape'),
+                    json_extract(before_state, '$.position[0]'),
+                    json_extract(before_state, '$.position[1]'),
+                    json_extract(before_state, '$.position[2]'),
+                    json_extract(before_state, '$.rotation[0]'),
+                    json_extract(before_state, '$.rotation[1]'),
+                    json_extract(before_state, '$.rotation[2]'),
+                    json_extract(before_state, '$.scale[0]'),
+                    json_extract(before_state, '$.scale[1]'),
+                    json_extract(before_state, '$.scale[2]'),
+                    json_extract(before_state, '$.color[0]'),
+                    json_extract(before_state, '$.color[1]'),
+                    json_extract(before_state, '$.color[2]')
+             FROM prim_history
+             WHERE id IN (SELECT MIN(id) FROM prim_history
+                          WHERE region_id = ?1 AND at > ?2 GROUP BY prim_id)
+             ORDER BY prim_id",
+        )?;
+        let rows = stmt.query_map(rusqlite::params![region_id, at], |row| {
+            let id: i64 = row.get(0)?;
+            let Some(region_id) = row.get::<_, Option<i64>>(1)? else {
+                return Ok(PrimChange::Remove(id));
+            };
+            Ok(PrimChange::Upsert(PrimDto {
+                id,
+                region_id,
                 name: row.get(2)?,
                 shape: row.get(3)?,
                 position: Vec3::new(row.get(4)?, row.get(5)?, row.get(6)?),
                 rotation: Vec3::new(row.get(7)?, row.get(8)?, row.get(9)?),
                 scale: Vec3::new(row.get(10)?, row.get(11)?, row.get(12)?),
                 color: [row.get(13)?, row.get(14)?, row.get(15)?],
-            })
+            }))
+        })?;
+        rows.collect::<Result<Vec<_>, _>>()?
+    };
+    let mut changed = Vec::new();
+    for change in targets {
+        if write_prim_change(&tx, &change, user_id)? {
+            changed.push(change);
+        }
+    }
+    tx.commit()?;
+    Ok(changed)
+}
+
+/// Every `(region_id, user_id, role)` grant (ADR-016).
+pub fn load_region_roles(conn: &Connection) -> anyhow::Result<Vec<(i64, i64, RegionRole)>> {
+    let mut stmt = conn.prepare("SELECT region_id, user_id, role FROM region_roles")?;
+    let rows = stmt
+        .query_map([], |row| {
+            Ok((row.get(0)?, row.get(1)?, row.get::<_, String>(2)?))
         })?
         .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(region_id, user_id, name)| {
+            let role = parse_role(&name);
+            if role.is_none() {
+                tracing::warn!(region_id, user_id, %name, "ignoring unknown region role");
+            }
+            Some((region_id, user_id, role?))
+        })
+        .collect())
+}
 
-    Ok((regions, prims))
+/// Store `role` for the account in the region (`Visitor` deletes the grant). `false` when the
+/// region or the account does not exist.
+pub fn set_region_role(
+    conn: &Connection,
+    region_id: i64,
+    user_id: i64,
+    role: RegionRole,
+) -> anyhow::Result<bool> {
+    let exists: bool = conn.query_row(
+        "SELECT EXISTS (SELECT 1 FROM regions WHERE id = ?1)
+            AND EXISTS (SELECT 1 FROM accounts WHERE id = ?2)",
+        [region_id, user_id],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        return Ok(false);
+    }
+    if role == RegionRole::Visitor {
+        conn.execute(
+            "DELETE FROM region_roles WHERE region_id = ?1 AND user_id = ?2",
+            [region_id, user_id],
+        )?;
+    } else {
+        conn.execute(
+            "INSERT INTO region_roles (region_id, user_id, role) VALUES (?1, ?2, ?3)
+             ON CONFLICT (region_id, user_id)
+             DO UPDATE SET role = excluded.role, granted_at = datetime('now')",
+            rusqlite::params![region_id, user_id, role_name(role)],
+        )?;
+    }
+    Ok(true)
+}
+
+type Job = Box<dyn FnOnce(&mut Connection) + Send>;
+
+/// ADR-013: the sim's one database connection, owned by a worker thread. Async code queues jobs
+/// on it, so SQLite never blocks the runtime and every write goes through the same connection.
+#[derive(Clone)]
+pub struct Db {
+    jobs: std::sync::mpsc::Sender<Job>,
+}
+
+impl Db {
+    pub fn spawn(mut conn: Connection) -> anyhow::Result<Self> {
+        let (jobs, rx) = std::sync::mpsc::channel::<Job>();
+        std::thread::Builder::new()
+            .name("sqlite".into())
+            .spawn(move || {
+                while let Ok(job) = rx.recv() {
+                    job(&mut conn);
+                }
+            })
+            .context("spawn database worker")?;
+        Ok(Self { jobs })
+    }
+
+    /// Run `f` on the worker's connection and wait for its result.
+    pub async fn call<R, F>(&self, f: F) -> anyhow::Result<R>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Connection) -> anyhow::Result<R> + Send + 'static,
+    {
+        self.submit(f).await
+    }
+
+    /// Queue `f` right away and return a future for its result. Jobs run in submission order, so
+    /// changes submitted under the world lock reach SQLite in the order they were applied.
+    pub fn submit<R, F>(&self, f: F) -> impl Future<Output = anyhow::Result<R>>
+    where
+        R: Send + 'static,
+        F: FnOnce(&mut Connection) -> anyhow::Result<R> + Send + 'static,
+    {
+        let (tx, rx) = oneshot::channel();
+        let queued = self.jobs.send(Box::new(move |conn| {
+            let _ = tx.send(f(conn));
+        }));
+        async move {
+            queued.map_err(|_| anyhow::anyhow!("database worker stopped"))?;
+            rx.await
+                .map_err(|_| anyhow::anyhow!("database worker stopped"))?
+        }
+    }
+}
+
+/// A logged-in account.
+pub struct Account {
+    pub id: i64,
+    /// `None` until the account sets one (ADR-019).
+    pub display_name: Option<String>,
+}
+
+/// Account for `public_key`, creating it when `register` allows it. `None` = unknown key on a sim
+/// that does not register new accounts.
+pub fn login_account(
+    conn: &Connection,
+    public_key: &[u8],
+    register: bool,
+) -> anyhow::Result<Option<Account>> {
+    let existing = conn
+        .query_row(
+            "SELECT id, display_name FROM accounts WHERE public_key = ?1",
+            [public_key],
+            |row| {
+                Ok(Account {
+                    id: row.get(0)?,
+                    display_name: row.get(1)?,
+                })
+            },
+        )
+        .optional()?;
+    let account = match existing {
+        Some(account) => account,
+        None if register => {
+            conn.execute(
+                "INSERT INTO accounts (public_key) VALUES (?1)",
+                [public_key],
+            )?;
+            let id = conn.last_insert_rowid();
+            tracing::info!(user_id = id, "registered account");
+            Account {
+                id,
+                display_name: None,
+            }
+        }
+        None => return Ok(None),
+    };
+    conn.execute(
+        "UPDATE accounts SET last_login_at = datetime('now') WHERE id = ?1",
+        [account.id],
+    )?;
+    Ok(Some(account))
+}
+
+/// Store the account's display name (already validated).
+pub fn set_display_name(conn: &Connection, user_id: i64, name: &str) -> anyhow::Result<()> {
+    conn.execute(
+        "UPDATE accounts SET display_name = ?2 WHERE id = ?1",
+        rusqlite::params![user_id, name],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prim(id: i64, name: &str) -> PrimDto {
+        PrimDto {
+            id,
+            region_id: 1,
+            name: name.into(),
+            shape: "box".into(),
+            position: Vec3::new(1.5, 0.5, -2.25),
+            rotation: Vec3::new(0.0, 0.5, 0.0),
+            scale: Vec3::new(1.0, 2.0, 0.5),
+            color: [0.25, 0.5, 1.0],
+        }
+    }
+
+    /// Names of the stored prims, by id.
+    fn names(conn: &Connection) -> Vec<(i64, String)> {
+        let (_, prims) = load_world(conn).unwrap();
+        prims.into_iter().map(|p| (p.id, p.name)).collect()
+    }
+
+    /// A history time after everything recorded so far and before anything recorded next.
+    fn checkpoint(conn: &Connection) -> String {
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let now = history_time(conn, "now").unwrap().unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        now
+    }
+
+    #[test]
+    fn rollback_restores_history_and_can_be_rolled_back() {
+        let mut conn = open_and_migrate(":memory:").unwrap();
+        let owner = login_account(&conn, &[7; 32], true).unwrap().unwrap().id;
+        let empty = checkpoint(&conn);
+
+        let (a, b) = (prim(1, "a"), prim(2, "b"));
+        store_prim_change(&mut conn, &PrimChange::Upsert(a.clone()), Some(owner)).unwrap();
+        store_prim_change(&mut conn, &PrimChange::Upsert(b.clone()), Some(owner)).unwrap();
+        let built = checkpoint(&conn);
+
+        let renamed = prim(1, "a2");
+        store_prim_change(&mut conn, &PrimChange::Upsert(renamed), Some(owner)).unwrap();
+        store_prim_change(&mut conn, &PrimChange::Remove(2), Some(owner)).unwrap();
+        // Storing what is already there records nothing.
+        store_prim_change(&mut conn, &PrimChange::Remove(2), Some(owner)).unwrap();
+        let ops: Vec<String> = conn
+            .prepare("SELECT op FROM prim_history ORDER BY id")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(ops, ["create", "create", "update", "delete"]);
+        let edited = checkpoint(&conn);
+
+        let changes = rollback_region(&mut conn, 1, &built, Some(owner)).unwrap();
+        assert_eq!(changes.len(), 2);
+        let (_, prims) = load_world(&conn).unwrap();
+        assert_eq!(prims, [a, b], "rebuilt from before_state JSON");
+        let authors: Vec<Option<i64>> = conn
+            .prepare("SELECT user_id FROM prim_history WHERE id > 4")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(authors, [Some(owner), Some(owner)]);
+        checkpoint(&conn);
+
+        rollback_region(&mut conn, 1, &edited, None).unwrap();
+        assert_eq!(names(&conn), [(1, "a2".to_string())]);
+
+        rollback_region(&mut conn, 1, &empty, None).unwrap();
+        assert!(names(&conn).is_empty());
+        assert!(rollback_region(&mut conn, 1, &empty, None)
+            .unwrap()
+            .is_empty());
+    }
 }
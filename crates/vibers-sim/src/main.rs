@@ -1,15 +1,25 @@
-//! Headless simulation server (ADR-007, ADR-008, ADR-010–014).
+//! Headless simulation server (ADR-007, ADR-008, ADR-010–014, ADR-016–021).
 
+mod auth;
 mod cli;
 mod config;
 mod db;
 mod net;
+mod outbound;
+mod permissions;
 mod state;
+mod stats;
+mod tile_proxy;
 
+use anyhow::Context;
 use clap::Parser;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::net::TcpListener;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{watch, RwLock};
+
+/// How long sessions get to flush their `ShuttingDown` error after Ctrl-C.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -30,37 +40,88 @@ async fn main() -> anyhow::Result<()> {
         tick_hz = config.tick_hz,
         aoi = config.aoi_radius,
         tile_template = %config.osm_tile_url_template,
+        idle_timeout = config.idle_timeout_secs,
+        slow_consumer_timeout = config.slow_consumer_timeout_secs,
+        open_registration = config.open_registration,
+        tile_proxy = %config.tile_proxy_listen,
         "vibers-sim"
     );
 
-    let conn = db::open_and_migrate(&config.database_path)?;
+    let mut conn = db::open_and_migrate(&config.database_path)?;
+    for r in &sim_cli.rollbacks {
+        let changed = db::rollback_region(&mut conn, r.region_id, &r.time, None)
+            .with_context(|| format!("--rollback {}:{}", r.region_id, r.time))?
+            .len();
+        tracing::info!(region_id = r.region_id, to = %r.time, changed, "rolled region back");
+    }
     let (regions, prims) = db::load_world(&conn)?;
-    drop(conn);
+    let next_prim_id = db::next_prim_id(&conn)?;
+    for g in &sim_cli.grants {
+        if !db::set_region_role(&conn, g.region_id, g.user_id, g.role)? {
+            anyhow::bail!(
+                "--grant {}:{}: no such region or account",
+                g.region_id,
+                g.user_id
+            );
+        }
+        tracing::info!(
+            region_id = g.region_id,
+            user_id = g.user_id,
+            role = permissions::role_name(g.role),
+            "granted region role"
+        );
+    }
+    let roles = permissions::RegionRoles::new(db::load_region_roles(&conn)?);
+    let db = db::Db::spawn(conn)?;
 
     let world = Arc::new(RwLock::new(state::SimWorld::new(
         regions,
         prims,
+        next_prim_id,
+        roles,
         config.aoi_radius,
     )));
 
-    let (tx_snap, _) = broadcast::channel::<Vec<u8>>(256);
+    let sessions = net::Sessions::default();
     let world_tick = world.clone();
+    let sessions_tick = sessions.clone();
     let config_tick = config.clone();
-    tokio::spawn(net::tick_loop(world_tick, config_tick, tx_snap.clone()));
+    tokio::spawn(net::tick_loop(world_tick, sessions_tick, config_tick));
+
+    if !config.tile_proxy_listen.is_empty() {
+        let proxy = Arc::new(tile_proxy::TileProxy::open(&config)?);
+        let tile_listener = TcpListener::bind(&config.tile_proxy_listen)
+            .await
+            .with_context(|| format!("tile proxy listen {}", config.tile_proxy_listen))?;
+        tracing::info!("serving tiles on {}", config.tile_proxy_listen);
+        tokio::spawn(proxy.serve(tile_listener));
+    }
 
     let listener = TcpListener::bind(&config.listen).await?;
     tracing::info!("listening on {}", config.listen);
 
+    let (shutdown_tx, _) = watch::channel(false);
     loop {
-        let (stream, addr) = listener.accept().await?;
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = tokio::signal::ctrl_c() => break,
+        };
         tracing::info!(%addr, "accepted");
         let world_c = world.clone();
+        let sessions_c = sessions.clone();
+        let db_c = db.clone();
         let cfg_c = config.clone();
-        let rx = tx_snap.subscribe();
+        let shutdown = shutdown_tx.subscribe();
         tokio::spawn(async move {
-            if let Err(e) = net::handle_connection(stream, world_c, cfg_c, rx).await {
+            let conn = net::handle_connection(stream, world_c, sessions_c, db_c, cfg_c, shutdown);
+            if let Err(e) = conn.await {
                 tracing::warn!(%addr, "client ended: {e:#}");
             }
         });
     }
+
+    tracing::info!("shutting down");
+    let _ = shutdown_tx.send(true);
+    let _ = tokio::time::timeout(SHUTDOWN_GRACE, shutdown_tx.closed()).await;
+    Ok(())
 }
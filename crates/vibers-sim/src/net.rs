@@ -1,27 +1,207 @@
+use crate::auth;
 use crate::config::SimConfig;
+use crate::db::{self, Db};
+use crate::outbound::{Outbound, Pushed, TickFrames};
+use crate::permissions::{self, Refusal};
 use crate::state::SimWorld;
+use crate::stats::{TickStats, TickTiming};
 use bytes::Bytes;
+use futures_util::stream::SplitSink;
 use futures_util::{SinkExt, StreamExt};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tokio::net::TcpStream;
-use tokio::sync::{broadcast, RwLock};
+use tokio::sync::{watch, RwLock};
 use tokio_util::codec::{Framed, LengthDelimitedCodec};
+use vibe_core::appearance::validate_appearance;
+use vibe_core::auth::{GUEST_USER_ID, LOGIN_PROTOCOL_VERSION, PUBLIC_KEY_LEN};
+use vibe_core::profile::{default_display_name, validate_display_name};
 use vibe_core::{
-    decode_app_frame, encode_app_frame, NetMessage, ProtocolError, PROTOCOL_VERSION,
+    decode_app_frame_versioned, encode_app_frame_as, message_kind, message_request_id,
+    negotiate_version, now_micros, Capabilities, ErrorCode, MoveInput, NetMessage, ProtocolError,
+    MIN_PROTOCOL_VERSION, PROTOCOL_VERSION,
 };
 
 const MAX_FRAME: usize = 32 * 1024 * 1024;
-/// ADR-012: simple per-connection rate limits (token-bucket style, fixed interval).
+/// ADR-012: rate limit for pre-v8 intents, which carry no `dt`. Sequenced intents are bounded by
+/// the sim's per-avatar input budget instead.
 const MIN_INTENT_INTERVAL: Duration = Duration::from_millis(50);
-const MIN_OBSERVER_INTERVAL: Duration = Duration::from_millis(100);
+/// Optional features this sim offers; each session gets the intersection with the client's set.
+const SIM_CAPABILITIES: Capabilities = Capabilities::REGION_STREAMING
+    .union(Capabilities::HEARTBEAT)
+    .union(Capabilities::REGION_ADMIN)
+    .union(Capabilities::PRIM_EDITING)
+    .union(Capabilities::CHAT)
+    .union(Capabilities::DISPLAY_NAMES)
+    .union(Capabilities::APPEARANCE);
+
+/// How often the tick loop logs its [`TickStats`] window.
+const TICK_STATS_INTERVAL: Duration = Duration::from_secs(10);
+/// How long the client gets to answer the login challenge.
+const LOGIN_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long a closing session's writer gets to flush its final frames.
+const CLOSE_FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Every connected session, keyed by the avatar id it controls (ADR-011/012).
+pub type Sessions = Arc<RwLock<HashMap<u64, Session>>>;
+
+/// Wire parameters agreed in the hello (ADR-009), the logged-in account and the session's
+/// outbound queue.
+#[derive(Clone)]
+pub struct Session {
+    protocol_version: u16,
+    capabilities: Capabilities,
+    user_id: i64,
+    outbound: Arc<Outbound>,
+}
+
+impl Session {
+    fn encode(&self, msg: &NetMessage) -> Result<Vec<u8>, ProtocolError> {
+        encode_app_frame_as(msg, self.protocol_version)
+    }
+
+    /// Queue a typed error as the session's last frame; the writer closes the connection after it.
+    fn close_with_error(&self, code: ErrorCode, message: String) {
+        tracing::debug!(?code, %message, "sending error");
+        match self.encode(&NetMessage::error(code, 0, message)) {
+            Ok(bytes) => self.outbound.close(Some(Bytes::from(bytes))),
+            Err(e) => {
+                tracing::debug!("error frame encode: {e}");
+                self.outbound.close(None);
+            }
+        }
+    }
+
+    /// Refuse one request; the session stays open.
+    fn reply_error(&self, request_id: u32, code: ErrorCode, message: String) {
+        tracing::debug!(request_id, ?code, %message, "refusing request");
+        match self.encode(&NetMessage::error(code, request_id, message)) {
+            Ok(bytes) => {
+                self.outbound.push_control(Bytes::from(bytes));
+            }
+            Err(e) => tracing::debug!("error frame encode: {e}"),
+        }
+    }
+
+    /// Whether `msg` should reach this client at all, given the negotiated capabilities.
+    fn wants(&self, msg: &NetMessage) -> bool {
+        match msg {
+            NetMessage::RegionEntered { .. } | NetMessage::RegionLeft { .. } => {
+                self.capabilities.contains(Capabilities::REGION_STREAMING)
+            }
+            NetMessage::PrimUpdated { .. } => {
+                self.capabilities.contains(Capabilities::PRIM_EDITING)
+            }
+            NetMessage::ChatMessage { .. } => self.capabilities.contains(Capabilities::CHAT),
+            NetMessage::AvatarProfiles { .. } => {
+                self.capabilities.contains(Capabilities::DISPLAY_NAMES)
+            }
+            NetMessage::AvatarAppearances { .. } => {
+                self.capabilities.contains(Capabilities::APPEARANCE)
+            }
+            _ => true,
+        }
+    }
+}
+
+type Conn = Framed<TcpStream, LengthDelimitedCodec>;
+
+/// Challenge the hello's key and resolve its account (ADR-009 login, protocol v10). Sessions older
+/// than login join as guests without a challenge. The outer error is a dead connection; the inner
+/// one a refusal to report to the client.
+async fn login(
+    framed: &mut Conn,
+    version: u16,
+    public_key: &[u8],
+    db: &Db,
+    config: &SimConfig,
+) -> anyhow::Result<Result<db::Account, Refusal>> {
+    if version < LOGIN_PROTOCOL_VERSION {
+        return Ok(Ok(db::Account {
+            id: GUEST_USER_ID,
+            display_name: None,
+        }));
+    }
+    if public_key.len() != PUBLIC_KEY_LEN {
+        let reason = "hello carries no Ed25519 public key".to_string();
+        return Ok(Err((ErrorCode::AuthFailed, reason)));
+    }
+    let nonce = auth::new_nonce()?;
+    let challenge = NetMessage::AuthChallenge {
+        nonce: nonce.clone(),
+    };
+    framed
+        .send(Bytes::from(encode_app_frame_as(&challenge, version)?))
+        .await?;
+    let Ok(reply) = tokio::time::timeout(LOGIN_TIMEOUT, framed.next()).await else {
+        let reason = format!("no login response within {LOGIN_TIMEOUT:?}");
+        return Ok(Err((ErrorCode::AuthFailed, reason)));
+    };
+    let reply = reply
+        .transpose()?
+        .ok_or_else(|| anyhow::anyhow!("closed during login"))?;
+    let signature = match decode_app_frame_versioned(&reply) {
+        Ok((v, NetMessage::AuthResponse { signature })) if v == version => signature,
+        Ok((_, msg)) => {
+            let reason = format!("expected AuthResponse, got {:?}", message_kind(&msg));
+            return Ok(Err((ErrorCode::BadRequest, reason)));
+        }
+        Err(e) => return Ok(Err((ErrorCode::BadRequest, e.to_string()))),
+    };
+    if !auth::verify(public_key, &nonce, &signature) {
+        let reason = "signature does not match the public key".to_string();
+        return Ok(Err((ErrorCode::AuthFailed, reason)));
+    }
+    let key = public_key.to_vec();
+    let register = config.open_registration;
+    match db
+        .call(move |conn| db::login_account(conn, &key, register))
+        .await
+    {
+        Ok(Some(account)) => Ok(Ok(account)),
+        Ok(None) => Ok(Err((ErrorCode::AuthFailed, "unknown account".into()))),
+        Err(e) => {
+            tracing::error!("account lookup: {e:#}");
+            Ok(Err((ErrorCode::Internal, "account lookup failed".into())))
+        }
+    }
+}
+
+/// Writer half of a session: drains its outbound queue until it is closed and empty.
+async fn write_outbound(
+    mut sink: SplitSink<Conn, Bytes>,
+    outbound: Arc<Outbound>,
+) -> std::io::Result<()> {
+    while let Some(batch) = outbound.next_batch().await {
+        for frame in batch {
+            sink.feed(frame).await?;
+        }
+        sink.flush().await?;
+    }
+    sink.close().await
+}
+
+/// Best-effort typed error during the hello, before the session has an outbound queue.
+async fn send_error(framed: &mut Conn, version: u16, code: ErrorCode, message: String) {
+    tracing::debug!(?code, %message, "sending error");
+    match encode_app_frame_as(&NetMessage::error(code, 0, message), version) {
+        Ok(bytes) => {
+            let _ = framed.send(Bytes::from(bytes)).await;
+        }
+        Err(e) => tracing::debug!("error frame encode: {e}"),
+    }
+}
 
 pub async fn handle_connection(
     stream: TcpStream,
     world: Arc<RwLock<SimWorld>>,
+    sessions: Sessions,
+    db: Db,
     config: Arc<SimConfig>,
-    mut snap_rx: broadcast::Receiver<Vec<u8>>,
+    mut shutdown: watch::Receiver<bool>,
 ) -> anyhow::Result<()> {
+    let local_ip = stream.local_addr().ok().map(|a| a.ip());
     let mut framed = Framed::new(
         stream,
         LengthDelimitedCodec::builder()
@@ -35,54 +215,116 @@ pub async fn handle_connection(
         .await
         .transpose()?
         .ok_or_else(|| anyhow::anyhow!("closed before hello"))?;
-    let msg = decode_app_frame(&first)?;
+    let (hello_version, msg) = match decode_app_frame_versioned(&first) {
+        Ok(decoded) => decoded,
+        Err(e) => {
+            let (version, code) = match e {
+                // Unknown frame version: the oldest one we speak is the best guess at readability.
+                ProtocolError::UnsupportedVersion(_) => {
+                    (MIN_PROTOCOL_VERSION, ErrorCode::VersionMismatch)
+                }
+                _ => (PROTOCOL_VERSION, ErrorCode::BadRequest),
+            };
+            send_error(&mut framed, version, code, e.to_string()).await;
+            return Err(e.into());
+        }
+    };
     let NetMessage::ClientHello {
-        protocol_version,
+        min_protocol_version,
+        max_protocol_version,
+        capabilities,
         client_token,
+        public_key,
     } = msg
     else {
-        return Err(
-            ProtocolError::ExpectedHello(format!("{msg:?}").into()).into(),
-        );
+        let e = ProtocolError::ExpectedHello(format!("{msg:?}").into());
+        send_error(&mut framed, hello_version, ErrorCode::BadRequest, e.to_string()).await;
+        return Err(e.into());
+    };
+    let Some(protocol_version) = negotiate_version(min_protocol_version, max_protocol_version)
+    else {
+        // Answer in the hello's own frame version so the client can read why.
+        send_error(
+            &mut framed,
+            hello_version,
+            ErrorCode::VersionMismatch,
+            format!(
+                "no common protocol version: client {min_protocol_version}..={max_protocol_version}, \
+                 sim {MIN_PROTOCOL_VERSION}..={PROTOCOL_VERSION}"
+            ),
+        )
+        .await;
+        return Err(ProtocolError::UnsupportedVersion(max_protocol_version).into());
+    };
+    // Nothing is spawned for a session until it has proven which account it is.
+    let account = match login(&mut framed, protocol_version, &public_key, &db, &config).await? {
+        Ok(account) => account,
+        Err((code, reason)) => {
+            tracing::info!(token = %client_token, ?code, %reason, "login refused");
+            send_error(&mut framed, protocol_version, code, reason.clone()).await;
+            anyhow::bail!("login refused: {reason}");
+        }
     };
-    if protocol_version != PROTOCOL_VERSION {
-        let err = encode_app_frame(&NetMessage::ServerError {
-            request_id: 0,
-            code: 1,
-            message: format!("version {protocol_version} not supported"),
-        })?;
-        framed.send(Bytes::from(err)).await?;
-        return Err(ProtocolError::UnsupportedVersion(protocol_version).into());
-    }
-    let avatar_id = {
+    let user_id = account.id;
+    let display_name = account
+        .display_name
+        .unwrap_or_else(|| default_display_name(user_id));
+    let session = Session {
+        protocol_version,
+        capabilities: capabilities.intersection(SIM_CAPABILITIES),
+        user_id,
+        outbound: Arc::new(Outbound::new(Duration::from_secs_f32(
+            config.slow_consumer_timeout_secs.max(0.1),
+        ))),
+    };
+    // Spawn, register and snapshot under one lock so no tick's deltas can precede the join
+    // snapshot or be built for a session the tick loop cannot reach.
+    let (avatar_id, join_snapshot) = {
         let mut w = world.write().await;
-        w.spawn_avatar()
+        let id = w.spawn_avatar(user_id, display_name);
+        sessions.write().await.insert(id, session.clone());
+        (id, w.join_snapshot(id))
     };
-    tracing::info!(token = %client_token, avatar_id, "client hello");
-    let ack = encode_app_frame(&NetMessage::ServerHelloAck {
+    tracing::info!(
+        token = %client_token,
+        user_id = session.user_id,
+        avatar_id,
+        protocol_version,
+        capabilities = session.capabilities.0,
+        "client hello"
+    );
+    let ack = session.encode(&NetMessage::ServerHelloAck {
         session_id: uuid::Uuid::new_v4(),
         tick_hz: config.tick_hz,
         your_avatar_id: avatar_id,
-        osm_tile_url_template: config.osm_tile_url_template.clone(),
+        osm_tile_url_template: config.handshake_tile_template(local_ip),
+        protocol_version,
+        capabilities: session.capabilities,
+        user_id,
     })?;
-    if let Err(e) = framed.send(Bytes::from(ack)).await {
-        let mut w = world.write().await;
-        w.remove_avatar(avatar_id);
-        return Err(e.into());
+    let snapshot = session.encode(&join_snapshot)?;
+    // Control frames go out before any tick update the loop may already have queued.
+    for frame in [ack, snapshot] {
+        session.outbound.push_control(Bytes::from(frame));
     }
+    let (sink, mut stream) = framed.split();
+    let mut writer = tokio::spawn(write_outbound(sink, session.outbound.clone()));
+    let mut writer_done = false;
 
     let mut last_intent = Instant::now()
         .checked_sub(MIN_INTENT_INTERVAL)
         .unwrap_or_else(Instant::now);
-    let mut last_observer = Instant::now()
-        .checked_sub(MIN_OBSERVER_INTERVAL)
-        .unwrap_or_else(Instant::now);
+
+    // ADR-008: any inbound frame proves the peer is alive; heartbeat clients ping while idle.
+    let idle_timeout = Duration::from_secs_f32(config.idle_timeout_secs.max(0.1));
+    let idle = tokio::time::sleep(idle_timeout);
+    tokio::pin!(idle);
 
     let mut outcome: anyhow::Result<()> = Ok(());
     loop {
         tokio::select! {
             biased;
-            incoming = framed.next() => {
+            incoming = stream.next() => {
                 match incoming {
                     None => break,
                     Some(Err(e)) => {
@@ -90,89 +332,560 @@ pub async fn handle_connection(
                         break;
                     }
                     Some(Ok(bytes)) => {
-                        let msg = decode_app_frame(&bytes)?;
+                        idle.as_mut().reset(tokio::time::Instant::now() + idle_timeout);
+                        let decoded = decode_app_frame_versioned(&bytes).and_then(|(version, msg)| {
+                            if version == session.protocol_version {
+                                Ok(msg)
+                            } else {
+                                Err(ProtocolError::UnsupportedVersion(version))
+                            }
+                        });
+                        let msg = match decoded {
+                            Ok(msg) => msg,
+                            Err(e) => {
+                                session.close_with_error(ErrorCode::BadRequest, e.to_string());
+                                outcome = Err(e.into());
+                                break;
+                            }
+                        };
+                        // ADR-016: every mutating message is authorized before it is looked at.
+                        if let Some((target, action)) = permissions::mutation(&msg) {
+                            let allowed =
+                                world.read().await.authorize(session.user_id, target, action);
+                            if let Err((code, reason)) = allowed {
+                                tracing::info!(
+                                    avatar_id,
+                                    user_id = session.user_id,
+                                    ?target,
+                                    ?action,
+                                    ?code,
+                                    "denied"
+                                );
+                                session.reply_error(message_request_id(&msg), code, reason);
+                                continue;
+                            }
+                        }
                         match msg {
                             NetMessage::ClientIntent {
+                                input_seq,
+                                dt,
                                 move_x,
                                 move_z,
                                 display_yaw,
                                 fly_up,
                                 fly_down,
+                                flying,
                                 ..
                             } => {
-                                if last_intent.elapsed() < MIN_INTENT_INTERVAL {
-                                    continue;
-                                }
+                                // Pre-v8 intents hold until the next one: apply for the time since the last.
+                                let dt = if dt > 0.0 {
+                                    dt
+                                } else {
+                                    if last_intent.elapsed() < MIN_INTENT_INTERVAL {
+                                        continue;
+                                    }
+                                    last_intent.elapsed().as_secs_f32()
+                                };
                                 last_intent = Instant::now();
+                                let input = MoveInput {
+                                    move_x,
+                                    move_z,
+                                    display_yaw,
+                                    fly_up,
+                                    fly_down,
+                                    flying,
+                                };
                                 let mut w = world.write().await;
-                                w.apply_intent(avatar_id, move_x, move_z, display_yaw, fly_up, fly_down);
+                                w.apply_intent(avatar_id, input_seq, &input, dt);
+                            }
+                            NetMessage::SnapshotAck { tick } => {
+                                let mut w = world.write().await;
+                                w.ack_tick(avatar_id, tick);
+                            }
+                            NetMessage::Ping { sent_at_us } => {
+                                let pong = NetMessage::Pong {
+                                    ping_sent_at_us: sent_at_us,
+                                    pong_sent_at_us: now_micros(),
+                                };
+                                match session.encode(&pong) {
+                                    Ok(bytes) => {
+                                        session.outbound.push_control(Bytes::from(bytes));
+                                    }
+                                    Err(e) => {
+                                        outcome = Err(e.into());
+                                        break;
+                                    }
+                                }
+                            }
+                            NetMessage::SetRegionRole {
+                                request_id,
+                                region_id,
+                                user_id,
+                                role,
+                            } => {
+                                if !session.capabilities.contains(Capabilities::REGION_ADMIN) {
+                                    let reason = "REGION_ADMIN was not negotiated".to_string();
+                                    session.reply_error(request_id, ErrorCode::BadRequest, reason);
+                                    continue;
+                                }
+                                // An owner cannot lock the region out by demoting themself.
+                                if user_id == session.user_id {
+                                    let reason = "you cannot change your own role".to_string();
+                                    session.reply_error(request_id, ErrorCode::InvalidEdit, reason);
+                                    continue;
+                                }
+                                let stored = db
+                                    .call(move |conn| {
+                                        db::set_region_role(conn, region_id, user_id, role)
+                                    })
+                                    .await;
+                                match stored {
+                                    Ok(true) => {
+                                        let mut w = world.write().await;
+                                        w.set_region_role(region_id, user_id, role);
+                                        tracing::info!(
+                                            granted_by = session.user_id,
+                                            region_id,
+                                            user_id,
+                                            role = permissions::role_name(role),
+                                            "region role set"
+                                        );
+                                    }
+                                    Ok(false) => {
+                                        let reason = format!("no account {user_id}");
+                                        let code = ErrorCode::InvalidEdit;
+                                        session.reply_error(request_id, code, reason);
+                                    }
+                                    Err(e) => {
+                                        tracing::warn!("set_region_role: {e:#}");
+                                        let reason = "could not store the role".to_string();
+                                        let code = ErrorCode::Internal;
+                                        session.reply_error(request_id, code, reason);
+                                    }
+                                }
+                            }
+                            NetMessage::EditPrim { request_id, edit } => {
+                                if !session.capabilities.contains(Capabilities::PRIM_EDITING) {
+                                    let reason = "PRIM_EDITING was not negotiated".to_string();
+                                    session.reply_error(request_id, ErrorCode::BadRequest, reason);
+                                    continue;
+                                }
+                                // Applied and queued for SQLite under one lock, so the database
+                                // sees edits in the order every session does (ADR-013).
+                                let (id, undo, store) = {
+                                    let mut w = world.write().await;
+                                    let undo = w.prim_undo(&edit);
+                                    match w.edit_prim(&edit) {
+                                        Ok(change) => {
+                                            let id = change.prim_id();
+                                            let author = Some(session.user_id);
+                                            let store = db.submit(move |conn| {
+                                                db::store_prim_change(conn, &change, author)
+                                            });
+                                            (id, undo, store)
+                                        }
+                                        Err((code, reason)) => {
+                                            session.reply_error(request_id, code, reason);
+                                            continue;
+                                        }
+                                    }
+                                };
+                                if let Err(e) = store.await {
+                                    tracing::error!(prim_id = id, "prim edit not stored: {e:#}");
+                                    // Undone, so sessions do not keep a prim the database lacks.
+                                    world.write().await.restore_prims(&[undo]);
+                                    let reason = "edit could not be stored".to_string();
+                                    session.reply_error(request_id, ErrorCode::Internal, reason);
+                                    continue;
+                                }
+                                tracing::debug!(user_id = session.user_id, ?edit, "prim edited");
+                                match session.encode(&NetMessage::PrimEditAck { request_id, id }) {
+                                    Ok(bytes) => {
+                                        session.outbound.push_control(Bytes::from(bytes));
+                                    }
+                                    Err(e) => {
+                                        outcome = Err(e.into());
+                                        break;
+                                    }
+                                }
+                            }
+                            NetMessage::RollbackRegion {
+                                request_id,
+                                region_id,
+                                to,
+                            } => {
+                                if !session.capabilities.contains(Capabilities::REGION_ADMIN) {
+                                    let reason = "REGION_ADMIN was not negotiated".to_string();
+                                    session.reply_error(request_id, ErrorCode::BadRequest, reason);
+                                    continue;
+                                }
+                                // Queued under the world lock, so SQLite rolls back between the
+                                // edits before and after it (ADR-013), but not waited for under it.
+                                let rolled_back = {
+                                    let _order = world.write().await;
+                                    let time = to.clone();
+                                    let author = Some(session.user_id);
+                                    db.submit(move |conn| {
+                                        if db::history_time(conn, &time)?.is_none() {
+                                            return Ok(None);
+                                        }
+                                        let changes =
+                                            db::rollback_region(conn, region_id, &time, author)?;
+                                        Ok(Some(changes))
+                                    })
+                                };
+                                match rolled_back.await {
+                                    Ok(Some(changes)) => {
+                                        // Edits since may have changed these prims again; once the
+                                        // queue has caught up under the lock, SQLite has them as
+                                        // the world should.
+                                        let ids: Vec<i64> =
+                                            changes.iter().map(|c| c.prim_id()).collect();
+                                        let mut w = world.write().await;
+                                        let current = db
+                                            .call(move |conn| db::current_prims(conn, &ids))
+                                            .await;
+                                        match current {
+                                            Ok(current) => w.restore_prims(&current),
+                                            Err(e) => {
+                                                drop(w);
+                                                tracing::error!(region_id, "rollback: {e:#}");
+                                                let reason = "rolled back in the database only";
+                                                let code = ErrorCode::Internal;
+                                                let reason = reason.to_string();
+                                                session.reply_error(request_id, code, reason);
+                                                continue;
+                                            }
+                                        }
+                                        drop(w);
+                                        tracing::info!(
+                                            user_id = session.user_id,
+                                            region_id,
+                                            %to,
+                                            changed = changes.len(),
+                                            "rolled region back"
+                                        );
+                                    }
+                                    Ok(None) => {
+                                        let reason = format!("{to:?} is not a date/time");
+                                        let code = ErrorCode::InvalidEdit;
+                                        session.reply_error(request_id, code, reason);
+                                    }
+                                    Err(e) => {
+                                        tracing::error!(region_id, "rollback: {e:#}");
+                                        let reason = "rollback failed".to_string();
+                                        let code = ErrorCode::Internal;
+                                        session.reply_error(request_id, code, reason);
+                                    }
+                                }
+                            }
+                            NetMessage::SendChat {
+                                request_id,
+                                scope,
+                                text,
+                            } => {
+                                if !session.capabilities.contains(Capabilities::CHAT) {
+                                    let reason = "CHAT was not negotiated".to_string();
+                                    session.reply_error(request_id, ErrorCode::BadRequest, reason);
+                                    continue;
+                                }
+                                let said = {
+                                    let mut w = world.write().await;
+                                    w.say(avatar_id, session.user_id, scope, &text)
+                                };
+                                match said {
+                                    Ok(()) => {
+                                        tracing::debug!(user_id = session.user_id, ?scope, "chat");
+                                    }
+                                    Err((code, reason)) => {
+                                        session.reply_error(request_id, code, reason);
+                                    }
+                                }
+                            }
+                            NetMessage::SetDisplayName { request_id, name } => {
+                                if !session.capabilities.contains(Capabilities::DISPLAY_NAMES) {
+                                    let reason = "DISPLAY_NAMES was not negotiated".to_string();
+                                    session.reply_error(request_id, ErrorCode::BadRequest, reason);
+                                    continue;
+                                }
+                                let name = match validate_display_name(&name) {
+                                    Ok(name) => name.to_string(),
+                                    Err(reason) => {
+                                        let code = ErrorCode::InvalidProfile;
+                                        session.reply_error(request_id, code, reason);
+                                        continue;
+                                    }
+                                };
+                                // Charged only once stored, so a failed write costs no change.
+                                let allowed = world.read().await.check_profile_change(avatar_id);
+                                if let Err((code, reason)) = allowed {
+                                    session.reply_error(request_id, code, reason);
+                                    continue;
+                                }
+                                let user_id = session.user_id;
+                                let stored_name = name.clone();
+                                let stored = db
+                                    .call(move |conn| {
+                                        db::set_display_name(conn, user_id, &stored_name)
+                                    })
+                                    .await;
+                                match stored {
+                                    Ok(()) => {
+                                        let mut w = world.write().await;
+                                        w.charge_profile_change(avatar_id);
+                                        w.set_display_name(user_id, &name);
+                                        tracing::info!(user_id, %name, "display name set");
+                                    }
+                                    Err(e) => {
+                                        tracing::warn!("set_display_name: {e:#}");
+                                        let reason = "could not store the name".to_string();
+                                        let code = ErrorCode::Internal;
+                                        session.reply_error(request_id, code, reason);
+                                    }
+                                }
                             }
-                            NetMessage::ObserverUpdate { position } => {
-                                if last_observer.elapsed() < MIN_OBSERVER_INTERVAL {
+                            NetMessage::SetAppearance {
+                                request_id,
+                                appearance,
+                            } => {
+                                if !session.capabilities.contains(Capabilities::APPEARANCE) {
+                                    let reason = "APPEARANCE was not negotiated".to_string();
+                                    session.reply_error(request_id, ErrorCode::BadRequest, reason);
                                     continue;
                                 }
-                                last_observer = Instant::now();
+                                if let Err(reason) = validate_appearance(&appearance) {
+                                    let code = ErrorCode::InvalidProfile;
+                                    session.reply_error(request_id, code, reason);
+                                    continue;
+                                }
+                                let model = appearance.model.clone();
                                 let mut w = world.write().await;
-                                w.set_observer(position);
+                                if let Err((code, reason)) = w.spend_profile_change(avatar_id) {
+                                    session.reply_error(request_id, code, reason);
+                                    continue;
+                                }
+                                w.set_appearance(avatar_id, appearance);
+                                drop(w);
+                                tracing::debug!(avatar_id, %model, "appearance set");
                             }
                             NetMessage::ClientHello { .. } => {
                                 tracing::warn!("duplicate hello ignored");
                             }
-                            NetMessage::PrimRemoved { .. }
+                            // ADR-012: AOI follows this session's avatar; client-claimed positions are not trusted.
+                            NetMessage::ObserverUpdate { .. }
+                            | NetMessage::PrimRemoved { .. }
                             | NetMessage::WorldSnapshot { .. }
+                            | NetMessage::AvatarsAdded { .. }
+                            | NetMessage::AvatarsUpdated { .. }
+                            | NetMessage::AvatarsRemoved { .. }
+                            | NetMessage::RegionEntered { .. }
+                            | NetMessage::RegionLeft { .. }
                             | NetMessage::ServerHelloAck { .. }
-                            | NetMessage::ServerError { .. } => {
+                            | NetMessage::ServerError { .. }
+                            | NetMessage::Pong { .. }
+                            | NetMessage::AuthChallenge { .. }
+                            | NetMessage::AuthResponse { .. }
+                            | NetMessage::PrimUpdated { .. }
+                            | NetMessage::PrimEditAck { .. }
+                            | NetMessage::ChatMessage { .. }
+                            | NetMessage::AvatarProfiles { .. }
+                            | NetMessage::AvatarAppearances { .. } => {
                                 tracing::debug!(?msg, "ignored message from client");
                             }
                         }
                     }
                 }
             }
-            snap = snap_rx.recv() => {
-                match snap {
-                    Ok(bytes) => {
-                        if let Err(e) = framed.send(Bytes::from(bytes)).await {
-                            outcome = Err(e.into());
-                            break;
-                        }
-                    }
-                    Err(broadcast::error::RecvError::Lagged(_)) => continue,
-                    Err(broadcast::error::RecvError::Closed) => break,
+            () = &mut idle => {
+                tracing::info!(avatar_id, ?idle_timeout, "idle timeout");
+                let reason = format!("nothing received for {idle_timeout:?}");
+                session.close_with_error(ErrorCode::IdleTimeout, reason);
+                break;
+            }
+            _ = shutdown.changed() => {
+                session.close_with_error(ErrorCode::ShuttingDown, "sim is shutting down".into());
+                break;
+            }
+            // Ends on a write error, or after the tick loop evicted this session as too slow.
+            written = &mut writer => {
+                writer_done = true;
+                match written {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => outcome = Err(e.into()),
+                    Err(e) => outcome = Err(e.into()),
                 }
+                break;
             }
         }
     }
 
+    session.outbound.close(None);
+    if !writer_done {
+        let flushed = tokio::time::timeout(CLOSE_FLUSH_TIMEOUT, &mut writer).await;
+        if flushed.is_err() {
+            writer.abort();
+        }
+    }
+    let out = session.outbound.stats();
+    tracing::debug!(
+        avatar_id,
+        frames = out.frames,
+        bytes = out.bytes,
+        coalesced = out.coalesced,
+        max_queued_bytes = out.max_queued_bytes,
+        "session outbound"
+    );
+
     {
         let mut w = world.write().await;
         w.remove_avatar(avatar_id);
+        sessions.write().await.remove(&avatar_id);
     }
     tracing::info!(avatar_id, "avatar removed (disconnect)");
 
     outcome
 }
 
-/// Periodically steps simulation and broadcasts postcard-encoded [`NetMessage::WorldSnapshot`] in app frames.
-pub async fn tick_loop(
-    world: Arc<RwLock<SimWorld>>,
-    config: Arc<SimConfig>,
-    tx: broadcast::Sender<Vec<u8>>,
-) {
-    let period = std::time::Duration::from_secs_f32((1.0 / config.tick_hz).max(0.001));
-    let mut interval = tokio::time::interval(period);
-    let mut tick: u64 = 0;
+/// Encode one session's updates, dropping what its capabilities exclude or its version lacks.
+fn encode_updates(avatar_id: u64, session: &Session, msgs: &[NetMessage]) -> TickFrames {
+    let mut frames = TickFrames::default();
+    for msg in msgs.iter().filter(|m| session.wants(m)) {
+        let bytes = match session.encode(msg) {
+            Ok(b) => Bytes::from(b),
+            // Older negotiated version: this message simply does not exist there.
+            Err(ProtocolError::NotInVersion { .. }) => continue,
+            Err(e) => {
+                tracing::error!(avatar_id, "session update encode: {e}");
+                continue;
+            }
+        };
+        match msg {
+            NetMessage::RegionEntered { .. }
+            | NetMessage::RegionLeft { .. }
+            | NetMessage::PrimUpdated { .. }
+            | NetMessage::PrimRemoved { .. }
+            | NetMessage::ChatMessage { .. }
+            | NetMessage::AvatarProfiles { .. }
+            | NetMessage::AvatarAppearances { .. } => frames.events.push(bytes),
+            _ => frames.state.push(bytes),
+        }
+    }
+    frames
+}
+
+/// Steps the simulation at a fixed `1 / tick_hz` and queues each session's region enter/leave, prim
+/// changes and avatar deltas (ADR-010–012), computed from that session's AOI and last acknowledged
+/// tick.
+/// Sessions whose queue stays behind past `slow_consumer_timeout_secs` are evicted.
+///
+/// Every step advances the world by exactly one period. A tick that starts late is not caught up
+/// with a burst: the ticks it missed are counted as overruns and skipped, and the schedule moves
+/// on from the next deadline. Phase timings are traced per tick and summarised in [`TickStats`].
+pub async fn tick_loop(world: Arc<RwLock<SimWorld>>, sessions: Sessions, config: Arc<SimConfig>) {
+    let period = Duration::from_secs_f32((1.0 / config.tick_hz).max(0.001));
+    let dt = period.as_secs_f32();
+    let mut stats = TickStats::default();
+    let mut next = tokio::time::Instant::now() + period;
+    let mut next_report = Instant::now() + TICK_STATS_INTERVAL;
     loop {
-        interval.tick().await;
-        tick += 1;
+        tokio::time::sleep_until(next).await;
+        let late = tokio::time::Instant::now().saturating_duration_since(next);
+        let skipped = (late.as_nanos() / period.as_nanos()) as u32;
+        if skipped > 0 {
+            stats.overruns += u64::from(skipped);
+            tracing::debug!(skipped, ?late, "tick overrun");
+        }
+        next += period * (skipped + 1);
+
+        let mut timing = TickTiming::default();
+        let started = Instant::now();
         let mut w = world.write().await;
-        w.step(period.as_secs_f32());
-        let snap = w.snapshot(tick);
+        w.step(dt);
+        timing.step = started.elapsed();
+        let tick = w.tick();
+        let load = w.load();
+        let built = Instant::now();
+        let ids: Vec<u64> = w.avatar_ids().collect();
+        let updates: Vec<(u64, Vec<NetMessage>)> = ids
+            .into_iter()
+            .map(|id| (id, w.session_updates(id)))
+            .filter(|(_, msgs)| !msgs.is_empty())
+            .collect();
         drop(w);
-        match encode_app_frame(&snap) {
-            Ok(bytes) => {
-                let _ = tx.send(bytes);
+        timing.build = built.elapsed();
+
+        let sessions = sessions.read().await;
+        let encoded = Instant::now();
+        let frames: Vec<(&Session, u64, TickFrames)> = updates
+            .iter()
+            .filter_map(|(id, msgs)| {
+                let session = sessions.get(id)?;
+                Some((session, *id, encode_updates(*id, session, msgs)))
+            })
+            .filter(|(_, _, f)| !f.is_empty())
+            .collect();
+        timing.encode = encoded.elapsed();
+
+        let fanned = Instant::now();
+        for (session, avatar_id, f) in frames {
+            match session.outbound.push_tick(f) {
+                Pushed::Queued | Pushed::Closed => {}
+                Pushed::Coalesced => stats.coalesced += 1,
+                Pushed::TooSlow => {
+                    stats.evicted += 1;
+                    tracing::warn!(avatar_id, "evicting slow consumer");
+                    let reason = format!(
+                        "outbound queue behind for over {}s",
+                        config.slow_consumer_timeout_secs
+                    );
+                    session.close_with_error(ErrorCode::SlowConsumer, reason);
+                }
+            }
+        }
+        drop(sessions);
+        timing.fan_out = fanned.elapsed();
+
+        tracing::trace!(
+            tick,
+            step = ?timing.step,
+            build = ?timing.build,
+            encode = ?timing.encode,
+            fan_out = ?timing.fan_out,
+            "tick"
+        );
+        stats.record(&timing, period);
+
+        if Instant::now() >= next_report {
+            next_report += TICK_STATS_INTERVAL;
+            let window = stats.take();
+            let mean = window.mean();
+            let (regions, prims, avatars) = load;
+            if window.overruns > 0 || window.over_budget > 0 {
+                tracing::warn!(
+                    tick_hz = config.tick_hz,
+                    ticks = window.ticks,
+                    overruns = window.overruns,
+                    over_budget = window.over_budget,
+                    max_tick = ?window.max.total(),
+                    regions,
+                    prims,
+                    avatars,
+                    "world too heavy for tick_hz"
+                );
             }
-            Err(e) => tracing::error!("snapshot encode: {e}"),
+            tracing::info!(
+                ticks = window.ticks,
+                overruns = window.overruns,
+                coalesced = window.coalesced,
+                evicted = window.evicted,
+                mean_step = ?mean.step,
+                mean_build = ?mean.build,
+                mean_encode = ?mean.encode,
+                mean_fan_out = ?mean.fan_out,
+                max_tick = ?window.max.total(),
+                avatars,
+                "tick stats"
+            );
         }
     }
 }
@@ -0,0 +1,251 @@
+//! Per-session outbound queue (ADR-008): the tick loop and the session's reader push encoded
+//! frames, one writer task per connection drains them, so a stalled socket never blocks the tick
+//! or the session's input handling.
+//!
+//! Priority order: control frames (hello ack, join snapshot, pongs, errors), then region and prim
+//! events, then avatar deltas. Avatar deltas are diffed against the client's last acknowledged tick, so a
+//! newer tick's deltas replace unsent older ones; region events are never dropped.
+
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+
+/// Queued bytes past which a session is evicted at once, however briefly it has been behind.
+const MAX_QUEUED_BYTES: usize = 64 * 1024 * 1024;
+
+/// One tick's encoded updates for one session.
+#[derive(Debug, Default)]
+pub struct TickFrames {
+    /// `RegionEntered` / `RegionLeft` and prim changes, in order.
+    pub events: Vec<Bytes>,
+    /// Avatar add/update/remove; supersedes any unsent earlier tick's.
+    pub state: Vec<Bytes>,
+}
+
+impl TickFrames {
+    pub fn is_empty(&self) -> bool {
+        self.events.is_empty() && self.state.is_empty()
+    }
+}
+
+/// What [`Outbound::push_tick`] did with a tick.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pushed {
+    Queued,
+    /// Replaced the avatar deltas of a tick the writer had not sent yet.
+    Coalesced,
+    /// The session has been behind for longer than its threshold (or queued too much); evict it.
+    TooSlow,
+    /// The session is closing; the tick was dropped.
+    Closed,
+}
+
+/// Counters over the session's lifetime, logged when it ends.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OutboundStats {
+    pub frames: u64,
+    pub bytes: u64,
+    /// Ticks whose avatar deltas were replaced before they were written.
+    pub coalesced: u64,
+    pub max_queued_bytes: usize,
+}
+
+#[derive(Default)]
+struct Queue {
+    control: VecDeque<Bytes>,
+    events: Vec<Bytes>,
+    state: Vec<Bytes>,
+    queued_bytes: usize,
+    /// Since when tick updates have been waiting when the next tick arrived.
+    behind_since: Option<Instant>,
+    closing: bool,
+    stats: OutboundStats,
+}
+
+fn len(frames: &[Bytes]) -> usize {
+    frames.iter().map(Bytes::len).sum()
+}
+
+impl Queue {
+    fn has_updates(&self) -> bool {
+        !self.events.is_empty() || !self.state.is_empty()
+    }
+
+    fn grew(&mut self, bytes: usize) {
+        self.queued_bytes += bytes;
+        self.stats.max_queued_bytes = self.stats.max_queued_bytes.max(self.queued_bytes);
+    }
+}
+
+pub struct Outbound {
+    queue: Mutex<Queue>,
+    ready: Notify,
+    /// How long tick updates may keep piling up before the session counts as too slow.
+    slow_after: Duration,
+}
+
+impl Outbound {
+    pub fn new(slow_after: Duration) -> Self {
+        Self {
+            queue: Mutex::default(),
+            ready: Notify::new(),
+            slow_after,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(std::sync::PoisonError::into_inner)
+    }
+
+    /// Queue a control frame ahead of all tick updates. Returns `false` once the session is closing.
+    pub fn push_control(&self, frame: Bytes) -> bool {
+        let mut q = self.lock();
+        if q.closing {
+            return false;
+        }
+        q.grew(frame.len());
+        q.control.push_back(frame);
+        drop(q);
+        self.ready.notify_one();
+        true
+    }
+
+    /// Queue one tick's updates, coalescing with an unsent earlier tick.
+    pub fn push_tick(&self, frames: TickFrames) -> Pushed {
+        let mut q = self.lock();
+        if q.closing {
+            return Pushed::Closed;
+        }
+        let now = Instant::now();
+        if q.has_updates() {
+            q.behind_since.get_or_insert(now);
+        }
+        let coalesced = !q.state.is_empty() && !frames.state.is_empty();
+        if !frames.state.is_empty() {
+            let replaced = len(&q.state);
+            q.queued_bytes -= replaced;
+            q.grew(len(&frames.state));
+            q.state = frames.state;
+        }
+        q.grew(len(&frames.events));
+        q.events.extend(frames.events);
+        if coalesced {
+            q.stats.coalesced += 1;
+        }
+        let too_slow = q.queued_bytes > MAX_QUEUED_BYTES
+            || q
+                .behind_since
+                .is_some_and(|t| now.duration_since(t) > self.slow_after);
+        drop(q);
+        self.ready.notify_one();
+        match (too_slow, coalesced) {
+            (true, _) => Pushed::TooSlow,
+            (false, true) => Pushed::Coalesced,
+            (false, false) => Pushed::Queued,
+        }
+    }
+
+    /// Drop pending tick updates, queue `last` (usually an error) and let the writer finish.
+    pub fn close(&self, last: Option<Bytes>) {
+        let mut q = self.lock();
+        if q.closing {
+            return;
+        }
+        q.closing = true;
+        q.events.clear();
+        q.state.clear();
+        q.control.extend(last);
+        q.queued_bytes = q.control.iter().map(Bytes::len).sum();
+        drop(q);
+        self.ready.notify_one();
+    }
+
+    /// Next frames to write, in priority order; `None` once closing and drained.
+    pub async fn next_batch(&self) -> Option<Vec<Bytes>> {
+        loop {
+            {
+                let mut q = self.lock();
+                if !q.control.is_empty() || q.has_updates() {
+                    let mut batch: Vec<Bytes> = q.control.drain(..).collect();
+                    let events = std::mem::take(&mut q.events);
+                    let state = std::mem::take(&mut q.state);
+                    batch.extend(events);
+                    batch.extend(state);
+                    q.behind_since = None;
+                    q.queued_bytes = 0;
+                    q.stats.frames += batch.len() as u64;
+                    q.stats.bytes += len(&batch) as u64;
+                    return Some(batch);
+                }
+                if q.closing {
+                    return None;
+                }
+            }
+            self.ready.notified().await;
+        }
+    }
+
+    pub fn stats(&self) -> OutboundStats {
+        self.lock().stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures_util::FutureExt;
+
+    fn tick(event: &'static [u8], state: &'static [u8]) -> TickFrames {
+        TickFrames {
+            events: vec![Bytes::from_static(event)],
+            state: vec![Bytes::from_static(state)],
+        }
+    }
+
+    #[test]
+    fn unsent_state_frames_are_coalesced() {
+        let out = Outbound::new(Duration::from_secs(5));
+        assert!(out.push_control(Bytes::from_static(b"ack")));
+        assert_eq!(out.push_tick(tick(b"e1", b"s1")), Pushed::Queued);
+        assert_eq!(out.push_tick(tick(b"e2", b"s2")), Pushed::Coalesced);
+        let batch = out.next_batch().now_or_never().flatten().unwrap();
+        assert_eq!(batch, ["ack", "e1", "e2", "s2"].map(Bytes::from));
+        let stats = out.stats();
+        assert_eq!((stats.frames, stats.coalesced), (4, 1));
+        // Drained: the next tick is not behind anything.
+        assert_eq!(out.push_tick(tick(b"e3", b"s3")), Pushed::Queued);
+    }
+
+    #[test]
+    fn evicted_when_behind_past_slow_after() {
+        let out = Outbound::new(Duration::from_millis(10));
+        assert_eq!(out.push_tick(tick(b"e1", b"s1")), Pushed::Queued);
+        assert_eq!(out.push_tick(tick(b"e2", b"s2")), Pushed::Coalesced);
+        std::thread::sleep(Duration::from_millis(20));
+        assert_eq!(out.push_tick(tick(b"e3", b"s3")), Pushed::TooSlow);
+    }
+
+    #[test]
+    fn evicted_at_once_past_max_queued_bytes() {
+        let out = Outbound::new(Duration::from_secs(60));
+        let frames = TickFrames {
+            events: vec![Bytes::from(vec![0; MAX_QUEUED_BYTES + 1])],
+            state: Vec::new(),
+        };
+        assert_eq!(out.push_tick(frames), Pushed::TooSlow);
+    }
+
+    #[test]
+    fn closing_drops_updates_but_sends_last_frame() {
+        let out = Outbound::new(Duration::from_secs(5));
+        out.push_tick(tick(b"e1", b"s1"));
+        out.close(Some(Bytes::from_static(b"bye")));
+        assert_eq!(out.push_tick(tick(b"e2", b"s2")), Pushed::Closed);
+        assert!(!out.push_control(Bytes::from_static(b"late")));
+        let batch = out.next_batch().now_or_never().flatten().unwrap();
+        assert_eq!(batch, [Bytes::from_static(b"bye")]);
+        assert_eq!(out.next_batch().now_or_never(), Some(None));
+    }
+}
@@ -0,0 +1,255 @@
+//! Region authorization (ADR-016): every mutating client message names a [`Target`] and an
+//! [`Action`]; the session's account must hold at least the action's role in the target's region.
+
+use std::collections::HashMap;
+use vibe_core::auth::GUEST_USER_ID;
+use vibe_core::{ErrorCode, NetMessage, PrimEdit, RegionRole};
+
+/// Why a request was refused, as sent back in `ServerError`.
+pub type Refusal = (ErrorCode, String);
+
+/// What a mutating message touches; a prim is authorized against the region that holds it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Target {
+    Region(i64),
+    Prim(i64),
+}
+
+/// What a mutating message does to a region.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Create, change or delete the region's prims.
+    EditPrims,
+    /// Grant or revoke roles in the region.
+    ManageRoles,
+    /// Put the region's prims back as they were at an earlier time (ADR-017).
+    Rollback,
+}
+
+impl Action {
+    pub fn required_role(self) -> RegionRole {
+        match self {
+            Self::EditPrims => RegionRole::Manager,
+            Self::ManageRoles | Self::Rollback => RegionRole::Owner,
+        }
+    }
+}
+
+/// Target and action a client message must be authorized for; `None` for messages that change
+/// nothing shared. Exhaustive on purpose: a new message has to be classified here.
+pub fn mutation(msg: &NetMessage) -> Option<(Target, Action)> {
+    match msg {
+        NetMessage::SetRegionRole { region_id, .. } => {
+            Some((Target::Region(*region_id), Action::ManageRoles))
+        }
+        NetMessage::EditPrim { edit, .. } => {
+            let target = match edit {
+                PrimEdit::Create(prim) => Target::Region(prim.region_id),
+                PrimEdit::Transform { id, .. }
+                | PrimEdit::Recolor { id, .. }
+                | PrimEdit::Rename { id, .. }
+                | PrimEdit::Delete { id } => Target::Prim(*id),
+            };
+            Some((target, Action::EditPrims))
+        }
+        NetMessage::RollbackRegion { region_id, .. } => {
+            Some((Target::Region(*region_id), Action::Rollback))
+        }
+        // Only ever the session's own account or avatar.
+        NetMessage::SetDisplayName { .. } | NetMessage::SetAppearance { .. } => None,
+        NetMessage::ClientHello { .. }
+        | NetMessage::ServerHelloAck { .. }
+        | NetMessage::ServerError { .. }
+        | NetMessage::ClientIntent { .. }
+        | NetMessage::ObserverUpdate { .. }
+        | NetMessage::WorldSnapshot { .. }
+        | NetMessage::PrimRemoved { .. }
+        | NetMessage::AvatarsAdded { .. }
+        | NetMessage::AvatarsUpdated { .. }
+        | NetMessage::AvatarsRemoved { .. }
+        | NetMessage::SnapshotAck { .. }
+        | NetMessage::RegionEntered { .. }
+        | NetMessage::RegionLeft { .. }
+        | NetMessage::Ping { .. }
+        | NetMessage::Pong { .. }
+        | NetMessage::AuthChallenge { .. }
+        | NetMessage::AuthResponse { .. }
+        | NetMessage::PrimUpdated { .. }
+        | NetMessage::PrimEditAck { .. }
+        | NetMessage::SendChat { .. }
+        | NetMessage::ChatMessage { .. }
+        | NetMessage::AvatarProfiles { .. }
+        | NetMessage::AvatarAppearances { .. } => None,
+    }
+}
+
+/// Name stored in `region_roles.role` and accepted by `--grant`.
+pub fn role_name(role: RegionRole) -> &'static str {
+    match role {
+        RegionRole::Visitor => "visitor",
+        RegionRole::Manager => "manager",
+        RegionRole::Owner => "owner",
+    }
+}
+
+pub fn parse_role(name: &str) -> Option<RegionRole> {
+    match name {
+        "visitor" => Some(RegionRole::Visitor),
+        "manager" => Some(RegionRole::Manager),
+        "owner" => Some(RegionRole::Owner),
+        _ => None,
+    }
+}
+
+/// Granted roles per region, mirrored from `region_roles` (written through by the sim).
+#[derive(Debug, Default)]
+pub struct RegionRoles {
+    grants: HashMap<i64, HashMap<i64, RegionRole>>,
+}
+
+impl RegionRoles {
+    pub fn new(rows: impl IntoIterator<Item = (i64, i64, RegionRole)>) -> Self {
+        let mut roles = Self::default();
+        for (region_id, user_id, role) in rows {
+            roles.set(region_id, user_id, role);
+        }
+        roles
+    }
+
+    /// Guests are visitors everywhere, whatever is stored for their id.
+    pub fn role(&self, region_id: i64, user_id: i64) -> RegionRole {
+        if user_id == GUEST_USER_ID {
+            return RegionRole::Visitor;
+        }
+        self.grants
+            .get(&region_id)
+            .and_then(|g| g.get(&user_id))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// `Visitor` removes the grant.
+    pub fn set(&mut self, region_id: i64, user_id: i64, role: RegionRole) {
+        let grants = self.grants.entry(region_id).or_default();
+        if role == RegionRole::Visitor {
+            grants.remove(&user_id);
+        } else {
+            grants.insert(user_id, role);
+        }
+    }
+
+    /// `Err` is the reason sent back with `PermissionDenied`.
+    pub fn check(&self, user_id: i64, region_id: i64, action: Action) -> Result<(), String> {
+        let have = self.role(region_id, user_id);
+        let need = action.required_role();
+        if have >= need {
+            Ok(())
+        } else {
+            Err(format!(
+                "{action:?} in region {region_id} needs {}, you are {}",
+                role_name(need),
+                role_name(have)
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use glam::Vec3;
+    use vibe_core::PrimDto;
+
+    const REGION: i64 = 1;
+    const OWNER: i64 = 10;
+    const MANAGER: i64 = 11;
+    const VISITOR: i64 = 12;
+
+    fn roles() -> RegionRoles {
+        RegionRoles::new([
+            (REGION, OWNER, RegionRole::Owner),
+            (REGION, MANAGER, RegionRole::Manager),
+        ])
+    }
+
+    #[test]
+    fn visitors_may_not_edit() {
+        let roles = roles();
+        assert!(roles.check(VISITOR, REGION, Action::EditPrims).is_err());
+        assert!(roles.check(VISITOR, REGION, Action::ManageRoles).is_err());
+        // A grant in one region says nothing about another.
+        assert!(roles.check(MANAGER, REGION + 1, Action::EditPrims).is_err());
+    }
+
+    #[test]
+    fn managers_edit_but_only_owners_grant() {
+        let roles = roles();
+        assert!(roles.check(MANAGER, REGION, Action::EditPrims).is_ok());
+        let grant = NetMessage::SetRegionRole {
+            request_id: 1,
+            region_id: REGION,
+            user_id: VISITOR,
+            role: RegionRole::Owner,
+        };
+        let (target, action) = mutation(&grant).unwrap();
+        assert_eq!(target, Target::Region(REGION));
+        assert!(roles.check(MANAGER, REGION, action).is_err());
+        assert!(roles.check(OWNER, REGION, action).is_ok());
+        let rollback = NetMessage::RollbackRegion {
+            request_id: 2,
+            region_id: REGION,
+            to: "2026-10-17T12:00".into(),
+        };
+        let (_, action) = mutation(&rollback).unwrap();
+        assert!(roles.check(MANAGER, REGION, action).is_err());
+        assert!(roles.check(OWNER, REGION, action).is_ok());
+    }
+
+    #[test]
+    fn revoking_leaves_a_visitor() {
+        let mut roles = roles();
+        roles.set(REGION, MANAGER, RegionRole::Visitor);
+        assert_eq!(roles.role(REGION, MANAGER), RegionRole::Visitor);
+        assert!(roles.check(MANAGER, REGION, Action::EditPrims).is_err());
+    }
+
+    #[test]
+    fn guests_are_visitors_whatever_is_stored() {
+        let roles = RegionRoles::new([(REGION, GUEST_USER_ID, RegionRole::Owner)]);
+        assert_eq!(roles.role(REGION, GUEST_USER_ID), RegionRole::Visitor);
+        assert!(roles
+            .check(GUEST_USER_ID, REGION, Action::EditPrims)
+            .is_err());
+    }
+
+    #[test]
+    fn prim_edits_target_the_region_or_the_prim() {
+        let prim = PrimDto {
+            id: 0,
+            region_id: REGION,
+            name: "box".into(),
+            shape: "cube".into(),
+            position: Vec3::ZERO,
+            rotation: Vec3::ZERO,
+            scale: Vec3::ONE,
+            color: [1.0; 3],
+        };
+        let create = NetMessage::EditPrim {
+            request_id: 1,
+            edit: PrimEdit::Create(prim),
+        };
+        let delete = NetMessage::EditPrim {
+            request_id: 2,
+            edit: PrimEdit::Delete { id: 7 },
+        };
+        assert_eq!(
+            mutation(&create),
+            Some((Target::Region(REGION), Action::EditPrims))
+        );
+        assert_eq!(
+            mutation(&delete),
+            Some((Target::Prim(7), Action::EditPrims))
+        );
+        assert_eq!(mutation(&NetMessage::SnapshotAck { tick: 1 }), None);
+    }
+}
@@ -1,27 +1,114 @@
 use glam::Vec3;
-use std::collections::HashMap;
-use vibe_core::{snap_yaw_continuation, AvatarStateDto, NetMessage, PrimDto, RegionDto};
+use std::collections::{HashMap, HashSet};
+use vibe_core::chat::{validate_chat, LOCAL_CHAT_RADIUS};
+use vibe_core::edit::validate_prim;
+use vibe_core::kinematics::{GROUND_CLEARANCE, MAX_INPUT_DT};
+use vibe_core::{
+    snap_yaw_continuation, step_position, AvatarAnim, AvatarAppearance, AvatarAppearanceDto,
+    AvatarProfileDto, AvatarReplicator, AvatarStateDto, ChatScope, ErrorCode, MoveInput,
+    NetMessage, PrimDto, PrimEdit, RegionDto, RegionRole, REGION_SIZE_METERS,
+};
+
+use crate::permissions::{Action, Refusal, RegionRoles, Target};
+
+/// ADR-012 hysteresis: a subscribed region is only dropped once it is this much farther than the AOI radius.
+const AOI_LEAVE_FACTOR: f32 = 1.2;
+/// ADR-010: seconds of input a client may bank beyond real time (absorbs send jitter). Inputs past
+/// the budget are clipped, so a fast clock or replayed packets cannot outrun the sim.
+const MAX_INPUT_BUDGET: f32 = 0.5;
+/// Horizontal speed (m/s) above which an avatar replicates as [`AvatarAnim::Run`].
+const RUN_SPEED_THRESHOLD: f32 = 0.1;
+/// ADR-018: chat lines an avatar may send in a burst; the allowance refills at
+/// [`CHAT_LINES_PER_SEC`].
+const CHAT_BURST: f32 = 5.0;
+const CHAT_LINES_PER_SEC: f32 = 1.0;
+/// ADR-019/020: profile changes (each one written and rebroadcast to every session in view) an
+/// avatar may make in a burst; the allowance refills at [`PROFILE_CHANGES_PER_SEC`].
+const PROFILE_BURST: f32 = 3.0;
+const PROFILE_CHANGES_PER_SEC: f32 = 0.2;
 
 struct AvatarSim {
+    /// Account that controls the avatar.
+    user_id: i64,
+    display_name: String,
+    /// Chosen by the session for its avatar (ADR-020).
+    appearance: AvatarAppearance,
+    /// Bumped when the profile or appearance changes, so views resend both (ADR-019).
+    profile_rev: u32,
     position: Vec3,
     yaw: f32,
+    /// Last `ClientIntent::input_seq` applied; echoed to the owning session.
+    last_input_seq: u32,
+    /// Movement time this avatar may still consume, refilled each tick.
+    input_budget: f32,
+    /// From the last applied input; replicated so clients can extrapolate and animate (ADR-011).
     velocity: Vec3,
-    fly_vertical: f32,
+    anim: AvatarAnim,
+    /// Chat lines this avatar may still send, refilled each tick.
+    chat_budget: f32,
+    /// Profile changes this avatar's session may still make, refilled each tick.
+    profile_budget: f32,
+}
+
+/// An applied prim edit, as handed to the database writer (ADR-013).
+#[derive(Debug, Clone)]
+pub enum PrimChange {
+    Upsert(PrimDto),
+    Remove(i64),
+}
+
+impl PrimChange {
+    pub fn prim_id(&self) -> i64 {
+        match self {
+            Self::Upsert(p) => p.id,
+            Self::Remove(id) => *id,
+        }
+    }
+}
+
+/// What one session has been sent, keyed in [`SimWorld`] by the session's own avatar id (ADR-011/012).
+#[derive(Default)]
+struct SessionView {
+    avatars: AvatarReplicator,
+    regions: HashSet<i64>,
+    echoed_input_seq: u32,
+    /// Avatar id -> profile revision this session has been sent (ADR-019).
+    profiles: HashMap<u64, u32>,
 }
 
 pub struct SimWorld {
     regions: Vec<RegionDto>,
     prims: Vec<PrimDto>,
+    next_prim_id: i64,
+    /// `(region_id, PrimUpdated | PrimRemoved)` applied since the last tick; replicated with the
+    /// next one so they stay ordered with region enter/leave (ADR-011/012).
+    prim_events: Vec<(i64, NetMessage)>,
+    /// The events being replicated by the current tick.
+    tick_prim_events: Vec<(i64, NetMessage)>,
+    /// `(recipient avatar id, ChatMessage)` said since the last tick (ADR-018).
+    chat_events: Vec<(u64, NetMessage)>,
+    /// The chat lines being delivered by the current tick.
+    tick_chat_events: Vec<(u64, NetMessage)>,
+    /// Granted region roles (ADR-016), written through to `region_roles`.
+    roles: RegionRoles,
     /// Region id -> approximate sim origin (for AOI); v0 single region at origin.
     region_sim_origin: HashMap<i64, Vec3>,
     avatars: HashMap<u64, AvatarSim>,
+    views: HashMap<u64, SessionView>,
     next_avatar_id: u64,
-    observer: Vec3,
+    tick: u64,
     aoi_radius_sq: f32,
+    aoi_leave_radius_sq: f32,
 }
 
 impl SimWorld {
-    pub fn new(mut regions: Vec<RegionDto>, prims: Vec<PrimDto>, aoi_radius: f32) -> Self {
+    pub fn new(
+        mut regions: Vec<RegionDto>,
+        prims: Vec<PrimDto>,
+        next_prim_id: i64,
+        roles: RegionRoles,
+        aoi_radius: f32,
+    ) -> Self {
         let mut region_sim_origin = HashMap::new();
         let n = regions.len().max(1);
         let grid_size = (n as f32).sqrt().ceil() as usize;
@@ -47,105 +134,456 @@ impl SimWorld {
         Self {
             regions,
             prims,
+            next_prim_id,
+            prim_events: Vec::new(),
+            tick_prim_events: Vec::new(),
+            chat_events: Vec::new(),
+            tick_chat_events: Vec::new(),
+            roles,
             region_sim_origin,
             avatars: HashMap::new(),
+            views: HashMap::new(),
             next_avatar_id: 1,
-            observer: Vec3::ZERO,
+            tick: 0,
             aoi_radius_sq: aoi_radius * aoi_radius,
+            aoi_leave_radius_sq: (aoi_radius * AOI_LEAVE_FACTOR).powi(2),
         }
     }
 
-    pub fn spawn_avatar(&mut self) -> u64 {
+    /// Sim origin of the lowest-id region; where new avatars appear.
+    fn spawn_point(&self) -> Vec3 {
+        self.regions
+            .iter()
+            .map(|r| r.id)
+            .min()
+            .and_then(|rid| self.region_sim_origin.get(&rid).copied())
+            .unwrap_or(Vec3::ZERO)
+    }
+
+    pub fn spawn_avatar(&mut self, user_id: i64, display_name: String) -> u64 {
         let id = self.next_avatar_id;
         self.next_avatar_id += 1;
-        let mut ids: Vec<i64> = self.regions.iter().map(|r| r.id).collect();
-        ids.sort();
-        let start = ids
-            .first()
-            .and_then(|rid| self.region_sim_origin.get(rid).copied())
-            .unwrap_or(Vec3::ZERO);
+        let start = self.spawn_point();
         self.avatars.insert(
             id,
             AvatarSim {
-                position: start + Vec3::new(0.0, 1.0, 0.0),
+                user_id,
+                display_name,
+                appearance: AvatarAppearance::default(),
+                profile_rev: 0,
+                position: start + Vec3::new(0.0, GROUND_CLEARANCE, 0.0),
                 // Match client tank convention: yaw π ↔ tank 0 ↔ travel −Z when pressing W.
                 yaw: std::f32::consts::PI,
+                last_input_seq: 0,
+                input_budget: MAX_INPUT_BUDGET,
                 velocity: Vec3::ZERO,
-                fly_vertical: 0.0,
+                anim: AvatarAnim::Idle,
+                chat_budget: CHAT_BURST,
+                profile_budget: PROFILE_BURST,
             },
         );
+        self.views.insert(id, SessionView::default());
         id
     }
 
     pub fn remove_avatar(&mut self, id: u64) {
         self.avatars.remove(&id);
+        self.views.remove(&id);
+    }
+
+    /// Client acknowledged everything up to `tick`; later deltas diff against it.
+    pub fn ack_tick(&mut self, avatar_id: u64, tick: u64) {
+        if let Some(v) = self.views.get_mut(&avatar_id) {
+            v.avatars.ack(tick);
+        }
+    }
+
+    pub fn avatar_ids(&self) -> impl Iterator<Item = u64> + '_ {
+        self.avatars.keys().copied()
+    }
+
+    /// Last stepped tick.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Regions, prims and avatars held by this sim; logged when ticks run over budget.
+    pub fn load(&self) -> (usize, usize, usize) {
+        (self.regions.len(), self.prims.len(), self.avatars.len())
+    }
+
+    /// ADR-016: may `user_id` do `action` to `target`? Unknown regions are denied; unknown prims
+    /// are invalid edits.
+    pub fn authorize(&self, user_id: i64, target: Target, action: Action) -> Result<(), Refusal> {
+        let region_id = match target {
+            Target::Region(id) => id,
+            Target::Prim(id) => self
+                .prims
+                .iter()
+                .find(|p| p.id == id)
+                .map(|p| p.region_id)
+                .ok_or_else(|| (ErrorCode::InvalidEdit, format!("unknown prim {id}")))?,
+        };
+        if !self.regions.iter().any(|r| r.id == region_id) {
+            let reason = format!("unknown region {region_id}");
+            return Err((ErrorCode::PermissionDenied, reason));
+        }
+        self.roles
+            .check(user_id, region_id, action)
+            .map_err(|reason| (ErrorCode::PermissionDenied, reason))
+    }
+
+    /// Mirror a grant already stored in `region_roles`.
+    pub fn set_region_role(&mut self, region_id: i64, user_id: i64, role: RegionRole) {
+        self.roles.set(region_id, user_id, role);
+    }
+
+    /// ADR-011 write path: validate and apply one prim edit (already authorized). New prims get
+    /// the next id; the change is replicated with the next tick and returned for the DB writer.
+    pub fn edit_prim(&mut self, edit: &PrimEdit) -> Result<PrimChange, Refusal> {
+        let invalid = |reason: String| (ErrorCode::InvalidEdit, reason);
+        let (index, prim) = match edit {
+            PrimEdit::Create(new) => {
+                let prim = PrimDto {
+                    id: self.next_prim_id,
+                    ..new.clone()
+                };
+                (None, prim)
+            }
+            _ => {
+                let id = edit.prim_id().unwrap_or_default();
+                let index = self
+                    .prims
+                    .iter()
+                    .position(|p| p.id == id)
+                    .ok_or_else(|| invalid(format!("unknown prim {id}")))?;
+                let Some(prim) = edit.applied_to(&self.prims[index]) else {
+                    let removed = self.prims.remove(index);
+                    self.prim_events
+                        .push((removed.region_id, NetMessage::PrimRemoved { id }));
+                    return Ok(PrimChange::Remove(id));
+                };
+                (Some(index), prim)
+            }
+        };
+        let origin = self
+            .region_sim_origin
+            .get(&prim.region_id)
+            .copied()
+            .ok_or_else(|| invalid(format!("unknown region {}", prim.region_id)))?;
+        validate_prim(&prim, origin).map_err(invalid)?;
+        match index {
+            Some(i) => self.prims[i] = prim.clone(),
+            None => {
+                self.next_prim_id += 1;
+                self.prims.push(prim.clone());
+            }
+        }
+        let message = NetMessage::PrimUpdated { prim: prim.clone() };
+        self.prim_events.push((prim.region_id, message));
+        Ok(PrimChange::Upsert(prim))
+    }
+
+    /// ADR-013: the change that puts back what `edit` is about to change, for when storing the
+    /// edit fails.
+    pub fn prim_undo(&self, edit: &PrimEdit) -> PrimChange {
+        let id = edit.prim_id().unwrap_or(self.next_prim_id);
+        match self.prims.iter().find(|p| p.id == id) {
+            Some(prim) => PrimChange::Upsert(prim.clone()),
+            None => PrimChange::Remove(id),
+        }
+    }
+
+    /// Put prims back as `changes` say: what a rollback has stored (ADR-017), or the undo of an
+    /// edit SQLite refused (ADR-013). Sessions with the region in view get them with the next
+    /// tick.
+    pub fn restore_prims(&mut self, changes: &[PrimChange]) {
+        for change in changes {
+            match change {
+                PrimChange::Upsert(prim) => {
+                    match self.prims.iter_mut().find(|p| p.id == prim.id) {
+                        Some(p) => *p = prim.clone(),
+                        None => self.prims.push(prim.clone()),
+                    }
+                    let message = NetMessage::PrimUpdated { prim: prim.clone() };
+                    self.prim_events.push((prim.region_id, message));
+                }
+                PrimChange::Remove(id) => {
+                    let Some(index) = self.prims.iter().position(|p| p.id == *id) else {
+                        continue;
+                    };
+                    let removed = self.prims.remove(index);
+                    self.prim_events
+                        .push((removed.region_id, NetMessage::PrimRemoved { id: *id }));
+                }
+            }
+        }
+    }
+
+    /// ADR-019/020: whether `avatar_id` may make a profile change now; `Err` once its budget is
+    /// used up.
+    pub fn check_profile_change(&self, avatar_id: u64) -> Result<(), Refusal> {
+        let Some(av) = self.avatars.get(&avatar_id) else {
+            return Err((ErrorCode::InvalidProfile, "you have no avatar".into()));
+        };
+        if av.profile_budget < 1.0 {
+            let reason = format!(
+                "at most {PROFILE_BURST} profile changes at once, then one every {} s",
+                1.0 / PROFILE_CHANGES_PER_SEC
+            );
+            return Err((ErrorCode::RateLimited, reason));
+        }
+        Ok(())
+    }
+
+    /// ADR-019/020: charge a profile change made after [`Self::check_profile_change`] allowed it.
+    pub fn charge_profile_change(&mut self, avatar_id: u64) {
+        if let Some(av) = self.avatars.get_mut(&avatar_id) {
+            av.profile_budget = (av.profile_budget - 1.0).max(0.0);
+        }
+    }
+
+    /// ADR-019/020: check and charge one of `avatar_id`'s profile changes, before the change is
+    /// applied.
+    pub fn spend_profile_change(&mut self, avatar_id: u64) -> Result<(), Refusal> {
+        self.check_profile_change(avatar_id)?;
+        self.charge_profile_change(avatar_id);
+        Ok(())
     }
 
-    pub fn set_observer(&mut self, p: Vec3) {
-        self.observer = p;
+    /// ADR-019: rename every avatar of the account (already validated and stored); sessions that
+    /// see them get the new profile with the next tick.
+    pub fn set_display_name(&mut self, user_id: i64, name: &str) {
+        for av in self.avatars.values_mut().filter(|a| a.user_id == user_id) {
+            av.display_name = name.to_string();
+            av.profile_rev += 1;
+        }
     }
 
-    pub fn apply_intent(
+    /// ADR-020: change how one avatar looks (already validated); sessions that see it get the new
+    /// appearance with the next tick.
+    pub fn set_appearance(&mut self, avatar_id: u64, appearance: AvatarAppearance) {
+        if let Some(av) = self.avatars.get_mut(&avatar_id) {
+            av.appearance = appearance;
+            av.profile_rev += 1;
+        }
+    }
+
+    /// ADR-018: deliver a chat line from `avatar_id` (logged in as `user_id`) with the next tick.
+    /// Local lines reach avatars within [`LOCAL_CHAT_RADIUS`], region lines every avatar standing
+    /// in the speaker's region; the speaker always hears its own line.
+    pub fn say(
         &mut self,
         avatar_id: u64,
-        move_x: f32,
-        move_z: f32,
-        display_yaw: f32,
-        fly_up: bool,
-        fly_down: bool,
-    ) {
+        user_id: i64,
+        scope: ChatScope,
+        text: &str,
+    ) -> Result<(), Refusal> {
+        let text = validate_chat(text).map_err(|reason| (ErrorCode::InvalidChat, reason))?;
+        let Some(speaker) = self.avatars.get(&avatar_id) else {
+            return Err((ErrorCode::InvalidChat, "you have no avatar".into()));
+        };
+        if speaker.chat_budget < 1.0 {
+            let reason = format!(
+                "at most {CHAT_BURST} chat lines at once, then {CHAT_LINES_PER_SEC} per second"
+            );
+            return Err((ErrorCode::RateLimited, reason));
+        }
+        let from = speaker.position;
+        let mut recipients: Vec<u64> = match scope {
+            ChatScope::Local => self
+                .avatars
+                .iter()
+                .filter(|(_, a)| a.position.distance_squared(from) <= LOCAL_CHAT_RADIUS.powi(2))
+                .map(|(&id, _)| id)
+                .collect(),
+            ChatScope::Region => {
+                let Some(region_id) = self.region_at(from) else {
+                    return Err((ErrorCode::InvalidChat, "you are not in a region".into()));
+                };
+                self.avatars
+                    .iter()
+                    .filter(|(_, a)| self.region_at(a.position) == Some(region_id))
+                    .map(|(&id, _)| id)
+                    .collect()
+            }
+            ChatScope::Direct { avatar_id: to } => {
+                if !self.avatars.contains_key(&to) {
+                    return Err((ErrorCode::InvalidChat, format!("no avatar {to} is online")));
+                }
+                vec![to]
+            }
+        };
+        if !recipients.contains(&avatar_id) {
+            recipients.push(avatar_id);
+        }
+        if let Some(speaker) = self.avatars.get_mut(&avatar_id) {
+            speaker.chat_budget -= 1.0;
+        }
+        let message = NetMessage::ChatMessage {
+            from_avatar_id: avatar_id,
+            from_user_id: user_id,
+            scope,
+            text: text.to_string(),
+        };
+        self.chat_events
+            .extend(recipients.into_iter().map(|id| (id, message.clone())));
+        Ok(())
+    }
+
+    /// ADR-010: apply one input command for `dt` seconds with the shared kinematics, within the
+    /// avatar's input budget. `seq` 0 (pre-v8 clients) never advances the echoed sequence.
+    pub fn apply_intent(&mut self, avatar_id: u64, seq: u32, input: &MoveInput, dt: f32) {
         let Some(av) = self.avatars.get_mut(&avatar_id) else {
             return;
         };
-        let speed = 8.0_f32;
-        let mut v = Vec3::new(move_x, 0.0, move_z);
-        if v.length_squared() > 1.0 {
-            v = v.normalize() * speed;
-        } else {
-            v *= speed;
-        }
-        av.velocity.x = v.x;
-        av.velocity.z = v.z;
+        let dt = dt.clamp(0.0, av.input_budget);
+        av.input_budget -= dt;
+        let previous = av.position;
+        av.position = step_position(previous, input, dt);
+        if dt > 0.0 {
+            av.velocity = (av.position - previous) / dt.min(MAX_INPUT_DT);
+            let horizontal = Vec3::new(av.velocity.x, 0.0, av.velocity.z);
+            av.anim = if horizontal.length() > RUN_SPEED_THRESHOLD {
+                AvatarAnim::Run
+            } else if input.flying {
+                AvatarAnim::Hover
+            } else {
+                AvatarAnim::Idle
+            };
+        }
         // Always apply facing so remotes see orbit-camera rotation while idle (not only when moving).
-        av.yaw = snap_yaw_continuation(av.yaw, display_yaw);
-        let fly_speed = 5.0_f32;
-        av.fly_vertical = if fly_up {
-            fly_speed
-        } else if fly_down {
-            -fly_speed
-        } else {
-            0.0
-        };
+        av.yaw = snap_yaw_continuation(av.yaw, input.display_yaw);
+        av.last_input_seq = av.last_input_seq.max(seq);
     }
 
+    /// Advance the tick; avatars only move when their inputs arrive ([`Self::apply_intent`]).
+    /// An avatar whose budget is full has sent no input for a while: it is at rest.
     pub fn step(&mut self, dt: f32) {
+        self.tick += 1;
+        self.tick_prim_events = std::mem::take(&mut self.prim_events);
+        self.tick_chat_events = std::mem::take(&mut self.chat_events);
         for av in self.avatars.values_mut() {
-            av.position.x += av.velocity.x * dt;
-            av.position.z += av.velocity.z * dt;
-            av.position.y += av.fly_vertical * dt;
-            if av.position.y < 0.0 {
-                av.position.y = 0.0;
+            av.chat_budget = (av.chat_budget + dt * CHAT_LINES_PER_SEC).min(CHAT_BURST);
+            av.profile_budget =
+                (av.profile_budget + dt * PROFILE_CHANGES_PER_SEC).min(PROFILE_BURST);
+            av.input_budget = (av.input_budget + dt).min(MAX_INPUT_BUDGET);
+            if av.input_budget >= MAX_INPUT_BUDGET {
+                av.velocity = Vec3::ZERO;
+                if av.anim == AvatarAnim::Run {
+                    av.anim = AvatarAnim::Idle;
+                }
             }
-            // Horizontal yaw comes from [`SimWorld::apply_intent`] (`display_yaw`); do not derive from
-            // `atan2(velocity)` (branch cuts + integration drift caused client flip-flops).
         }
     }
 
-    /// ADR-012: filter regions/prims by distance from observer to region origin (v0 heuristic).
-    pub fn snapshot(&self, tick: u64) -> NetMessage {
+    fn last_input_seq(&self, avatar_id: u64) -> u32 {
+        self.avatars.get(&avatar_id).map_or(0, |a| a.last_input_seq)
+    }
+
+    /// ADR-012: each session observes from its own avatar, never from a client-reported position.
+    fn observer_of(&self, avatar_id: u64) -> Vec3 {
+        self.avatars
+            .get(&avatar_id)
+            .map(|a| a.position)
+            .unwrap_or_else(|| self.spawn_point())
+    }
+
+    /// Avatars within this session's AOI (always including its own), ordered by id.
+    fn visible_avatars(&self, avatar_id: u64) -> Vec<AvatarStateDto> {
+        let observer = self.observer_of(avatar_id);
+        let mut avatars: Vec<AvatarStateDto> = self
+            .avatars
+            .iter()
+            .filter(|(&id, a)| {
+                id == avatar_id || (a.position - observer).length_squared() <= self.aoi_radius_sq
+            })
+            .map(|(&id, a)| AvatarStateDto {
+                id,
+                position: a.position,
+                yaw: a.yaw,
+                velocity: a.velocity,
+                anim: a.anim,
+            })
+            .collect();
+        avatars.sort_by_key(|a| a.id);
+        avatars
+    }
+
+    /// Squared horizontal distance from `p` to the region's ground square (0 inside it).
+    fn region_distance_sq(&self, region_id: i64, p: Vec3) -> f32 {
+        let Some(origin) = self.region_sim_origin.get(&region_id) else {
+            return 0.0;
+        };
+        let half = REGION_SIZE_METERS as f32 / 2.0;
+        let dx = ((p.x - origin.x).abs() - half).max(0.0);
+        let dz = ((p.z - origin.z).abs() - half).max(0.0);
+        dx * dx + dz * dz
+    }
+
+    /// Region whose ground square contains `p`, the one with the nearest origin if several do.
+    fn region_at(&self, p: Vec3) -> Option<i64> {
+        self.region_sim_origin
+            .iter()
+            .filter(|&(&id, _)| self.region_distance_sq(id, p) == 0.0)
+            .min_by(|(_, a), (_, b)| a.distance_squared(p).total_cmp(&b.distance_squared(p)))
+            .map(|(&id, _)| id)
+    }
+
+    fn prims_in(&self, region_id: i64) -> Vec<PrimDto> {
+        self.prims
+            .iter()
+            .filter(|p| p.region_id == region_id)
+            .cloned()
+            .collect()
+    }
+
+    /// ADR-019/020: profiles and appearances of `visible` avatars this session has not been sent
+    /// in their current revision. Entries of avatars that are gone are dropped.
+    fn profile_updates(&mut self, avatar_id: u64, visible: &[AvatarStateDto]) -> Vec<NetMessage> {
+        let Some(v) = self.views.get_mut(&avatar_id) else {
+            return Vec::new();
+        };
+        v.profiles.retain(|id, _| self.avatars.contains_key(id));
+        let mut profiles = Vec::new();
+        let mut appearances = Vec::new();
+        for a in visible {
+            let Some(av) = self.avatars.get(&a.id) else {
+                continue;
+            };
+            if v.profiles.insert(a.id, av.profile_rev) != Some(av.profile_rev) {
+                profiles.push(AvatarProfileDto {
+                    id: a.id,
+                    user_id: av.user_id,
+                    display_name: av.display_name.clone(),
+                });
+                appearances.push(AvatarAppearanceDto {
+                    id: a.id,
+                    appearance: av.appearance.clone(),
+                });
+            }
+        }
+        if profiles.is_empty() {
+            return Vec::new();
+        }
+        // Each goes only to sessions with its capability (`Session::wants`).
+        vec![
+            NetMessage::AvatarProfiles { profiles },
+            NetMessage::AvatarAppearances { appearances },
+        ]
+    }
+
+    /// ADR-011: full AOI-filtered world for a joining session. The regions sent here become the
+    /// session's subscription set; the avatars become its first delta baseline once acknowledged.
+    pub fn join_snapshot(&mut self, avatar_id: u64) -> NetMessage {
+        let observer = self.observer_of(avatar_id);
         let regions: Vec<RegionDto> = self
             .regions
             .iter()
-            .filter(|r| {
-                let Some(origin) = self.region_sim_origin.get(&r.id) else {
-                    return true;
-                };
-                (*origin - self.observer).length_squared() <= self.aoi_radius_sq
-            })
+            .filter(|r| self.region_distance_sq(r.id, observer) <= self.aoi_radius_sq)
             .cloned()
             .collect();
-
-        let region_ids: std::collections::HashSet<i64> = regions.iter().map(|r| r.id).collect();
+        let region_ids: HashSet<i64> = regions.iter().map(|r| r.id).collect();
         let prims: Vec<PrimDto> = self
             .prims
             .iter()
@@ -153,22 +591,115 @@ impl SimWorld {
             .cloned()
             .collect();
 
-        // v0 multiplayer: replicate every avatar to all clients. (Regions/prims still use observer AOI.)
-        let avatars: Vec<AvatarStateDto> = self
-            .avatars
-            .iter()
-            .map(|(&id, a)| AvatarStateDto {
-                id,
-                position: a.position,
-                yaw: a.yaw,
-            })
-            .collect();
+        let avatars = self.visible_avatars(avatar_id);
+        let tick = self.tick;
+        let input_seq = self.last_input_seq(avatar_id);
+        if let Some(v) = self.views.get_mut(&avatar_id) {
+            v.avatars.record(tick, &avatars);
+            v.regions = region_ids;
+            v.echoed_input_seq = input_seq;
+        }
 
         NetMessage::WorldSnapshot {
             tick,
             regions,
             prims,
             avatars,
+            input_seq,
+        }
+    }
+
+    /// ADR-012: stream regions in and out as the session's avatar moves. Regions enter within the
+    /// AOI radius and leave beyond [`AOI_LEAVE_FACTOR`] × radius, so boundary walks do not flap.
+    fn region_events_for(&mut self, avatar_id: u64) -> Vec<NetMessage> {
+        let observer = self.observer_of(avatar_id);
+        let Some(subscribed) = self.views.get(&avatar_id).map(|v| &v.regions) else {
+            return Vec::new();
+        };
+        let mut left: Vec<i64> = subscribed
+            .iter()
+            .copied()
+            .filter(|&id| self.region_distance_sq(id, observer) > self.aoi_leave_radius_sq)
+            .collect();
+        left.sort();
+        let entered: Vec<RegionDto> = self
+            .regions
+            .iter()
+            .filter(|r| {
+                !subscribed.contains(&r.id)
+                    && self.region_distance_sq(r.id, observer) <= self.aoi_radius_sq
+            })
+            .cloned()
+            .collect();
+
+        let mut out = Vec::with_capacity(left.len() + entered.len());
+        for &region_id in &left {
+            out.push(NetMessage::RegionLeft { region_id });
+        }
+        for region in &entered {
+            out.push(NetMessage::RegionEntered {
+                region: region.clone(),
+                prims: self.prims_in(region.id),
+            });
+        }
+        if let Some(v) = self.views.get_mut(&avatar_id) {
+            for id in &left {
+                v.regions.remove(id);
+            }
+            v.regions.extend(entered.iter().map(|r| r.id));
+        }
+        out
+    }
+
+    /// Everything one session needs this tick: region enter/leave (ADR-012), prim changes in its
+    /// regions, chat lines it hears (ADR-018), profiles and appearances of avatars new to it
+    /// (ADR-019/020), then avatar add/update/remove against its last acknowledged tick
+    /// (ADR-010/011). A newly processed input is always echoed, with an empty `AvatarsUpdated` if
+    /// nothing else changed.
+    pub fn session_updates(&mut self, avatar_id: u64) -> Vec<NetMessage> {
+        let mut out = self.region_events_for(avatar_id);
+        // A region entered this tick already arrives with its current prims.
+        let entered: HashSet<i64> = out
+            .iter()
+            .filter_map(|m| match m {
+                NetMessage::RegionEntered { region, .. } => Some(region.id),
+                _ => None,
+            })
+            .collect();
+        if let Some(v) = self.views.get(&avatar_id) {
+            out.extend(
+                self.tick_prim_events
+                    .iter()
+                    .filter(|(id, _)| v.regions.contains(id) && !entered.contains(id))
+                    .map(|(_, m)| m.clone()),
+            );
+        }
+        out.extend(
+            self.tick_chat_events
+                .iter()
+                .filter(|(id, _)| *id == avatar_id)
+                .map(|(_, m)| m.clone()),
+        );
+        let avatars = self.visible_avatars(avatar_id);
+        out.extend(self.profile_updates(avatar_id, &avatars));
+        let tick = self.tick;
+        let input_seq = self.last_input_seq(avatar_id);
+        let Some(v) = self.views.get_mut(&avatar_id) else {
+            return out;
+        };
+        let deltas = v.avatars.diff(tick, input_seq, &avatars);
+        let echoed = deltas
+            .iter()
+            .any(|m| matches!(m, NetMessage::AvatarsUpdated { .. }));
+        out.extend(deltas);
+        if !echoed && input_seq != v.echoed_input_seq {
+            out.push(NetMessage::AvatarsUpdated {
+                tick,
+                updates: Vec::new(),
+                input_seq,
+            });
         }
+        v.echoed_input_seq = input_seq;
+        out
     }
 }
@@ -0,0 +1,74 @@
+//! Tick timing (ADR-014 operations): per-tick phase durations and a rolling summary that shows
+//! when the world has become too heavy for the configured `tick_hz`.
+
+use std::time::Duration;
+
+/// Wall time of each phase of one tick.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TickTiming {
+    /// `SimWorld::step` under the world lock.
+    pub step: Duration,
+    /// Per-session deltas (`SimWorld::session_updates`) under the world lock.
+    pub build: Duration,
+    /// Encoding every session's updates in its negotiated protocol version, after the lock.
+    pub encode: Duration,
+    /// Handing the encoded updates to the sessions.
+    pub fan_out: Duration,
+}
+
+impl TickTiming {
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.step + self.build + self.encode + self.fan_out
+    }
+}
+
+/// Aggregates since the last [`TickStats::take`]; logged periodically by the tick loop.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TickStats {
+    pub ticks: u64,
+    /// Ticks that were due but skipped because the previous one ran late.
+    pub overruns: u64,
+    /// Ticks whose work alone took longer than the tick period.
+    pub over_budget: u64,
+    /// Session updates that replaced an unsent earlier tick's (slow reader).
+    pub coalesced: u64,
+    /// Sessions disconnected for staying behind.
+    pub evicted: u64,
+    pub max: TickTiming,
+    sum: TickTiming,
+}
+
+impl TickStats {
+    pub fn record(&mut self, t: &TickTiming, period: Duration) {
+        self.ticks += 1;
+        if t.total() > period {
+            self.over_budget += 1;
+        }
+        self.sum.step += t.step;
+        self.sum.build += t.build;
+        self.sum.encode += t.encode;
+        self.sum.fan_out += t.fan_out;
+        self.max.step = self.max.step.max(t.step);
+        self.max.build = self.max.build.max(t.build);
+        self.max.encode = self.max.encode.max(t.encode);
+        self.max.fan_out = self.max.fan_out.max(t.fan_out);
+    }
+
+    /// Mean phase durations over the recorded ticks.
+    #[must_use]
+    pub fn mean(&self) -> TickTiming {
+        let n = u32::try_from(self.ticks.max(1)).unwrap_or(u32::MAX);
+        TickTiming {
+            step: self.sum.step / n,
+            build: self.sum.build / n,
+            encode: self.sum.encode / n,
+            fan_out: self.sum.fan_out / n,
+        }
+    }
+
+    /// Return the current window and start a new one.
+    pub fn take(&mut self) -> Self {
+        std::mem::take(self)
+    }
+}
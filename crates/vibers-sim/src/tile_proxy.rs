@@ -0,0 +1,406 @@
+//! Map tile proxy (ADR-021): an optional HTTP listener serving `GET /{z}/{x}/{y}.png` from a disk
+//! cache, fetching misses and stale tiles from `osm_tile_url_template`. The handshake then names
+//! the proxy instead of the upstream, so one sim with internet access feeds a whole LAN session and
+//! the upstream sees each tile once, at a bounded request rate.
+//!
+//! Tiles are kept at `DIR/{z}/{x}/{y}.png` by the tile cache the client uses too
+//! ([`vibe_tile_cache`], ADR-004).
+
+use anyhow::Context;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use vibe_tile_cache::{unix_now, CachedTile, TileDiskCache, TileValidity};
+use vibe_core::TileKey;
+
+use crate::config::SimConfig;
+
+/// Longest request head accepted; a tile request needs a few hundred bytes.
+const MAX_REQUEST_HEAD: usize = 8 * 1024;
+/// Time a client gets to send its request, and to take the response.
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(10);
+const UPSTREAM_TIMEOUT: Duration = Duration::from_secs(15);
+/// Longest a request waits for its turn upstream before it is answered 503.
+const GATE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Deepest zoom level served.
+const MAX_ZOOM: u32 = 22;
+
+enum Upstream {
+    Changed(CachedTile),
+    /// 304: the cached copy is current until `expires_at`.
+    Unchanged {
+        expires_at: i64,
+    },
+    /// 404: the upstream has no such tile.
+    Missing,
+}
+
+enum TileError {
+    /// Other requests kept the upstream busy past [`GATE_TIMEOUT`].
+    Busy,
+    Failed(String),
+}
+
+impl From<tokio::task::JoinError> for TileError {
+    fn from(e: tokio::task::JoinError) -> Self {
+        Self::Failed(e.to_string())
+    }
+}
+
+pub struct TileProxy {
+    upstream: String,
+    cache: TileDiskCache,
+    agent: ureq::Agent,
+    /// Earliest start of the next upstream request. Held for the whole fetch, so upstream
+    /// requests also run one at a time. Waited for on the runtime; only the upstream request
+    /// itself runs on a blocking thread.
+    next_upstream: Mutex<Instant>,
+    upstream_interval: Duration,
+}
+
+impl TileProxy {
+    pub fn open(config: &SimConfig) -> anyhow::Result<Self> {
+        let upstream = &config.osm_tile_url_template;
+        if !(upstream.starts_with("http://") || upstream.starts_with("https://")) {
+            anyhow::bail!(
+                "the tile proxy needs an http(s) osm_tile_url_template, not {upstream:?}"
+            );
+        }
+        if !config.tile_upstream_rps.is_finite() || config.tile_upstream_rps <= 0.0 {
+            anyhow::bail!("tile_upstream_rps must be positive");
+        }
+        let cache = TileDiskCache::open(
+            Path::new(&config.tile_cache_dir),
+            config.tile_cache_mb << 20,
+        )?;
+        Ok(Self {
+            upstream: upstream.clone(),
+            cache,
+            agent: ureq::AgentBuilder::new().timeout(UPSTREAM_TIMEOUT).build(),
+            next_upstream: Mutex::new(Instant::now()),
+            upstream_interval: Duration::from_secs_f32(1.0 / config.tile_upstream_rps),
+        })
+    }
+
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) {
+        loop {
+            let (stream, addr) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    tracing::warn!("tile proxy accept: {e}");
+                    continue;
+                }
+            };
+            let proxy = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = proxy.handle(stream).await {
+                    tracing::debug!(%addr, "tile request: {e:#}");
+                }
+            });
+        }
+    }
+
+    /// Answer one request, then close the connection.
+    async fn handle(self: Arc<Self>, mut stream: TcpStream) -> anyhow::Result<()> {
+        let head = tokio::time::timeout(CLIENT_TIMEOUT, read_head(&mut stream))
+            .await
+            .context("request timed out")??;
+        let response = match parse_request(&head) {
+            Err(status) => status_response(status),
+            Ok(request) => match self.tile(request.key.clone()).await {
+                Ok(Some(tile)) => tile_response(&tile, &request),
+                Ok(None) => status_response("404 Not Found"),
+                Err(TileError::Busy) => status_response("503 Service Unavailable"),
+                Err(TileError::Failed(e)) => {
+                    tracing::warn!("tile {}: {e}", request.key.to_path());
+                    status_response("502 Bad Gateway")
+                }
+            },
+        };
+        tokio::time::timeout(CLIENT_TIMEOUT, async {
+            stream.write_all(&response).await?;
+            stream.shutdown().await
+        })
+        .await
+        .context("response timed out")??;
+        Ok(())
+    }
+
+    /// The tile from the cache if fresh, else from upstream (conditionally, when a stale copy is
+    /// cached) once it is this request's turn. After [`GATE_TIMEOUT`] without a turn, a stale copy
+    /// is served or the request is refused as busy. `None` if the upstream has no such tile.
+    async fn tile(self: Arc<Self>, key: TileKey) -> Result<Option<CachedTile>, TileError> {
+        let proxy = self.clone();
+        let path = key.to_path();
+        let cached = tokio::task::spawn_blocking(move || proxy.cache.get(&path)).await?;
+        if cached.as_ref().is_some_and(CachedTile::is_fresh) {
+            return Ok(cached);
+        }
+        let gate = self.next_upstream.lock();
+        let Ok(mut next) = tokio::time::timeout(GATE_TIMEOUT, gate).await else {
+            return cached.map(Some).ok_or(TileError::Busy);
+        };
+        // Another request may have fetched it while we waited for the gate.
+        let proxy = self.clone();
+        let path = key.to_path();
+        let cached = tokio::task::spawn_blocking(move || proxy.cache.get(&path)).await?;
+        if cached.as_ref().is_some_and(CachedTile::is_fresh) {
+            return Ok(cached);
+        }
+        tokio::time::sleep_until((*next).into()).await;
+        let proxy = self.clone();
+        let fetch_key = key.clone();
+        let (fetched, cached) = tokio::task::spawn_blocking(move || {
+            let fetched = proxy.fetch_upstream(&fetch_key, cached.as_ref());
+            (fetched, cached)
+        })
+        .await?;
+        *next = Instant::now() + self.upstream_interval;
+        drop(next);
+        let proxy = self.clone();
+        tokio::task::spawn_blocking(move || proxy.settle(&key, fetched, cached))
+            .await?
+            .map_err(TileError::Failed)
+    }
+
+    /// Cache what the upstream answered, and pick what to serve: a stale copy also when the
+    /// upstream failed. Blocking.
+    fn settle(
+        &self,
+        key: &TileKey,
+        fetched: Result<Upstream, String>,
+        cached: Option<CachedTile>,
+    ) -> Result<Option<CachedTile>, String> {
+        let path = key.to_path();
+        match (fetched, cached) {
+            (Ok(Upstream::Changed(tile)), _) => {
+                if let Err(e) = self.cache.put(&path, &tile) {
+                    tracing::warn!("tile cache: {e:#}");
+                }
+                Ok(Some(tile))
+            }
+            (Ok(Upstream::Unchanged { expires_at }), Some(mut tile)) => {
+                tile.validity.expires_at = expires_at;
+                if let Err(e) = self.cache.refresh(&path, &tile.validity) {
+                    tracing::warn!("tile cache: {e:#}");
+                }
+                Ok(Some(tile))
+            }
+            (Ok(Upstream::Missing), _) => Ok(None),
+            (Err(e), Some(tile)) => {
+                tracing::debug!("tile {}: {e}; serving the stale copy", key.to_path());
+                Ok(Some(tile))
+            }
+            (Ok(Upstream::Unchanged { .. }), None) => {
+                Err("upstream answered 304 without a cached copy".into())
+            }
+            (Err(e), None) => Err(e),
+        }
+    }
+
+    /// One upstream request.
+    fn fetch_upstream(
+        &self,
+        key: &TileKey,
+        cached: Option<&CachedTile>,
+    ) -> Result<Upstream, String> {
+        let url = self
+            .upstream
+            .replace("{z}", &key.z.to_string())
+            .replace("{x}", &key.x.to_string())
+            .replace("{y}", &key.y.to_string());
+        let mut request = self.agent.get(&url);
+        if let Some(tile) = cached {
+            if let Some(etag) = &tile.validity.etag {
+                request = request.set("If-None-Match", etag);
+            }
+            if let Some(last_modified) = &tile.validity.last_modified {
+                request = request.set("If-Modified-Since", last_modified);
+            }
+        }
+        let response = match request.call() {
+            Ok(response) => response,
+            Err(ureq::Error::Status(404, _)) => return Ok(Upstream::Missing),
+            Err(e) => return Err(e.to_string()),
+        };
+        let validity = TileValidity::from_headers(
+            response.header("ETag"),
+            response.header("Last-Modified"),
+            response.header("Cache-Control"),
+        );
+        if response.status() == 304 {
+            let expires_at = validity.expires_at;
+            return Ok(Upstream::Unchanged { expires_at });
+        }
+        let content_type = response.content_type().to_owned();
+        if !content_type.starts_with("image/") {
+            return Err(format!("upstream sent {content_type}, not an image"));
+        }
+        let mut bytes = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut bytes)
+            .map_err(|e| format!("reading {url}: {e}"))?;
+        Ok(Upstream::Changed(CachedTile {
+            bytes,
+            content_type,
+            validity,
+        }))
+    }
+}
+
+struct TileRequest {
+    key: TileKey,
+    if_none_match: Option<String>,
+    if_modified_since: Option<String>,
+}
+
+async fn read_head(stream: &mut TcpStream) -> anyhow::Result<Vec<u8>> {
+    let mut head = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !head.windows(4).any(|w| w == b"\r\n\r\n") {
+        anyhow::ensure!(head.len() <= MAX_REQUEST_HEAD, "request head too long");
+        let n = stream.read(&mut buf).await?;
+        anyhow::ensure!(n > 0, "closed before the request ended");
+        head.extend_from_slice(&buf[..n]);
+    }
+    Ok(head)
+}
+
+/// `GET /{z}/{x}/{y}.png`; `Err` is the response status.
+fn parse_request(head: &[u8]) -> Result<TileRequest, &'static str> {
+    let head = std::str::from_utf8(head).map_err(|_| "400 Bad Request")?;
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(target)) = (request_line.next(), request_line.next()) else {
+        return Err("400 Bad Request");
+    };
+    if method != "GET" {
+        return Err("405 Method Not Allowed");
+    }
+    let path = target.split('?').next().unwrap_or_default();
+    let key = path
+        .strip_prefix('/')
+        .and_then(|p| p.strip_suffix(".png"))
+        .and_then(|p| {
+            let mut parts = p.split('/').map(str::parse::<i64>);
+            match (parts.next(), parts.next(), parts.next(), parts.next()) {
+                (Some(Ok(z)), Some(Ok(x)), Some(Ok(y)), None) => {
+                    let z = u32::try_from(z).ok().filter(|z| *z <= MAX_ZOOM)?;
+                    let size = 1i64 << z;
+                    ((0..size).contains(&x) && (0..size).contains(&y))
+                        .then(|| TileKey::new(x, y, z))
+                }
+                _ => None,
+            }
+        })
+        .ok_or("404 Not Found")?;
+    let mut request = TileRequest {
+        key,
+        if_none_match: None,
+        if_modified_since: None,
+    };
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = Some(value.trim().to_owned());
+        if name.eq_ignore_ascii_case("If-None-Match") {
+            request.if_none_match = value;
+        } else if name.eq_ignore_ascii_case("If-Modified-Since") {
+            request.if_modified_since = value;
+        }
+    }
+    Ok(request)
+}
+
+/// 200 with the tile, or 304 if the client's copy matches. The client may keep it for as long as
+/// the proxy's copy stays fresh.
+fn tile_response(tile: &CachedTile, request: &TileRequest) -> Vec<u8> {
+    let validity = &tile.validity;
+    let unchanged = match (&request.if_none_match, &validity.etag) {
+        (Some(theirs), Some(ours)) => theirs == ours,
+        (None, _) => {
+            request.if_modified_since.is_some()
+                && request.if_modified_since == validity.last_modified
+        }
+        _ => false,
+    };
+    let mut head = format!(
+        "Cache-Control: max-age={}\r\nConnection: close\r\n",
+        (validity.expires_at - unix_now()).max(0)
+    );
+    if let Some(etag) = &validity.etag {
+        head += &format!("ETag: {etag}\r\n");
+    }
+    if let Some(last_modified) = &validity.last_modified {
+        head += &format!("Last-Modified: {last_modified}\r\n");
+    }
+    if unchanged {
+        return format!("HTTP/1.1 304 Not Modified\r\n{head}\r\n").into_bytes();
+    }
+    let mut response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\n{head}\r\n",
+        tile.content_type,
+        tile.bytes.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(&tile.bytes);
+    response
+}
+
+fn status_response(status: &str) -> Vec<u8> {
+    format!("HTTP/1.1 {status}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n").into_bytes()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(target: &str) -> Result<TileKey, &'static str> {
+        let head = format!("GET {target} HTTP/1.1\r\nHost: sim\r\n\r\n");
+        parse_request(head.as_bytes()).map(|request| request.key)
+    }
+
+    #[test]
+    fn parses_a_tile_path() {
+        assert_eq!(
+            parse("/17/67926/42563.png"),
+            Ok(TileKey::new(67926, 42563, 17))
+        );
+        assert_eq!(parse("/0/0/0.png?v=2"), Ok(TileKey::new(0, 0, 0)));
+        let head = b"GET /1/1/0.png HTTP/1.1\r\nif-none-match: \"abc\"\r\n\r\n";
+        let request = parse_request(head).ok().unwrap();
+        assert_eq!(request.if_none_match.as_deref(), Some("\"abc\""));
+    }
+
+    #[test]
+    fn out_of_range_tiles_are_not_found() {
+        for target in [
+            "/23/0/0.png",
+            "/-1/0/0.png",
+            "/1/2/0.png",
+            "/1/0/2.png",
+            "/2/-1/0.png",
+            "/2/0/-1.png",
+            "/99999999999/0/0.png",
+            "/1/0.png",
+            "/1/0/0/0.png",
+            "/1/0/0.jpg",
+        ] {
+            assert_eq!(parse(target), Err("404 Not Found"), "{target}");
+        }
+        assert!(parse("/22/4194303/4194303.png").is_ok());
+    }
+
+    #[test]
+    fn only_get_is_allowed() {
+        let head = b"POST /1/0/0.png HTTP/1.1\r\n\r\n";
+        assert_eq!(parse_request(head).err(), Some("405 Method Not Allowed"));
+        assert_eq!(parse_request(b"\r\n\r\n").err(), Some("400 Bad Request"));
+    }
+}